@@ -1,6 +1,11 @@
+mod grammar;
+pub use grammar::*;
+mod help;
+pub use help::*;
+
 use std::collections::HashSet;
 use std::env;
-use std::iter::Peekable;
+use std::iter::{Enumerate, Peekable};
 use std::path::PathBuf;
 
 pub fn parse_arguments() -> Result<Arguments, ParseError> {
@@ -17,13 +22,37 @@ pub struct Arguments {
 pub enum ProgramOption {
     Help,
     Path(PathBuf),
+    GitDir(PathBuf),
+    Recursive(PathBuf),
+    Workspace(PathBuf),
+    IncludeNested,
+    Submodules,
+    Jobs(usize),
     FetchPrune,
+    Remote(String),
+    AllRemotes,
+    Verbose,
+    Trace,
+    Timeout(u64),
 }
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum ProgramCommand {
-    List(ListOption),
-    Clean(CleanOption),
+    List(ListArguments),
+    Clean(CleanArguments),
+    Stats(StatsOption),
+    Diff(DiffOption),
+    PruneRemote(PruneRemoteArguments),
+    Completions(CompletionsOption),
+    Man(ManOption),
+    Complete(CompleteOption),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ListArguments {
+    pub option: ListOption,
+    pub format: ListFormat,
+    pub output: Option<PathBuf>,
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -34,6 +63,29 @@ pub enum ListOption {
     Tracked,
     Gone,
     Diverged,
+    // Orphaned remote-tracking refs, with the ones no local branch tracks only if untracked is set
+    RemoteRefs { untracked: bool },
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ListFormat {
+    Text,
+    Porcelain,
+    PorcelainNul,
+    Tree { depth: Option<usize> },
+    Markdown,
+    Csv,
+    Json,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct CleanArguments {
+    pub option: CleanOption,
+    pub switch_to: Option<String>,
+    // Delete orphaned remote-tracking refs instead of gone branches
+    pub remote_refs: bool,
+    // With remote_refs, also delete the ones no local branch tracks
+    pub untracked: bool,
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -43,19 +95,111 @@ pub enum CleanOption {
     Automatic,
 }
 
+#[derive(Debug, PartialEq, Eq)]
+pub struct PruneRemoteArguments {
+    pub option: PruneRemoteOption,
+    pub remote: String,
+    pub base: Option<String>,
+    pub older_than: u64,
+    pub protected: Vec<String>,
+    pub batch_size: usize,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum PruneRemoteOption {
+    Help,
+    Confirm,
+    Automatic,
+    DryRun,
+}
+
+pub const DEFAULT_OLDER_THAN_DAYS: u64 = 30;
+pub const DEFAULT_BATCH_SIZE: usize = 50;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum StatsOption {
+    Help,
+    Text,
+    Json,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum DiffOption {
+    Help,
+    Show,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum CompletionsOption {
+    Help,
+    Bash,
+    Zsh,
+    Fish,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ManOption {
+    Help,
+    Print,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum CompleteOption {
+    Branches,
+    Remotes,
+}
+
 #[derive(thiserror::Error, Debug, PartialEq, Eq)]
 pub enum ParseError {
-    #[error("Error parsing option --path")]
-    OptionPath,
+    #[error("Unknown option {token} at position {position}{}", did_you_mean(.suggestion))]
+    UnknownOption {
+        token: String,
+        position: usize,
+        suggestion: Option<&'static str>,
+    },
 
-    #[error("Command not found")]
-    Command,
+    #[error("Unknown command {token} at position {position}{}", did_you_mean(.suggestion))]
+    Command {
+        token: String,
+        position: usize,
+        suggestion: Option<&'static str>,
+    },
 
-    #[error("Error parsing list options")]
-    ListOption,
+    #[error("Unexpected argument {token} at position {position}")]
+    UnexpectedArgument { token: String, position: usize },
 
-    #[error("Error parsing clean options")]
-    CleanOption,
+    #[error("Missing argument <{name}> for command {token} at position {position}")]
+    MissingArgument {
+        name: &'static str,
+        token: String,
+        position: usize,
+    },
+
+    #[error("Missing value for option {token} at position {position}")]
+    MissingValue { token: String, position: usize },
+
+    #[error("Option {token} at position {position} doesn't take a value")]
+    UnexpectedValue { token: String, position: usize },
+
+    #[error("Invalid value {token} for option {option} at position {position}")]
+    InvalidValue {
+        option: &'static str,
+        token: String,
+        position: usize,
+    },
+}
+
+fn did_you_mean(suggestion: &Option<&str>) -> String {
+    match suggestion {
+        Some(suggestion) => format!(", did you mean {suggestion}?"),
+        None => String::new(),
+    }
+}
+
+// An option found in the command line, along with its value (if the option takes one)
+struct ParsedOption {
+    spec: &'static OptionSpec,
+    value: Option<String>,
 }
 
 struct ArgumentsParser<I, S>
@@ -63,7 +207,7 @@ where
     I: Iterator<Item = S>,
     S: AsRef<str>,
 {
-    pub token_source: Peekable<I>,
+    pub token_source: Peekable<Enumerate<I>>,
 }
 
 impl<I, S> ArgumentsParser<I, S>
@@ -73,117 +217,475 @@ where
 {
     fn new(iterator: I) -> Self {
         ArgumentsParser {
-            token_source: iterator.peekable(),
+            token_source: iterator.enumerate().peekable(),
         }
     }
 
+    /*
+        Global options may appear anywhere, before or after the command
+        Options of the command are looked up first, so that "list --help" prints the help of list
+        After a -- separator every token is treated as a positional argument
+    */
     fn parse(&mut self) -> Result<Arguments, ParseError> {
         let mut options = HashSet::<ProgramOption>::new();
+        let mut command: Option<(&'static CommandSpec, usize)> = None;
+        let mut command_options = Vec::new();
+        let mut positional = None;
+        let mut only_positionals = false;
 
-        let mut parsed_program_option = self.parse_program_option()?;
+        while let Some((index, token)) = self.token_source.next() {
+            let token = token.as_ref();
+            let position = index + 1;
 
-        while let Some(program_option) = parsed_program_option {
-            options.insert(program_option);
-            parsed_program_option = self.parse_program_option()?;
-        }
+            if !only_positionals && token == "--" {
+                only_positionals = true;
+                continue;
+            }
 
-        let command = self.parse_command()?;
+            if !only_positionals && token.starts_with('-') && token != "-" {
+                let local_options = command.map_or(&[][..], |(command, _)| command.options);
 
-        Ok(Arguments { options, command })
-    }
+                let (name, inline_value) = match token.split_once('=') {
+                    Some((name, value)) if name.starts_with("--") => (name, Some(value)),
+                    _ => (token, None),
+                };
 
-    fn parse_program_option(&mut self) -> Result<Option<ProgramOption>, ParseError> {
-        let token = match self.token_source.peek() {
-            Some(token) => token,
-            None => return Ok(None),
-        };
+                if let Some(spec) = find_option(local_options, name) {
+                    command_options.push(self.parse_option(spec, inline_value, position)?);
+                } else if let Some(spec) = find_option(GLOBAL_OPTIONS, name) {
+                    let parsed = self.parse_option(spec, inline_value, position)?;
+                    insert_program_option(&mut options, parsed);
+                } else {
+                    let candidates = local_options.iter().chain(GLOBAL_OPTIONS);
+                    return Err(ParseError::UnknownOption {
+                        token: token.to_string(),
+                        position,
+                        suggestion: suggest(name, candidates.map(|option| option.name)),
+                    });
+                }
 
-        match token.as_ref() {
-            "--help" => {
-                let _ = self.token_source.next();
-                Ok(Some(ProgramOption::Help))
-            }
-            "--path" => {
-                let _ = self.token_source.next();
-                let pathbuf = self.parse_validate_path_option()?;
-                Ok(Some(ProgramOption::Path(pathbuf)))
+                continue;
             }
-            "--fetch-prune" => {
-                let _ = self.token_source.next();
-                Ok(Some(ProgramOption::FetchPrune))
+
+            match command {
+                Some((
+                    CommandSpec {
+                        name,
+                        positional: Some(value_spec),
+                        ..
+                    },
+                    _,
+                )) if positional.is_none() => {
+                    if !validate_value(&value_spec.kind, token) {
+                        return Err(ParseError::InvalidValue {
+                            option: name,
+                            token: token.to_string(),
+                            position,
+                        });
+                    }
+
+                    positional = Some(token.to_string());
+                }
+                Some(_) => {
+                    return Err(ParseError::UnexpectedArgument {
+                        token: token.to_string(),
+                        position,
+                    })
+                }
+                None => {
+                    let spec = match find_command(token) {
+                        Some(spec) => spec,
+                        None => {
+                            let candidates = visible_commands().map(|command| command.name);
+                            return Err(ParseError::Command {
+                                token: token.to_string(),
+                                position,
+                                suggestion: suggest(token, candidates),
+                            });
+                        }
+                    };
+
+                    command = Some((spec, position));
+                }
             }
-            _ => Ok(None),
         }
+
+        let command = match command {
+            Some((spec, position)) => {
+                check_positional(spec, position, &positional, &command_options)?;
+                Some(build_command(spec, command_options, positional))
+            }
+            None => None,
+        };
+
+        Ok(Arguments { options, command })
     }
 
-    fn parse_validate_path_option(&mut self) -> Result<PathBuf, ParseError> {
-        let token_path = match self.token_source.next() {
-            Some(token_path) => token_path,
-            None => return Err(ParseError::OptionPath),
+    fn parse_option(
+        &mut self,
+        spec: &'static OptionSpec,
+        inline_value: Option<&str>,
+        position: usize,
+    ) -> Result<ParsedOption, ParseError> {
+        let value_spec = match (&spec.value, inline_value) {
+            (None, None) => return Ok(ParsedOption { spec, value: None }),
+            (None, Some(_)) => {
+                return Err(ParseError::UnexpectedValue {
+                    token: spec.name.to_string(),
+                    position,
+                })
+            }
+            (Some(value_spec), _) => value_spec,
         };
 
-        let path = match PathBuf::try_from(token_path.as_ref()) {
-            Ok(path) => path,
-            Err(_) => return Err(ParseError::OptionPath),
+        let (value, value_position) = match inline_value {
+            Some(value) => (value.to_string(), position),
+            None => match self.token_source.next() {
+                Some((index, token)) => (token.as_ref().to_string(), index + 1),
+                None => {
+                    return Err(ParseError::MissingValue {
+                        token: spec.name.to_string(),
+                        position,
+                    })
+                }
+            },
         };
 
+        if !validate_value(&value_spec.kind, &value) {
+            return Err(ParseError::InvalidValue {
+                option: spec.name,
+                token: value,
+                position: value_position,
+            });
+        }
+
+        Ok(ParsedOption {
+            spec,
+            value: Some(value),
+        })
+    }
+}
+
+fn validate_value(kind: &ValueKind, value: &str) -> bool {
+    match kind {
         /*
            Check if path exists
            This check prevents the code from wrongly interpreting other tokens as valid paths
            It also ensures that we can give the user a more meaningful error message instead of
            letting the code fail in other layers, which may lead to criptic error messages
         */
-        match path.try_exists() {
-            Ok(exists) if exists => Ok(path),
-            _ => Err(ParseError::OptionPath),
+        ValueKind::ExistingPath => matches!(PathBuf::from(value).try_exists(), Ok(true)),
+        ValueKind::File => !value.is_empty() && !value.starts_with("--"),
+        ValueKind::Number => value.parse::<usize>().is_ok(),
+        // Git doesn't allow branch names starting with -, so it is most likely a misplaced option
+        ValueKind::Branch => !value.is_empty() && !value.starts_with('-'),
+        ValueKind::Remote => !value.is_empty() && !value.starts_with('-'),
+        ValueKind::OneOf(values) => values.contains(&value),
+    }
+}
+
+// A positional argument is required, unless the user only wants to read the help of the command
+fn check_positional(
+    spec: &CommandSpec,
+    position: usize,
+    positional: &Option<String>,
+    options: &[ParsedOption],
+) -> Result<(), ParseError> {
+    let asks_help = options.iter().any(|parsed| parsed.spec.name == "--help");
+
+    match (&spec.positional, positional) {
+        (Some(value_spec), None) if !asks_help => Err(ParseError::MissingArgument {
+            name: value_spec.name,
+            token: spec.name.to_string(),
+            position,
+        }),
+        _ => Ok(()),
+    }
+}
+
+fn insert_program_option(options: &mut HashSet<ProgramOption>, parsed: ParsedOption) {
+    let option = match (parsed.spec.name, parsed.value) {
+        ("--path", Some(path)) => {
+            // Like git -C, the last path wins
+            options.retain(|option| !matches!(option, ProgramOption::Path(_)));
+            ProgramOption::Path(PathBuf::from(path))
+        }
+        ("--git-dir", Some(git_dir)) => {
+            options.retain(|option| !matches!(option, ProgramOption::GitDir(_)));
+            ProgramOption::GitDir(PathBuf::from(git_dir))
         }
+        ("--recursive", Some(directory)) => {
+            options.retain(|option| !matches!(option, ProgramOption::Recursive(_)));
+            ProgramOption::Recursive(PathBuf::from(directory))
+        }
+        ("--workspace", Some(file)) => {
+            options.retain(|option| !matches!(option, ProgramOption::Workspace(_)));
+            ProgramOption::Workspace(PathBuf::from(file))
+        }
+        ("--include-nested", _) => ProgramOption::IncludeNested,
+        ("--submodules", _) => ProgramOption::Submodules,
+        // SAFETY: The parser already checked that the value is a number
+        ("--jobs", Some(jobs)) => {
+            options.retain(|option| !matches!(option, ProgramOption::Jobs(_)));
+            ProgramOption::Jobs(jobs.parse().unwrap())
+        }
+        ("--fetch-prune", _) => ProgramOption::FetchPrune,
+        // Unlike the other options, every remote given is kept
+        ("--remote", Some(remote)) => ProgramOption::Remote(remote),
+        ("--all-remotes", _) => ProgramOption::AllRemotes,
+        // SAFETY: The parser already checked that the value is a number
+        ("--timeout", Some(seconds)) => {
+            options.retain(|option| !matches!(option, ProgramOption::Timeout(_)));
+            ProgramOption::Timeout(seconds.parse().unwrap())
+        }
+        ("--verbose", _) => ProgramOption::Verbose,
+        ("--trace", _) => ProgramOption::Trace,
+        ("--help", _) => ProgramOption::Help,
+        (name, _) => {
+            unreachable!("Program option {name} is declared in the grammar but never built")
+        }
+    };
+
+    options.insert(option);
+}
+
+// SAFETY: The unwraps below are for options the grammar declares as taking a value, which the parser enforces
+fn build_command(
+    spec: &CommandSpec,
+    options: Vec<ParsedOption>,
+    positional: Option<String>,
+) -> ProgramCommand {
+    match spec.name {
+        "list" => ProgramCommand::List(build_list_arguments(options)),
+        "clean" => ProgramCommand::Clean(build_clean_arguments(options)),
+        "completions" => ProgramCommand::Completions(build_completions_option(options, positional)),
+        "diff" => ProgramCommand::Diff(build_diff_option(options)),
+        "prune-remote" => {
+            ProgramCommand::PruneRemote(build_prune_remote_arguments(options, positional))
+        }
+        "man" => ProgramCommand::Man(build_man_option(options)),
+        "__complete" => ProgramCommand::Complete(build_complete_option(positional)),
+        "stats" => ProgramCommand::Stats(build_stats_option(options)),
+        name => unreachable!("Command {name} is declared in the grammar but never built"),
     }
+}
 
-    fn parse_command(&mut self) -> Result<Option<ProgramCommand>, ParseError> {
-        let token = match self.token_source.next() {
-            Some(token) => token,
-            None => return Ok(None),
-        };
+fn build_list_arguments(options: Vec<ParsedOption>) -> ListArguments {
+    let mut option = None;
+    let mut format = ListFormat::Text;
+    let mut output = None;
+    let mut untracked = false;
 
-        let command = match token.as_ref() {
-            "list" => ProgramCommand::List(self.parse_list_options()?),
-            "clean" => ProgramCommand::Clean(self.parse_clean_options()?),
-            _ => return Err(ParseError::Command),
+    for parsed in options {
+        let parsed_option = match parsed.spec.name {
+            // Like --tree keeps the --depth given before it, --porcelain keeps -z
+            "--porcelain" => {
+                if format != ListFormat::PorcelainNul {
+                    format = ListFormat::Porcelain;
+                }
+                continue;
+            }
+            "-z" => {
+                format = ListFormat::PorcelainNul;
+                continue;
+            }
+            "--tree" => {
+                if !matches!(format, ListFormat::Tree { .. }) {
+                    format = ListFormat::Tree { depth: None };
+                }
+                continue;
+            }
+            "--depth" => {
+                let depth = parsed.value.unwrap().parse().unwrap();
+                format = ListFormat::Tree { depth: Some(depth) };
+                continue;
+            }
+            "--format" => {
+                format = match parsed.value.unwrap().as_str() {
+                    "porcelain" => ListFormat::Porcelain,
+                    "markdown" => ListFormat::Markdown,
+                    "csv" => ListFormat::Csv,
+                    "json" => ListFormat::Json,
+                    _ => ListFormat::Text,
+                };
+                continue;
+            }
+            "--output" => {
+                output = Some(PathBuf::from(parsed.value.unwrap()));
+                continue;
+            }
+            "--untracked" => {
+                untracked = true;
+                continue;
+            }
+            "--help" => ListOption::Help,
+            "--all" => ListOption::All,
+            "--tracked" => ListOption::Tracked,
+            "--local" => ListOption::Local,
+            "--diverged" => ListOption::Diverged,
+            "--remote-refs" => ListOption::RemoteRefs { untracked: false },
+            _ => ListOption::Gone,
         };
 
-        Ok(Some(command))
+        // Like formats, the last filter wins, except --help, which wins over any other option
+        if option != Some(ListOption::Help) {
+            option = Some(parsed_option);
+        }
     }
 
-    fn parse_list_options(&mut self) -> Result<ListOption, ParseError> {
-        let token = match self.token_source.next() {
-            Some(token) => token,
-            None => return Ok(ListOption::Gone),
+    let option = match option {
+        Some(ListOption::RemoteRefs { .. }) => ListOption::RemoteRefs { untracked },
+        option => option.unwrap_or(ListOption::Gone),
+    };
+
+    ListArguments {
+        option,
+        format,
+        output,
+    }
+}
+
+fn build_clean_arguments(options: Vec<ParsedOption>) -> CleanArguments {
+    let mut option = None;
+    let mut switch_to = None;
+    let mut remote_refs = false;
+    let mut untracked = false;
+
+    for parsed in options {
+        let parsed_option = match parsed.spec.name {
+            "--switch-to" => {
+                switch_to = parsed.value;
+                continue;
+            }
+            "--remote-refs" => {
+                remote_refs = true;
+                continue;
+            }
+            "--untracked" => {
+                untracked = true;
+                continue;
+            }
+            "--help" => CleanOption::Help,
+            "--automatic" => CleanOption::Automatic,
+            _ => CleanOption::Step,
         };
 
-        match token.as_ref() {
-            "--help" => Ok(ListOption::Help),
-            "--all" => Ok(ListOption::All),
-            "--tracked" => Ok(ListOption::Tracked),
-            "--local" => Ok(ListOption::Local),
-            "--gone" => Ok(ListOption::Gone),
-            "--diverged" => Ok(ListOption::Diverged),
-            _ => Err(ParseError::ListOption),
+        // The last option wins, except --help, which wins over any other option
+        if option != Some(CleanOption::Help) {
+            option = Some(parsed_option);
         }
     }
 
-    fn parse_clean_options(&mut self) -> Result<CleanOption, ParseError> {
-        let token = match self.token_source.next() {
-            Some(token) => token,
-            None => return Ok(CleanOption::Step),
+    CleanArguments {
+        option: option.unwrap_or(CleanOption::Step),
+        switch_to,
+        remote_refs,
+        untracked,
+    }
+}
+
+fn build_prune_remote_arguments(
+    options: Vec<ParsedOption>,
+    positional: Option<String>,
+) -> PruneRemoteArguments {
+    let mut option = None;
+    let mut base = None;
+    let mut older_than = DEFAULT_OLDER_THAN_DAYS;
+    let mut protected = Vec::new();
+    let mut batch_size = DEFAULT_BATCH_SIZE;
+
+    for parsed in options {
+        let parsed_option = match parsed.spec.name {
+            "--base" => {
+                base = parsed.value;
+                continue;
+            }
+            "--older-than" => {
+                older_than = parsed.value.unwrap().parse().unwrap();
+                continue;
+            }
+            // Every pattern is kept, like --remote
+            "--protect" => {
+                protected.extend(parsed.value);
+                continue;
+            }
+            "--batch-size" => {
+                batch_size = parsed.value.unwrap().parse().unwrap();
+                continue;
+            }
+            "--help" => PruneRemoteOption::Help,
+            "--automatic" => PruneRemoteOption::Automatic,
+            "--dry-run" => PruneRemoteOption::DryRun,
+            _ => PruneRemoteOption::Confirm,
         };
 
-        match token.as_ref() {
-            "--help" => Ok(CleanOption::Help),
-            "--step" => Ok(CleanOption::Step),
-            "--automatic" => Ok(CleanOption::Automatic),
-            _ => Err(ParseError::CleanOption),
+        // The last option wins, except --help, which wins over any other option
+        if option != Some(PruneRemoteOption::Help) {
+            option = Some(parsed_option);
         }
     }
+
+    PruneRemoteArguments {
+        option: option.unwrap_or(PruneRemoteOption::Confirm),
+        // Only missing when asking for help
+        remote: positional.unwrap_or_default(),
+        base,
+        older_than,
+        protected,
+        batch_size,
+    }
+}
+
+fn build_completions_option(
+    options: Vec<ParsedOption>,
+    positional: Option<String>,
+) -> CompletionsOption {
+    if !options.is_empty() {
+        return CompletionsOption::Help;
+    }
+
+    match positional.as_deref() {
+        Some("bash") => CompletionsOption::Bash,
+        Some("zsh") => CompletionsOption::Zsh,
+        Some("fish") => CompletionsOption::Fish,
+        _ => CompletionsOption::Help,
+    }
+}
+
+fn build_diff_option(options: Vec<ParsedOption>) -> DiffOption {
+    if options.is_empty() {
+        DiffOption::Show
+    } else {
+        DiffOption::Help
+    }
+}
+
+fn build_man_option(options: Vec<ParsedOption>) -> ManOption {
+    if options.is_empty() {
+        ManOption::Print
+    } else {
+        ManOption::Help
+    }
+}
+
+// The parser already checked that the positional is one of the dynamic values
+fn build_complete_option(positional: Option<String>) -> CompleteOption {
+    match positional.as_deref() {
+        Some("remotes") => CompleteOption::Remotes,
+        _ => CompleteOption::Branches,
+    }
+}
+
+fn build_stats_option(options: Vec<ParsedOption>) -> StatsOption {
+    // The last option wins, except --help, which wins over any other option
+    if options.iter().any(|parsed| parsed.spec.name == "--help") {
+        return StatsOption::Help;
+    }
+
+    match options.last().map(|parsed| parsed.spec.name) {
+        Some("--json") => StatsOption::Json,
+        _ => StatsOption::Text,
+    }
 }
 
 #[test]
@@ -207,7 +709,11 @@ fn command_without_option() {
 
     let expected = Arguments {
         options: HashSet::new(),
-        command: Some(ProgramCommand::List(ListOption::Gone)),
+        command: Some(ProgramCommand::List(ListArguments {
+            option: ListOption::Gone,
+            format: ListFormat::Text,
+            output: None,
+        })),
     };
 
     assert_eq!(sut, expected);
@@ -219,7 +725,11 @@ fn command_with_option() {
 
     let expected = Arguments {
         options: HashSet::new(),
-        command: Some(ProgramCommand::List(ListOption::All)),
+        command: Some(ProgramCommand::List(ListArguments {
+            option: ListOption::All,
+            format: ListFormat::Text,
+            output: None,
+        })),
     };
 
     assert_eq!(sut, expected);
@@ -231,7 +741,11 @@ fn multiple_options_for_command() {
 
     let expected = Arguments {
         options: HashSet::new(),
-        command: Some(ProgramCommand::List(ListOption::All)),
+        command: Some(ProgramCommand::List(ListArguments {
+            option: ListOption::Gone,
+            format: ListFormat::Text,
+            output: None,
+        })),
     };
 
     assert_eq!(sut, expected);
@@ -247,17 +761,228 @@ fn multiple_options_and_command_with_option() {
 
     let expected = Arguments {
         options,
-        command: Some(ProgramCommand::Clean(CleanOption::Automatic)),
+        command: Some(ProgramCommand::Clean(CleanArguments {
+            option: CleanOption::Automatic,
+            switch_to: None,
+            remote_refs: false,
+            untracked: false,
+        })),
+    };
+
+    assert_eq!(sut, expected);
+}
+
+#[test]
+fn last_format_and_filter_win() {
+    let list = |tokens: &[&str]| match parse_in_test(tokens).unwrap().command {
+        Some(ProgramCommand::List(arguments)) => (arguments.option, arguments.format),
+        command => panic!("Not a list command: {command:?}"),
+    };
+
+    assert_eq!(
+        list(&["list", "--porcelain", "--tree"]),
+        (ListOption::Gone, ListFormat::Tree { depth: None })
+    );
+    assert_eq!(
+        list(&["list", "--tree", "--porcelain"]),
+        (ListOption::Gone, ListFormat::Porcelain)
+    );
+    assert_eq!(
+        list(&["list", "--format=csv", "--depth=1", "--format=json"]),
+        (ListOption::Gone, ListFormat::Json)
+    );
+    assert_eq!(
+        list(&["list", "-z", "--porcelain", "--local"]),
+        (ListOption::Local, ListFormat::PorcelainNul)
+    );
+    assert_eq!(
+        list(&["list", "--help", "--all"]),
+        (ListOption::Help, ListFormat::Text)
+    );
+
+    let sut = parse_in_test(&["clean", "--automatic", "--step"]).unwrap();
+    assert!(matches!(
+        sut.command,
+        Some(ProgramCommand::Clean(CleanArguments {
+            option: CleanOption::Step,
+            ..
+        }))
+    ));
+
+    let sut = parse_in_test(&["stats", "--json", "--text"]).unwrap();
+    assert_eq!(sut.command, Some(ProgramCommand::Stats(StatsOption::Text)));
+}
+
+#[test]
+fn porcelain_with_filter() {
+    let sut = parse_in_test(&["list", "--porcelain", "--gone"]).unwrap();
+
+    let expected = Arguments {
+        options: HashSet::new(),
+        command: Some(ProgramCommand::List(ListArguments {
+            option: ListOption::Gone,
+            format: ListFormat::Porcelain,
+            output: None,
+        })),
+    };
+
+    assert_eq!(sut, expected);
+}
+
+#[test]
+fn porcelain_nul_terminated() {
+    let sut = parse_in_test(&["list", "--all", "--porcelain", "-z"]).unwrap();
+
+    let expected = Arguments {
+        options: HashSet::new(),
+        command: Some(ProgramCommand::List(ListArguments {
+            option: ListOption::All,
+            format: ListFormat::PorcelainNul,
+            output: None,
+        })),
+    };
+
+    assert_eq!(sut, expected);
+}
+
+#[test]
+fn tree_with_depth() {
+    let sut = parse_in_test(&["list", "--tree", "--all", "--depth", "2"]).unwrap();
+
+    let expected = Arguments {
+        options: HashSet::new(),
+        command: Some(ProgramCommand::List(ListArguments {
+            option: ListOption::All,
+            format: ListFormat::Tree { depth: Some(2) },
+            output: None,
+        })),
+    };
+
+    assert_eq!(sut, expected);
+}
+
+#[test]
+fn tree_invalid_depth() {
+    let sut = parse_in_test(&["list", "--depth", "--all"]).err().unwrap();
+
+    let expected = ParseError::InvalidValue {
+        option: "--depth",
+        token: "--all".to_string(),
+        position: 3,
+    };
+
+    assert_eq!(sut, expected)
+}
+
+#[test]
+fn markdown_to_file() {
+    let sut = parse_in_test(&["list", "--format", "markdown", "--output", "report.md"]).unwrap();
+
+    let expected = Arguments {
+        options: HashSet::new(),
+        command: Some(ProgramCommand::List(ListArguments {
+            option: ListOption::Gone,
+            format: ListFormat::Markdown,
+            output: Some(PathBuf::from("report.md")),
+        })),
+    };
+
+    assert_eq!(sut, expected);
+}
+
+#[test]
+fn unknown_format() {
+    let sut = parse_in_test(&["list", "--format", "xml"]).err().unwrap();
+
+    let expected = ParseError::InvalidValue {
+        option: "--format",
+        token: "xml".to_string(),
+        position: 3,
+    };
+
+    assert_eq!(sut, expected)
+}
+
+#[test]
+fn list_unknown_option() {
+    let sut = parse_in_test(&["list", "--porcelain", "--unknown"])
+        .err()
+        .unwrap();
+
+    let expected = ParseError::UnknownOption {
+        token: "--unknown".to_string(),
+        position: 3,
+        suggestion: None,
+    };
+
+    assert_eq!(sut, expected)
+}
+
+#[test]
+fn stats_json() {
+    let sut = parse_in_test(&["stats", "--json"]).unwrap();
+
+    let expected = Arguments {
+        options: HashSet::new(),
+        command: Some(ProgramCommand::Stats(StatsOption::Json)),
     };
 
     assert_eq!(sut, expected);
 }
 
+#[test]
+fn diff_help() {
+    let sut = parse_in_test(&["diff", "--help"]).unwrap();
+
+    let expected = Arguments {
+        options: HashSet::new(),
+        command: Some(ProgramCommand::Diff(DiffOption::Help)),
+    };
+
+    assert_eq!(sut, expected);
+}
+
+#[test]
+fn clean_switch_to() {
+    let sut = parse_in_test(&["clean", "--switch-to", "develop", "--automatic"]).unwrap();
+
+    let expected = Arguments {
+        options: HashSet::new(),
+        command: Some(ProgramCommand::Clean(CleanArguments {
+            option: CleanOption::Automatic,
+            switch_to: Some("develop".to_string()),
+            remote_refs: false,
+            untracked: false,
+        })),
+    };
+
+    assert_eq!(sut, expected);
+}
+
+#[test]
+fn clean_switch_to_missing() {
+    let sut = parse_in_test(&["clean", "--switch-to", "--automatic"])
+        .err()
+        .unwrap();
+
+    let expected = ParseError::InvalidValue {
+        option: "--switch-to",
+        token: "--automatic".to_string(),
+        position: 3,
+    };
+
+    assert_eq!(sut, expected)
+}
+
 #[test]
 fn path_missing() {
     let sut = parse_in_test(&["--path", "clean"]).err().unwrap();
 
-    let expected = ParseError::OptionPath;
+    let expected = ParseError::InvalidValue {
+        option: "--path",
+        token: "clean".to_string(),
+        position: 2,
+    };
 
     assert_eq!(sut, expected)
 }
@@ -268,7 +993,11 @@ fn path_missing_2() {
         .err()
         .unwrap();
 
-    let expected = ParseError::OptionPath;
+    let expected = ParseError::InvalidValue {
+        option: "--path",
+        token: "--fetch--prune".to_string(),
+        position: 2,
+    };
 
     assert_eq!(sut, expected)
 }
@@ -277,7 +1006,10 @@ fn path_missing_2() {
 fn path_not_provided() {
     let sut = parse_in_test(&["--path"]).err().unwrap();
 
-    let expected = ParseError::OptionPath;
+    let expected = ParseError::MissingValue {
+        token: "--path".to_string(),
+        position: 1,
+    };
 
     assert_eq!(sut, expected)
 }
@@ -288,7 +1020,446 @@ fn path_doesnt_exist() {
         .err()
         .unwrap();
 
-    let expected = ParseError::OptionPath;
+    let expected = ParseError::InvalidValue {
+        option: "--path",
+        token: "/something/not/exist".to_string(),
+        position: 2,
+    };
+
+    assert_eq!(sut, expected)
+}
+
+#[test]
+fn global_option_after_command() {
+    let sut = parse_in_test(&["list", "--all", "--fetch-prune", "--path", "/"]).unwrap();
+
+    let mut options = HashSet::new();
+    options.insert(ProgramOption::FetchPrune);
+    options.insert(ProgramOption::Path(PathBuf::from("/")));
+
+    let expected = Arguments {
+        options,
+        command: Some(ProgramCommand::List(ListArguments {
+            option: ListOption::All,
+            format: ListFormat::Text,
+            output: None,
+        })),
+    };
+
+    assert_eq!(sut, expected);
+}
+
+#[test]
+fn verbose_short_option() {
+    let sut = parse_in_test(&["-v", "stats", "--trace"]).unwrap();
+
+    let mut options = HashSet::new();
+    options.insert(ProgramOption::Verbose);
+    options.insert(ProgramOption::Trace);
+
+    let expected = Arguments {
+        options,
+        command: Some(ProgramCommand::Stats(StatsOption::Text)),
+    };
+
+    assert_eq!(sut, expected);
+}
+
+#[test]
+fn last_timeout_wins() {
+    let sut = parse_in_test(&["--timeout", "5", "list", "--timeout=0"]).unwrap();
+
+    let mut options = HashSet::new();
+    options.insert(ProgramOption::Timeout(0));
+
+    let expected = Arguments {
+        options,
+        command: Some(ProgramCommand::List(ListArguments {
+            option: ListOption::Gone,
+            format: ListFormat::Text,
+            output: None,
+        })),
+    };
+
+    assert_eq!(sut, expected);
+}
+
+#[test]
+fn git_dir_option() {
+    let sut = parse_in_test(&["--git-dir", "/", "stats"]).unwrap();
+
+    let mut options = HashSet::new();
+    options.insert(ProgramOption::GitDir(PathBuf::from("/")));
+
+    let expected = Arguments {
+        options,
+        command: Some(ProgramCommand::Stats(StatsOption::Text)),
+    };
+
+    assert_eq!(sut, expected);
+}
+
+#[test]
+fn recursive_option() {
+    let sut = parse_in_test(&[
+        "list",
+        "--recursive",
+        "/",
+        "--format",
+        "json",
+        "--include-nested",
+    ])
+    .unwrap();
+
+    let mut options = HashSet::new();
+    options.insert(ProgramOption::Recursive(PathBuf::from("/")));
+    options.insert(ProgramOption::IncludeNested);
+
+    let expected = Arguments {
+        options,
+        command: Some(ProgramCommand::List(ListArguments {
+            option: ListOption::Gone,
+            format: ListFormat::Json,
+            output: None,
+        })),
+    };
+
+    assert_eq!(sut, expected);
+}
+
+#[test]
+fn workspace_option() {
+    let sut = parse_in_test(&["clean", "--automatic", "--workspace", "/"]).unwrap();
+
+    assert!(sut
+        .options
+        .contains(&ProgramOption::Workspace(PathBuf::from("/"))));
+}
+
+#[test]
+fn submodules_option() {
+    let sut = parse_in_test(&["list", "--submodules", "--all"]).unwrap();
+
+    assert!(sut.options.contains(&ProgramOption::Submodules));
+}
+
+#[test]
+fn every_remote_is_kept() {
+    let sut = parse_in_test(&[
+        "--fetch-prune",
+        "--remote",
+        "origin",
+        "--remote=upstream",
+        "list",
+    ])
+    .unwrap();
+
+    assert!(sut
+        .options
+        .contains(&ProgramOption::Remote("origin".to_string())));
+    assert!(sut
+        .options
+        .contains(&ProgramOption::Remote("upstream".to_string())));
+}
+
+#[test]
+fn complete_remotes() {
+    let sut = parse_in_test(&["__complete", "remotes"]).unwrap();
+
+    assert_eq!(
+        sut.command,
+        Some(ProgramCommand::Complete(CompleteOption::Remotes))
+    );
+}
+
+#[test]
+fn remote_refs_options() {
+    let sut = parse_in_test(&["list", "--remote-refs", "--porcelain"]).unwrap();
+
+    assert!(matches!(
+        sut.command,
+        Some(ProgramCommand::List(ListArguments {
+            option: ListOption::RemoteRefs { untracked: false },
+            format: ListFormat::Porcelain,
+            ..
+        }))
+    ));
+
+    let sut = parse_in_test(&["list", "--untracked", "--remote-refs"]).unwrap();
+
+    assert!(matches!(
+        sut.command,
+        Some(ProgramCommand::List(ListArguments {
+            option: ListOption::RemoteRefs { untracked: true },
+            ..
+        }))
+    ));
+
+    let sut = parse_in_test(&["clean", "--remote-refs", "--automatic"]).unwrap();
+
+    let expected = Some(ProgramCommand::Clean(CleanArguments {
+        option: CleanOption::Automatic,
+        switch_to: None,
+        remote_refs: true,
+        untracked: false,
+    }));
+
+    assert_eq!(sut.command, expected);
+
+    let sut = parse_in_test(&["clean", "--remote-refs", "--untracked"]).unwrap();
+
+    let expected = Some(ProgramCommand::Clean(CleanArguments {
+        option: CleanOption::Step,
+        switch_to: None,
+        remote_refs: true,
+        untracked: true,
+    }));
+
+    assert_eq!(sut.command, expected);
+}
+
+#[test]
+fn prune_remote_arguments() {
+    let sut = parse_in_test(&[
+        "prune-remote",
+        "--protect",
+        "release/*",
+        "upstream",
+        "--dry-run",
+        "--older-than=7",
+        "--protect=hotfix/*",
+    ])
+    .unwrap();
+
+    let expected = Arguments {
+        options: HashSet::new(),
+        command: Some(ProgramCommand::PruneRemote(PruneRemoteArguments {
+            option: PruneRemoteOption::DryRun,
+            remote: "upstream".to_string(),
+            base: None,
+            older_than: 7,
+            protected: vec!["release/*".to_string(), "hotfix/*".to_string()],
+            batch_size: DEFAULT_BATCH_SIZE,
+        })),
+    };
+
+    assert_eq!(sut, expected);
+}
+
+#[test]
+fn prune_remote_missing_remote() {
+    let sut = parse_in_test(&["prune-remote", "--automatic"]);
+
+    assert!(matches!(sut, Err(ParseError::MissingArgument { .. })));
+}
+
+#[test]
+fn last_jobs_wins() {
+    let sut = parse_in_test(&["--jobs", "4", "list", "--jobs=8"]).unwrap();
+
+    assert!(sut.options.contains(&ProgramOption::Jobs(8)));
+    assert!(!sut.options.contains(&ProgramOption::Jobs(4)));
+}
+
+#[test]
+fn inline_values() {
+    let sut = parse_in_test(&["--path=/", "list", "--depth=1", "--format=csv"]).unwrap();
+
+    let mut options = HashSet::new();
+    options.insert(ProgramOption::Path(PathBuf::from("/")));
+
+    let expected = Arguments {
+        options,
+        command: Some(ProgramCommand::List(ListArguments {
+            option: ListOption::Gone,
+            format: ListFormat::Csv,
+            output: None,
+        })),
+    };
+
+    assert_eq!(sut, expected);
+}
+
+#[test]
+fn git_compatible_path() {
+    let sut = parse_in_test(&["-C", "/", "stats"]).unwrap();
+
+    let mut options = HashSet::new();
+    options.insert(ProgramOption::Path(PathBuf::from("/")));
+
+    let expected = Arguments {
+        options,
+        command: Some(ProgramCommand::Stats(StatsOption::Text)),
+    };
+
+    assert_eq!(sut, expected);
+}
+
+#[test]
+fn last_path_wins() {
+    let current_dir = env::current_dir().unwrap();
+    let current_dir = current_dir.to_str().unwrap();
+
+    let sut = parse_in_test(&["-C", "/", "--path", current_dir]).unwrap();
+
+    let mut options = HashSet::new();
+    options.insert(ProgramOption::Path(PathBuf::from(current_dir)));
+
+    let expected = Arguments {
+        options,
+        command: None,
+    };
+
+    assert_eq!(sut, expected);
+}
+
+#[test]
+fn separator() {
+    let sut = parse_in_test(&["--fetch-prune", "--", "clean"]).unwrap();
+
+    let mut options = HashSet::new();
+    options.insert(ProgramOption::FetchPrune);
+
+    let expected = Arguments {
+        options,
+        command: Some(ProgramCommand::Clean(CleanArguments {
+            option: CleanOption::Step,
+            switch_to: None,
+            remote_refs: false,
+            untracked: false,
+        })),
+    };
+
+    assert_eq!(sut, expected);
+}
+
+#[test]
+fn options_after_separator_are_positional() {
+    let sut = parse_in_test(&["list", "--", "--all"]).err().unwrap();
+
+    let expected = ParseError::UnexpectedArgument {
+        token: "--all".to_string(),
+        position: 3,
+    };
+
+    assert_eq!(sut, expected)
+}
+
+#[test]
+fn flag_with_value() {
+    let sut = parse_in_test(&["list", "--all=yes"]).err().unwrap();
+
+    let expected = ParseError::UnexpectedValue {
+        token: "--all".to_string(),
+        position: 2,
+    };
+
+    assert_eq!(sut, expected)
+}
+
+#[test]
+fn suggest_option() {
+    let sut = parse_in_test(&["list", "--porcelian"]).err().unwrap();
+
+    let expected = ParseError::UnknownOption {
+        token: "--porcelian".to_string(),
+        position: 2,
+        suggestion: Some("--porcelain"),
+    };
+
+    assert_eq!(sut, expected);
+    assert_eq!(
+        sut.to_string(),
+        "Unknown option --porcelian at position 2, did you mean --porcelain?"
+    );
+}
+
+#[test]
+fn suggest_command() {
+    let sut = parse_in_test(&["--fetch-prune", "claen"]).err().unwrap();
+
+    let expected = ParseError::Command {
+        token: "claen".to_string(),
+        position: 2,
+        suggestion: Some("clean"),
+    };
+
+    assert_eq!(sut, expected)
+}
+
+#[test]
+fn command_option_before_command() {
+    let sut = parse_in_test(&["--all", "list"]).err().unwrap();
+
+    let expected = ParseError::UnknownOption {
+        token: "--all".to_string(),
+        position: 1,
+        suggestion: None,
+    };
+
+    assert_eq!(sut, expected)
+}
+
+#[test]
+fn completions_shell() {
+    let sut = parse_in_test(&["completions", "zsh"]).unwrap();
+
+    let expected = Arguments {
+        options: HashSet::new(),
+        command: Some(ProgramCommand::Completions(CompletionsOption::Zsh)),
+    };
+
+    assert_eq!(sut, expected);
+}
+
+#[test]
+fn man_command() {
+    let sut = parse_in_test(&["man"]).unwrap();
+
+    let expected = Arguments {
+        options: HashSet::new(),
+        command: Some(ProgramCommand::Man(ManOption::Print)),
+    };
+
+    assert_eq!(sut, expected);
+}
+
+#[test]
+fn completions_missing_shell() {
+    let sut = parse_in_test(&["completions"]).err().unwrap();
+
+    let expected = ParseError::MissingArgument {
+        name: "SHELL",
+        token: "completions".to_string(),
+        position: 1,
+    };
+
+    assert_eq!(sut, expected)
+}
+
+#[test]
+fn completions_unknown_shell() {
+    let sut = parse_in_test(&["completions", "powershell"]).err().unwrap();
+
+    let expected = ParseError::InvalidValue {
+        option: "completions",
+        token: "powershell".to_string(),
+        position: 2,
+    };
+
+    assert_eq!(sut, expected)
+}
+
+#[test]
+fn completions_too_many_shells() {
+    let sut = parse_in_test(&["completions", "bash", "zsh"])
+        .err()
+        .unwrap();
+
+    let expected = ParseError::UnexpectedArgument {
+        token: "zsh".to_string(),
+        position: 3,
+    };
 
     assert_eq!(sut, expected)
 }
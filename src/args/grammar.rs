@@ -0,0 +1,425 @@
+/*
+    The command line grammar: which commands exist and which options each of them accepts
+    The parser is driven by these tables, so adding an option here is enough to make it parseable
+*/
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct OptionSpec {
+    pub name: &'static str,
+    pub short: Option<&'static str>,
+    pub value: Option<ValueSpec>,
+    pub help: &'static str,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ValueSpec {
+    pub name: &'static str,
+    pub kind: ValueKind,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ValueKind {
+    ExistingPath,
+    File,
+    Number,
+    Branch,
+    Remote,
+    OneOf(&'static [&'static str]),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct CommandSpec {
+    pub name: &'static str,
+    pub options: &'static [OptionSpec],
+    pub positional: Option<ValueSpec>,
+    // Hidden commands are meant to be used by other programs, like shell completion scripts
+    pub hidden: bool,
+    pub help: &'static str,
+    pub examples: &'static [Example],
+}
+
+// A typical use of a command, listed in its help after the options
+#[derive(Debug, PartialEq, Eq)]
+pub struct Example {
+    pub term: &'static str,
+    pub command: &'static str,
+}
+
+const fn flag(name: &'static str, help: &'static str) -> OptionSpec {
+    OptionSpec {
+        name,
+        short: None,
+        value: None,
+        help,
+    }
+}
+
+const fn valued(
+    name: &'static str,
+    value_name: &'static str,
+    kind: ValueKind,
+    help: &'static str,
+) -> OptionSpec {
+    OptionSpec {
+        name,
+        short: None,
+        value: Some(ValueSpec {
+            name: value_name,
+            kind,
+        }),
+        help,
+    }
+}
+
+pub const LIST_FORMATS: &[&str] = &["text", "porcelain", "markdown", "csv", "json"];
+pub const SHELLS: &[&str] = &["bash", "zsh", "fish"];
+pub const DYNAMIC_VALUES: &[&str] = &["branches", "remotes"];
+
+pub const GLOBAL_OPTIONS: &[OptionSpec] = &[
+    flag("--help", "Print help"),
+    OptionSpec {
+        short: Some("-C"),
+        ..valued(
+            "--path",
+            "PATH",
+            ValueKind::ExistingPath,
+            "Execute operations in another path",
+        )
+    },
+    valued(
+        "--git-dir",
+        "DIR",
+        ValueKind::ExistingPath,
+        "Use the repository at DIR, e.g. a bare repository, like git --git-dir",
+    ),
+    valued(
+        "--recursive",
+        "DIR",
+        ValueKind::ExistingPath,
+        "Run list or clean in every git repository found under DIR",
+    ),
+    valued(
+        "--workspace",
+        "FILE",
+        ValueKind::ExistingPath,
+        "Run list or clean in every repository listed in the manifest FILE, with its overrides",
+    ),
+    flag(
+        "--include-nested",
+        "With --recursive, also run in repositories nested in other ones, like submodules",
+    ),
+    flag(
+        "--submodules",
+        "Also run list or clean in every initialised submodule, recursively",
+    ),
+    valued(
+        "--jobs",
+        "N",
+        ValueKind::Number,
+        "Query and fetch up to N repositories at once with --recursive, --workspace or --submodules, and fetch up to N remotes at once (default 1, 0 for one per CPU)",
+    ),
+    flag(
+        "--fetch-prune",
+        "Execute git fetch --prune before executing specified command, recording the branches first for diff when none are recorded",
+    ),
+    valued(
+        "--remote",
+        "NAME",
+        ValueKind::Remote,
+        "With --fetch-prune, fetch the remote NAME instead of the default one. Can be repeated",
+    ),
+    flag(
+        "--all-remotes",
+        "With --fetch-prune, fetch every configured remote",
+    ),
+    OptionSpec {
+        short: Some("-v"),
+        ..flag(
+            "--verbose",
+            "Log each git command to stderr, with its directory, duration and exit status",
+        )
+    },
+    flag(
+        "--trace",
+        "Same as --verbose, but also log the output of each git command",
+    ),
+    valued(
+        "--timeout",
+        "SECONDS",
+        ValueKind::Number,
+        "Kill git commands running longer than SECONDS (default 120, 0 disables the timeout)",
+    ),
+];
+
+pub const COMMANDS: &[CommandSpec] = &[
+    CommandSpec {
+        name: "list",
+        options: &[
+            flag("--help", "Print list options"),
+            flag(
+                "--gone",
+                "List tracking branches that are gone from origin (default option)",
+            ),
+            flag("--diverged", "List tracking branches that diverged from origin"),
+            flag("--all", "List all local and tracked branches"),
+            flag("--tracked", "List all tracked branches"),
+            flag("--local", "List local branches"),
+            flag(
+                "--remote-refs",
+                "List remote-tracking refs of remotes no longer configured",
+            ),
+            flag(
+                "--untracked",
+                "With --remote-refs, also list the ones no local branch tracks, except the default branches of remotes",
+            ),
+            flag(
+                "--porcelain",
+                "Print one tab separated record per branch, stable between versions",
+            ),
+            flag(
+                "-z",
+                "Same as --porcelain, but records are terminated by NUL instead of newline",
+            ),
+            flag(
+                "--tree",
+                "Group branches hierarchically by their /-separated name prefix",
+            ),
+            valued(
+                "--depth",
+                "N",
+                ValueKind::Number,
+                "Same as --tree, but only N levels of groups are shown",
+            ),
+            valued(
+                "--format",
+                "FORMAT",
+                ValueKind::OneOf(LIST_FORMATS),
+                "Output format: text (default), porcelain, markdown, csv or json",
+            ),
+            valued(
+                "--output",
+                "FILE",
+                ValueKind::File,
+                "Write the output to FILE instead of stdout",
+            ),
+        ],
+        positional: None,
+        hidden: false,
+        help: "List branches",
+        examples: &[],
+    },
+    CommandSpec {
+        name: "clean",
+        options: &[
+            flag("--help", "Print clean options"),
+            flag(
+                "--step",
+                "Ask for user confirmation before deleting each branch (default option)",
+            ),
+            flag(
+                "--automatic",
+                "Delete branches without asking for user confirmation",
+            ),
+            valued(
+                "--switch-to",
+                "BRANCH",
+                ValueKind::Branch,
+                "Branch to check out when the current branch is gone (default is the remote default branch)",
+            ),
+            flag(
+                "--remote-refs",
+                "Delete the remote-tracking refs list --remote-refs shows instead, without fetching",
+            ),
+            flag(
+                "--untracked",
+                "With --remote-refs, also delete the ones no local branch tracks, except the default branches of remotes",
+            ),
+        ],
+        positional: None,
+        hidden: false,
+        help: "Delete local branches that are gone from origin",
+        examples: &[],
+    },
+    CommandSpec {
+        name: "stats",
+        options: &[
+            flag("--help", "Print stats options"),
+            flag("--text", "Print statistics as text (default option)"),
+            flag("--json", "Print statistics as a JSON document"),
+        ],
+        positional: None,
+        hidden: false,
+        help: "Print statistics about branches",
+        examples: &[],
+    },
+    CommandSpec {
+        name: "diff",
+        options: &[flag("--help", "Print diff options")],
+        positional: None,
+        hidden: false,
+        help: "Show how branches changed since the last recorded state, then record the current one",
+        examples: &[],
+    },
+    CommandSpec {
+        name: "prune-remote",
+        options: &[
+            flag("--help", "Print prune-remote options"),
+            flag(
+                "--confirm",
+                "List the branches, then ask for confirmation before deleting them (default option)",
+            ),
+            flag(
+                "--automatic",
+                "Delete branches without asking for user confirmation",
+            ),
+            flag(
+                "--dry-run",
+                "Only list the branches that would be deleted",
+            ),
+            valued(
+                "--base",
+                "BRANCH",
+                ValueKind::Branch,
+                "Branch of the remote the others must be merged into (default is the remote default branch)",
+            ),
+            valued(
+                "--older-than",
+                "DAYS",
+                ValueKind::Number,
+                "Only delete branches whose last commit is at least DAYS days old (default 30)",
+            ),
+            valued(
+                "--protect",
+                "PATTERN",
+                ValueKind::Branch,
+                "Never delete branches matching PATTERN, where * matches anything. Can be repeated",
+            ),
+            valued(
+                "--batch-size",
+                "N",
+                ValueKind::Number,
+                "Delete up to N branches per git push (default 50)",
+            ),
+        ],
+        positional: Some(ValueSpec {
+            name: "REMOTE",
+            kind: ValueKind::Remote,
+        }),
+        hidden: false,
+        help: "Delete branches of a remote that are merged into its base branch",
+        examples: &[],
+    },
+    CommandSpec {
+        name: "completions",
+        options: &[flag("--help", "Print completions options")],
+        positional: Some(ValueSpec {
+            name: "SHELL",
+            kind: ValueKind::OneOf(SHELLS),
+        }),
+        hidden: false,
+        help: "Print a completion script for bash, zsh or fish",
+        examples: &[
+            Example {
+                term: "bash",
+                command: "source <(git-cleanup completions bash)",
+            },
+            Example {
+                term: "zsh",
+                command: "source <(git-cleanup completions zsh)",
+            },
+            Example {
+                term: "fish",
+                command: "git-cleanup completions fish | source",
+            },
+        ],
+    },
+    CommandSpec {
+        name: "man",
+        options: &[flag("--help", "Print man options")],
+        positional: None,
+        hidden: false,
+        help: "Print the man page in roff format, to be installed as git-cleanup.1",
+        examples: &[],
+    },
+    CommandSpec {
+        name: "__complete",
+        options: &[],
+        positional: Some(ValueSpec {
+            name: "VALUES",
+            kind: ValueKind::OneOf(DYNAMIC_VALUES),
+        }),
+        hidden: true,
+        help: "Print values for shell completion scripts",
+        examples: &[],
+    },
+];
+
+pub fn find_option<'a>(options: &'a [OptionSpec], token: &str) -> Option<&'a OptionSpec> {
+    options
+        .iter()
+        .find(|option| option.name == token || option.short == Some(token))
+}
+
+pub fn find_command(token: &str) -> Option<&'static CommandSpec> {
+    COMMANDS.iter().find(|command| command.name == token)
+}
+
+pub fn visible_commands() -> impl Iterator<Item = &'static CommandSpec> {
+    COMMANDS.iter().filter(|command| !command.hidden)
+}
+
+// Suggests the closest candidate, as long as it is close enough to be a plausible typo
+pub fn suggest<'a>(token: &str, candidates: impl Iterator<Item = &'a str>) -> Option<&'a str> {
+    let max_distance = (token.chars().count() / 3).clamp(2, 3);
+
+    candidates
+        .map(|candidate| (edit_distance(token, candidate), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+// Levenshtein distance, good enough for the handful of short words we compare
+fn edit_distance(left: &str, right: &str) -> usize {
+    let right: Vec<char> = right.chars().collect();
+    let mut previous: Vec<usize> = (0..=right.len()).collect();
+
+    for (i, left_char) in left.chars().enumerate() {
+        let mut current = vec![i + 1; right.len() + 1];
+
+        for (j, right_char) in right.iter().enumerate() {
+            let substitution = previous[j] + usize::from(left_char != *right_char);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+
+        previous = current;
+    }
+
+    previous[right.len()]
+}
+
+#[test]
+fn distance() {
+    assert_eq!(edit_distance("--gone", "--gone"), 0);
+    assert_eq!(edit_distance("--gnoe", "--gone"), 2);
+    assert_eq!(edit_distance("lst", "list"), 1);
+    assert_eq!(edit_distance("", "abc"), 3);
+}
+
+#[test]
+fn suggest_closest() {
+    let sut = suggest(
+        "--porcelan",
+        ["--porcelain", "--path", "--local"].into_iter(),
+    );
+    assert_eq!(sut, Some("--porcelain"));
+}
+
+#[test]
+fn suggest_nothing_when_too_far() {
+    let sut = suggest(
+        "--verbose",
+        ["--porcelain", "--path", "--local"].into_iter(),
+    );
+    assert_eq!(sut, None);
+}
@@ -0,0 +1,282 @@
+use super::grammar::*;
+
+/*
+    The help model is derived from the grammar, and rendered either for the terminal
+    or as a roff man page, which git opens when running "git cleanup --help"
+*/
+pub struct Help {
+    pub sections: Vec<HelpSection>,
+    pub notes: &'static [&'static str],
+}
+
+pub struct HelpSection {
+    pub title: &'static str,
+    pub entries: Vec<HelpEntry>,
+}
+
+pub struct HelpEntry {
+    pub term: String,
+    pub description: String,
+}
+
+const PROGRAM_NOTES: &[&str] = &[
+    "Execute each command with --help for available options",
+    "Options may appear anywhere, and values may also be given as --option=value",
+];
+
+const MAN_NAME: &str = "git-cleanup \\- delete local branches that are gone from their remote";
+
+impl Help {
+    pub fn program() -> Self {
+        let commands = visible_commands()
+            .map(|command| HelpEntry {
+                term: command_term(command),
+                description: command.help.to_string(),
+            })
+            .collect();
+
+        Help {
+            sections: vec![
+                options_section(GLOBAL_OPTIONS),
+                HelpSection {
+                    title: "Commands",
+                    entries: commands,
+                },
+            ],
+            notes: PROGRAM_NOTES,
+        }
+    }
+
+    pub fn command(command: &CommandSpec) -> Self {
+        let mut sections = Vec::new();
+
+        if let Some(value) = &command.positional {
+            sections.push(HelpSection {
+                title: "Arguments",
+                entries: vec![HelpEntry {
+                    term: format!("<{}>", value.name),
+                    description: value_description(&value.kind),
+                }],
+            });
+        }
+
+        sections.push(options_section(command.options));
+
+        if !command.examples.is_empty() {
+            sections.push(HelpSection {
+                title: "Examples",
+                entries: command
+                    .examples
+                    .iter()
+                    .map(|example| HelpEntry {
+                        term: example.term.to_string(),
+                        description: example.command.to_string(),
+                    })
+                    .collect(),
+            });
+        }
+
+        Help {
+            sections,
+            notes: &[],
+        }
+    }
+
+    // Descriptions are aligned within each section, so that terms are easy to scan
+    pub fn to_terminal(&self) -> String {
+        let mut sections = Vec::new();
+
+        for section in &self.sections {
+            let width = section
+                .entries
+                .iter()
+                .map(|entry| entry.term.len() + 1)
+                .max()
+                .unwrap_or(0);
+
+            let mut text = format!("{}:\n", section.title);
+
+            for entry in &section.entries {
+                let term = format!("{}:", entry.term);
+                text.push_str(&format!("    {term:width$} {}\n", entry.description));
+            }
+
+            sections.push(text);
+        }
+
+        let mut output = sections.join("\n");
+
+        if !self.notes.is_empty() {
+            output.push('\n');
+            for note in self.notes {
+                output.push_str(&format!("{note}\n"));
+            }
+        }
+
+        output
+    }
+}
+
+pub fn command_help(name: &str) -> String {
+    match find_command(name) {
+        Some(command) => Help::command(command).to_terminal(),
+        None => Help::program().to_terminal(),
+    }
+}
+
+pub fn man_page() -> String {
+    let program = Help::program();
+
+    let mut page = format!(
+        ".TH GIT-CLEANUP 1 \"\" \"git-cleanup {}\" \"Git Manual\"\n",
+        env!("CARGO_PKG_VERSION")
+    );
+
+    page.push_str(&format!(".SH NAME\n{MAN_NAME}\n"));
+    page.push_str(".SH SYNOPSIS\n");
+    page.push_str(".B git cleanup\n");
+    page.push_str("[\\fIOPTIONS\\fR] [\\fICOMMAND\\fR] [\\fICOMMAND OPTIONS\\fR]\n");
+
+    page.push_str(".SH DESCRIPTION\n");
+    let notes: Vec<String> = program.notes.iter().map(|note| roff_escape(note)).collect();
+    page.push_str(&format!("{}\n", notes.join("\n.PP\n")));
+
+    for section in &program.sections {
+        page.push_str(&format!(".SH {}\n", section.title.to_uppercase()));
+        push_man_entries(&mut page, &section.entries);
+    }
+
+    page.push_str(".SH COMMAND OPTIONS\n");
+    for command in visible_commands() {
+        page.push_str(&format!(".SS {}\n", command_term(command)));
+        page.push_str(&format!("{}\n", roff_escape(command.help)));
+
+        for section in Help::command(command).sections {
+            push_man_entries(&mut page, &section.entries);
+        }
+    }
+
+    page
+}
+
+fn push_man_entries(page: &mut String, entries: &[HelpEntry]) {
+    for entry in entries {
+        page.push_str(".TP\n");
+        page.push_str(&format!("\\fB{}\\fR\n", roff_escape(&entry.term)));
+        page.push_str(&format!("{}\n", roff_escape(&entry.description)));
+    }
+}
+
+fn options_section(options: &[OptionSpec]) -> HelpSection {
+    let entries = options
+        .iter()
+        .map(|option| HelpEntry {
+            term: option_term(option),
+            description: option.help.to_string(),
+        })
+        .collect();
+
+    HelpSection {
+        title: "Options",
+        entries,
+    }
+}
+
+fn option_term(option: &OptionSpec) -> String {
+    let mut term = match option.short {
+        Some(short) => format!("{short}, {}", option.name),
+        None => option.name.to_string(),
+    };
+
+    if let Some(value) = &option.value {
+        term.push_str(&format!(" <{}>", value.name));
+    }
+
+    term
+}
+
+fn command_term(command: &CommandSpec) -> String {
+    match &command.positional {
+        Some(value) => format!("{} <{}>", command.name, value.name),
+        None => command.name.to_string(),
+    }
+}
+
+fn value_description(kind: &ValueKind) -> String {
+    match kind {
+        ValueKind::OneOf(values) => format!("One of: {}", values.join(", ")),
+        ValueKind::ExistingPath => "An existing path".to_string(),
+        ValueKind::File => "A file path".to_string(),
+        ValueKind::Number => "A non negative number".to_string(),
+        ValueKind::Branch => "A branch name".to_string(),
+        ValueKind::Remote => "A remote name".to_string(),
+    }
+}
+
+// Backslashes and dashes have special meaning in roff, as do dots and quotes at the start of a line
+fn roff_escape(text: &str) -> String {
+    let escaped = text.replace('\\', "\\e").replace('-', "\\-");
+
+    if escaped.starts_with('.') || escaped.starts_with('\'') {
+        format!("\\&{escaped}")
+    } else {
+        escaped
+    }
+}
+
+#[test]
+fn stats_terminal_help() {
+    let sut = command_help("stats");
+
+    let expected = "Options:\n    \
+                        --help: Print stats options\n    \
+                        --text: Print statistics as text (default option)\n    \
+                        --json: Print statistics as a JSON document\n";
+
+    assert_eq!(sut, expected);
+}
+
+#[test]
+fn completions_terminal_help() {
+    let sut = command_help("completions");
+
+    let expected = "Arguments:\n    \
+                        <SHELL>: One of: bash, zsh, fish\n\
+                    \n\
+                    Options:\n    \
+                        --help: Print completions options\n\
+                    \n\
+                    Examples:\n    \
+                        bash: source <(git-cleanup completions bash)\n    \
+                        zsh:  source <(git-cleanup completions zsh)\n    \
+                        fish: git-cleanup completions fish | source\n";
+
+    assert_eq!(sut, expected);
+}
+
+#[test]
+fn program_help_aligns_terms() {
+    let sut = Help::program().to_terminal();
+
+    assert!(sut.starts_with("Options:\n    --help:              Print help\n"));
+    assert!(sut.contains("    -C, --path <PATH>:   Execute operations in another path\n"));
+    assert!(sut.contains("    completions <SHELL>:   Print a completion script"));
+    assert!(!sut.contains("__complete"));
+}
+
+#[test]
+fn man_page_escapes_options() {
+    let sut = man_page();
+
+    assert!(sut.starts_with(".TH GIT-CLEANUP 1"));
+    assert!(
+        sut.contains(".TP\n\\fB\\-C, \\-\\-path <PATH>\\fR\nExecute operations in another path\n")
+    );
+    assert!(sut.contains(".SS list\nList branches\n"));
+}
+
+#[test]
+fn escape_leading_dot() {
+    assert_eq!(roff_escape(".hidden"), "\\&.hidden");
+    assert_eq!(roff_escape("a\\b"), "a\\eb");
+}
@@ -0,0 +1,13 @@
+/*
+    Classification of branches: filters selecting them by status or by name, and the flat record
+    every listing and report is built from
+*/
+
+mod filter;
+pub use filter::*;
+
+mod record;
+pub use record::*;
+
+mod pattern;
+pub use pattern::*;
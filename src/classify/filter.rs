@@ -0,0 +1,52 @@
+use crate::git::{Branch, RemoteBranch, RemoteBranchStatus};
+
+pub fn is_any(_: &&Branch) -> bool {
+    true
+}
+
+pub fn is_local(branch: &&Branch) -> bool {
+    matches!(branch, Branch::Local { .. })
+}
+
+pub fn is_tracked(branch: &&Branch) -> bool {
+    matches!(branch, Branch::Tracking { .. })
+}
+
+pub fn is_gone(branch: &&Branch) -> bool {
+    matches!(
+        branch,
+        Branch::Tracking {
+            remote: RemoteBranch {
+                status: RemoteBranchStatus::Gone,
+                ..
+            },
+            ..
+        }
+    )
+}
+
+pub fn is_diverged(branch: &&Branch) -> bool {
+    matches!(
+        branch,
+        Branch::Tracking {
+            remote: RemoteBranch {
+                status: RemoteBranchStatus::Diverged { .. },
+                ..
+            },
+            ..
+        }
+    )
+}
+
+pub fn is_synchronized(branch: &&Branch) -> bool {
+    matches!(
+        branch,
+        Branch::Tracking {
+            remote: RemoteBranch {
+                status: RemoteBranchStatus::Synchronized,
+                ..
+            },
+            ..
+        }
+    )
+}
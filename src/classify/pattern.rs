@@ -0,0 +1,98 @@
+use crate::git::RefName;
+
+/*
+    Shell style patterns for branch names, where * matches any run of characters, / included,
+    and ? matches a single one. Git forbids both characters in ref names, so any branch name is also
+    a pattern matching only itself
+*/
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Pattern(Vec<u8>);
+
+impl Pattern {
+    pub fn new(pattern: impl Into<Vec<u8>>) -> Self {
+        Pattern(pattern.into())
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn matches(&self, name: RefName) -> bool {
+        matches_from(&self.0, name.as_bytes())
+    }
+}
+
+impl std::fmt::Display for Pattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", String::from_utf8_lossy(&self.0))
+    }
+}
+
+pub fn is_protected(name: RefName, patterns: &[Pattern]) -> bool {
+    patterns.iter().any(|pattern| pattern.matches(name))
+}
+
+// Backtracks only to the last *, which is enough since a later * can match anything an earlier one could
+fn matches_from(pattern: &[u8], name: &[u8]) -> bool {
+    let (mut pattern_index, mut name_index) = (0, 0);
+    let mut last_star: Option<(usize, usize)> = None;
+
+    while name_index < name.len() {
+        match pattern.get(pattern_index) {
+            Some(b'*') => {
+                last_star = Some((pattern_index, name_index));
+                pattern_index += 1;
+            }
+            Some(&byte) if byte == b'?' || byte == name[name_index] => {
+                pattern_index += 1;
+                name_index += 1;
+            }
+            _ => match last_star {
+                // The last * takes one more character, and matching resumes after it
+                Some((star_index, star_name_index)) => {
+                    last_star = Some((star_index, star_name_index + 1));
+                    pattern_index = star_index + 1;
+                    name_index = star_name_index + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[pattern_index..].iter().all(|byte| *byte == b'*')
+}
+
+#[test]
+fn literal_pattern() {
+    let sut = Pattern::new("main");
+
+    assert!(sut.matches(RefName(b"main")));
+    assert!(!sut.matches(RefName(b"main2")));
+    assert!(!sut.matches(RefName(b"mai")));
+}
+
+#[test]
+fn star_matches_across_slashes() {
+    let sut = Pattern::new("release/*");
+
+    assert!(sut.matches(RefName(b"release/1.0")));
+    assert!(sut.matches(RefName(b"release/1.0/hotfix")));
+    assert!(sut.matches(RefName(b"release/")));
+    assert!(!sut.matches(RefName(b"releases/1.0")));
+}
+
+#[test]
+fn star_backtracks() {
+    let sut = Pattern::new("*-fix-?");
+
+    assert!(sut.matches(RefName(b"team-fix-fix-1")));
+    assert!(!sut.matches(RefName(b"team-fix-12")));
+}
+
+#[test]
+fn protected_by_any_pattern() {
+    let patterns = [Pattern::new("main"), Pattern::new("hotfix/*")];
+
+    assert!(is_protected(RefName(b"hotfix/login"), &patterns));
+    assert!(!is_protected(RefName(b"feature"), &patterns));
+}
@@ -0,0 +1,150 @@
+use crate::git::{Branch, Head, RefName, Repository};
+use std::borrow::Cow;
+
+/*
+    A Record is the flat, column oriented view of a branch used by the machine readable
+    output formats. Columns must only ever be appended, never reordered or removed,
+    otherwise scripts depending on --porcelain will break
+    Names are kept as git printed them, so porcelain output can give scripts the exact bytes
+*/
+pub const COLUMNS: [&str; 5] = ["head", "name", "status", "remote", "remote_branch"];
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Record<'a> {
+    pub head: bool,
+    pub name: RefName<'a>,
+    pub status: &'static str,
+    pub remote_name: RefName<'a>,
+    pub remote_branch: RefName<'a>,
+}
+
+const NO_NAME: RefName = RefName(b"");
+
+impl<'a> Record<'a> {
+    pub fn new(branch: &'a Branch<'a>, head: bool) -> Self {
+        match branch {
+            Branch::Tracking { name, remote } => Record {
+                head,
+                name: *name,
+                status: remote.status.name(),
+                remote_name: remote.remote_name,
+                remote_branch: remote.branch_name,
+            },
+            Branch::Local { name } => Record {
+                head,
+                name: *name,
+                status: "local",
+                remote_name: NO_NAME,
+                remote_branch: NO_NAME,
+            },
+        }
+    }
+
+    pub fn fields(&self) -> [&'a [u8]; 5] {
+        let head: &[u8] = if self.head { b"*" } else { b"" };
+        [
+            head,
+            self.name.as_bytes(),
+            self.status.as_bytes(),
+            self.remote_name.as_bytes(),
+            self.remote_branch.as_bytes(),
+        ]
+    }
+
+    // Fields for human readable formats, where names that aren't UTF-8 are shown lossily
+    pub fn text_fields(&self) -> [Cow<'a, str>; 5] {
+        self.fields().map(String::from_utf8_lossy)
+    }
+}
+
+/*
+    Records are sorted by branch name so that the output is stable between executions
+    A detached HEAD is not a branch, so it has no record. Only the text listing shows it
+*/
+pub fn records<'a>(
+    repository: &'a Repository<'a>,
+    filter: impl Fn(&&Branch) -> bool,
+) -> Vec<Record<'a>> {
+    let mut records = Vec::with_capacity(repository.branches.len() + 1);
+
+    if let Head::Branch(branch) = &repository.head {
+        if filter(&branch) {
+            records.push(Record::new(branch, true));
+        }
+    }
+
+    for branch in repository.branches.iter().filter(filter) {
+        records.push(Record::new(branch, false));
+    }
+
+    records.sort_by(|left, right| left.name.cmp(&right.name));
+
+    records
+}
+
+#[test]
+fn records_are_sorted_and_mark_head() {
+    let repository = crate::git::repository! {
+        *tracking { "main", remote("main", "origin", synchronized) },
+        local("zeta"),
+        tracking { "feature", remote("feature", "upstream", gone) },
+    };
+
+    let sut = records(&repository, |_| true);
+
+    let expected = vec![
+        Record {
+            head: false,
+            name: RefName(b"feature"),
+            status: "gone",
+            remote_name: RefName(b"upstream"),
+            remote_branch: RefName(b"feature"),
+        },
+        Record {
+            head: true,
+            name: RefName(b"main"),
+            status: "synchronized",
+            remote_name: RefName(b"origin"),
+            remote_branch: RefName(b"main"),
+        },
+        Record {
+            head: false,
+            name: RefName(b"zeta"),
+            status: "local",
+            remote_name: RefName(b""),
+            remote_branch: RefName(b""),
+        },
+    ];
+
+    assert_eq!(sut, expected);
+}
+
+#[test]
+fn detached_head_has_no_record() {
+    let repository = crate::git::repository! {
+        *detached,
+        tracking { "feature", remote("feature", "origin", gone) },
+        local("local"),
+    };
+
+    let sut = records(&repository, crate::classify::is_gone);
+
+    let expected = vec![Record {
+        head: false,
+        name: RefName(b"feature"),
+        status: "gone",
+        remote_name: RefName(b"origin"),
+        remote_branch: RefName(b"feature"),
+    }];
+
+    assert_eq!(sut, expected);
+}
+
+#[test]
+fn local_record_fields() {
+    let branch = crate::git::local!("develop");
+
+    let sut = Record::new(&branch, false).text_fields();
+
+    assert_eq!(sut, ["", "develop", "local", "", ""]);
+}
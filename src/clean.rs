@@ -0,0 +1,349 @@
+mod remote;
+pub use remote::*;
+
+use crate::classify::{self, Pattern};
+use crate::execute::{self, Location};
+use crate::git::{
+    self, Branch, GitError, Head, RefName, RemoteBranch, RemoteBranchStatus, Repository,
+};
+use std::ffi::OsStr;
+use thiserror::Error;
+
+// Used when the remote doesn't tell us its default branch
+const FALLBACK_DEFAULT_BRANCHES: [&str; 2] = ["main", "master"];
+
+/// What a cleanup deletes, worked out before anything is changed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CleanupPlan<'a> {
+    /// Set when the current branch is gone, since git refuses to delete it without switching first
+    pub current: Option<CurrentBranch<'a>>,
+    /// The other gone branches, sorted so that runs are reproducible
+    pub branches: Vec<RefName<'a>>,
+}
+
+/// Limits on which gone branches a cleanup may delete, e.g. from a workspace manifest
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Policy {
+    /// Branches matching any of these are never deleted
+    pub protected: Vec<Pattern>,
+    /// When set, only branches tracking this remote are deleted
+    pub remote: Option<Vec<u8>>,
+}
+
+/// How a gone current branch is handled
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CurrentBranch<'a> {
+    /// Target is checked out before deleting the branch
+    Switch { name: RefName<'a>, target: Vec<u8> },
+    /// The repository is bare, and its HEAD picks the branch its clones check out, so the branch is kept
+    Bare { name: RefName<'a> },
+    /// There is no other branch to switch to, so the branch is kept
+    NoTarget { name: RefName<'a> },
+    /// The working tree has uncommitted changes, so the branch is kept
+    Dirty { name: RefName<'a>, target: Vec<u8> },
+}
+
+/// A change the cleanup is about to make, passed to the confirmation callback of [`CleanupPlan::run`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Step<'a> {
+    /// Switch from the current branch name to target, then delete name
+    Switch {
+        name: RefName<'a>,
+        target: RefName<'a>,
+    },
+    Delete {
+        name: RefName<'a>,
+    },
+}
+
+/// What a cleanup did, which matters most when it stopped halfway
+#[derive(Debug)]
+pub struct CleanupOutcome<'a> {
+    pub deleted: Vec<RefName<'a>>,
+    pub error: Option<CleanupError>,
+}
+
+#[derive(Error, Debug)]
+pub enum CleanupError {
+    #[error("Error switching to branch {target}")]
+    Switch { target: String, source: GitError },
+
+    #[error("Error deleting branch {branch}")]
+    Delete { branch: String, source: GitError },
+}
+
+impl Policy {
+    pub fn allows(&self, branch: &Branch) -> bool {
+        let (name, remote_name) = match branch {
+            Branch::Tracking { name, remote } => (*name, Some(remote.remote_name)),
+            Branch::Local { name } => (*name, None),
+        };
+
+        let on_remote = match (&self.remote, remote_name) {
+            (Some(remote), Some(remote_name)) => remote == remote_name.as_bytes(),
+            (Some(_), None) => false,
+            (None, _) => true,
+        };
+
+        on_remote && !classify::is_protected(name, &self.protected)
+    }
+}
+
+impl<'a> CurrentBranch<'a> {
+    pub fn name(&self) -> RefName<'a> {
+        match self {
+            CurrentBranch::Switch { name, .. }
+            | CurrentBranch::Bare { name }
+            | CurrentBranch::NoTarget { name }
+            | CurrentBranch::Dirty { name, .. } => *name,
+        }
+    }
+}
+
+impl<'a> CleanupPlan<'a> {
+    /// Plans the deletion of every gone branch of the repository
+    ///
+    /// Git is only queried when the current branch is gone, to find the branch to switch to,
+    /// which is switch_to when given, otherwise the default branch of the remote
+    pub fn new(
+        path: &(impl Location + ?Sized),
+        repository: &'a Repository<'a>,
+        switch_to: Option<&[u8]>,
+    ) -> Result<Self, GitError> {
+        Self::with_policy(path, repository, switch_to, &Policy::default())
+    }
+
+    /// Same as [`CleanupPlan::new`], but branches the policy doesn't allow are kept
+    pub fn with_policy(
+        path: &(impl Location + ?Sized),
+        repository: &'a Repository<'a>,
+        switch_to: Option<&[u8]>,
+        policy: &Policy,
+    ) -> Result<Self, GitError> {
+        let mut branches: Vec<RefName> = repository
+            .branches
+            .iter()
+            .filter(classify::is_gone)
+            .filter(|branch| policy.allows(branch))
+            .map(|branch| match branch {
+                Branch::Tracking { name, .. } | Branch::Local { name } => *name,
+            })
+            .collect();
+
+        branches.sort();
+
+        let current = match &repository.head {
+            Head::Branch(branch) if !policy.allows(branch) => None,
+            Head::Branch(Branch::Tracking {
+                name,
+                remote:
+                    RemoteBranch {
+                        status: RemoteBranchStatus::Gone,
+                        remote_name,
+                        ..
+                    },
+            }) => Some(plan_current(
+                path,
+                repository,
+                *name,
+                *remote_name,
+                switch_to,
+            )?),
+            _ => None,
+        };
+
+        Ok(CleanupPlan { current, branches })
+    }
+
+    /// Runs the plan, asking confirm before each step, and stops at the first step that is not confirmed
+    pub fn run(
+        &self,
+        path: &(impl Location + ?Sized),
+        mut confirm: impl FnMut(Step) -> bool,
+    ) -> CleanupOutcome<'a> {
+        // Branches are recorded as they are deleted, so that an interrupted cleanup can tell what it did
+        let mut deleted = Vec::new();
+        let error = self.run_steps(path, &mut confirm, &mut deleted).err();
+
+        CleanupOutcome { deleted, error }
+    }
+
+    /// Branches the plan deletes when every step is confirmed, starting with the current branch
+    pub fn deletions(&self) -> Vec<RefName<'a>> {
+        let current = match &self.current {
+            Some(CurrentBranch::Switch { name, .. }) => Some(*name),
+            _ => None,
+        };
+
+        current
+            .into_iter()
+            .chain(self.branches.iter().copied())
+            .collect()
+    }
+
+    /// Branches the plan would delete that are not in deleted, starting with the current branch
+    pub fn not_deleted(&self, deleted: &[RefName]) -> Vec<RefName<'a>> {
+        self.current
+            .iter()
+            .map(CurrentBranch::name)
+            .chain(self.branches.iter().copied())
+            .filter(|name| !deleted.contains(name))
+            .collect()
+    }
+
+    fn run_steps(
+        &self,
+        path: &(impl Location + ?Sized),
+        confirm: &mut impl FnMut(Step) -> bool,
+        deleted: &mut Vec<RefName<'a>>,
+    ) -> Result<(), CleanupError> {
+        if let Some(CurrentBranch::Switch { name, target }) = &self.current {
+            let target = RefName(target);
+
+            if !confirm(Step::Switch {
+                name: *name,
+                target,
+            }) {
+                return Ok(());
+            }
+
+            git::switch_branch(&path, target).map_err(|source| CleanupError::Switch {
+                target: target.to_string(),
+                source,
+            })?;
+
+            delete_branch(path, *name)?;
+            deleted.push(*name);
+        }
+
+        for &name in &self.branches {
+            if execute::is_interrupted() || !confirm(Step::Delete { name }) {
+                break;
+            }
+
+            delete_branch(path, name)?;
+            deleted.push(name);
+        }
+
+        Ok(())
+    }
+}
+
+fn plan_current<'a>(
+    path: &(impl Location + ?Sized),
+    repository: &Repository,
+    name: RefName<'a>,
+    remote_name: RefName,
+    switch_to: Option<&[u8]>,
+) -> Result<CurrentBranch<'a>, GitError> {
+    /*
+        Git would delete the branch HEAD points to in a bare repository, but moving HEAD
+        instead of a checkout would silently change the default branch of every clone
+    */
+    if git::is_bare_repository(&path)? {
+        return Ok(CurrentBranch::Bare { name });
+    }
+
+    let target = switch_to
+        .map(<[u8]>::to_vec)
+        .or_else(|| git::remote_default_branch(&path, remote_name))
+        .or_else(|| fallback_default_branch(repository));
+
+    let target = match target {
+        Some(target) if target != name.as_bytes() => target,
+        _ => return Ok(CurrentBranch::NoTarget { name }),
+    };
+
+    if git::is_worktree_dirty(&path)? {
+        return Ok(CurrentBranch::Dirty { name, target });
+    }
+
+    Ok(CurrentBranch::Switch { name, target })
+}
+
+fn fallback_default_branch(repository: &Repository) -> Option<Vec<u8>> {
+    let name = FALLBACK_DEFAULT_BRANCHES.into_iter().find(|fallback| {
+        repository.branches.iter().any(|branch| match branch {
+            Branch::Tracking { name, .. } | Branch::Local { name } => name == fallback,
+        })
+    })?;
+
+    Some(name.as_bytes().to_vec())
+}
+
+// Ctrl-C never kills the deletion halfway, so that an interruption leaves the branch either deleted or intact
+fn delete_branch(path: &(impl Location + ?Sized), name: RefName) -> Result<(), CleanupError> {
+    let arguments = [OsStr::new("branch"), OsStr::new("-d"), &name.to_os_str()];
+
+    match execute::execute_to_completion(&path, "git", &arguments) {
+        Ok(_) => Ok(()),
+        Err(error) => Err(CleanupError::Delete {
+            branch: name.to_string(),
+            source: error.into(),
+        }),
+    }
+}
+
+#[test]
+fn fallback_to_main() {
+    let repository = git::repository! {
+        *tracking { "feature", remote("feature", "origin", gone) },
+        tracking { "master", remote("master", "origin", synchronized) },
+        local("main"),
+    };
+
+    let sut = fallback_default_branch(&repository);
+
+    assert_eq!(sut, Some(b"main".to_vec()));
+}
+
+#[test]
+fn no_fallback() {
+    let repository = git::repository! {
+        *tracking { "feature", remote("feature", "origin", gone) },
+        local("develop"),
+    };
+
+    let sut = fallback_default_branch(&repository);
+
+    assert_eq!(sut, None);
+}
+
+#[test]
+fn not_deleted_starts_with_current_branch() {
+    let plan = CleanupPlan {
+        current: Some(CurrentBranch::NoTarget {
+            name: RefName(b"current"),
+        }),
+        branches: vec![RefName(b"first"), RefName(b"second")],
+    };
+
+    let sut = plan.not_deleted(&[RefName(b"first")]);
+
+    assert_eq!(sut, [RefName(b"current"), RefName(b"second")]);
+}
+
+#[test]
+fn policy_keeps_protected_and_other_remotes() {
+    let repository = git::repository! {
+        *tracking { "main", remote("main", "origin", gone) },
+        tracking { "release/1.0", remote("release/1.0", "origin", gone) },
+        tracking { "fork", remote("fork", "upstream", gone) },
+        tracking { "feature", remote("feature", "origin", gone) },
+    };
+    let policy = Policy {
+        protected: vec![Pattern::new("main"), Pattern::new("release/*")],
+        remote: Some(b"origin".to_vec()),
+    };
+
+    // Git is never queried, since the current branch is protected
+    let sut =
+        CleanupPlan::with_policy(std::path::Path::new("/"), &repository, None, &policy).unwrap();
+
+    let expected = CleanupPlan {
+        current: None,
+        branches: vec![RefName(b"feature")],
+    };
+
+    assert_eq!(sut, expected);
+}
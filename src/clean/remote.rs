@@ -0,0 +1,250 @@
+use super::FALLBACK_DEFAULT_BRANCHES;
+use crate::classify::{self, Pattern};
+use crate::execute::{self, Location};
+use crate::git::{self, GitError, RefName, RemoteBranchInfo};
+use std::time::{SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Which branches of a remote a remote cleanup deletes, worked out from the tracking refs of the last fetch
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemotePrunePlan {
+    pub remote: Vec<u8>,
+    /// The branch of the remote the deleted branches are merged into
+    pub base: Vec<u8>,
+    /// Branches on the remote, sorted by name so that runs are reproducible
+    pub branches: Vec<PlannedBranch>,
+}
+
+/// A branch a remote cleanup deletes, with the commit its tracking ref pointed to when planning
+///
+/// The deletion is refused if the branch moved on the remote since, so that no one's commits are lost
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlannedBranch {
+    pub name: Vec<u8>,
+    pub commit: String,
+}
+
+/// What a remote cleanup did, which matters most when git refused some of the deletions
+#[derive(Debug)]
+pub struct RemotePruneOutcome {
+    pub deleted: Vec<Vec<u8>>,
+    pub failures: Vec<BatchFailure>,
+}
+
+/// A batch git failed to delete, with the branches of the batch that are still on the remote
+#[derive(Debug)]
+pub struct BatchFailure {
+    pub branches: Vec<Vec<u8>>,
+    pub error: GitError,
+}
+
+#[derive(Error, Debug)]
+pub enum RemotePruneError {
+    #[error("No remote named {remote}")]
+    UnknownRemote { remote: String },
+
+    #[error("Remote {remote} has no default branch, nor a main or master branch, to compare the others with")]
+    NoBase { remote: String },
+
+    #[error("Remote {remote} has no branch {base}")]
+    MissingBase { remote: String, base: String },
+
+    #[error(transparent)]
+    Git(#[from] GitError),
+}
+
+impl RemotePrunePlan {
+    /// Plans the deletion of the branches of remote merged into base and at least min_age_days old
+    ///
+    /// Base defaults to the default branch of the remote, then main or master. Neither base nor the default
+    /// branch of the remote are ever deleted, nor are branches matching the protected patterns
+    pub fn new(
+        path: &(impl Location + ?Sized),
+        remote: RefName,
+        base: Option<&[u8]>,
+        min_age_days: u64,
+        protected: &[Pattern],
+    ) -> Result<Self, RemotePruneError> {
+        if !git::remotes(&path)?
+            .iter()
+            .any(|name| name == remote.as_bytes())
+        {
+            return Err(RemotePruneError::UnknownRemote {
+                remote: remote.to_string(),
+            });
+        }
+
+        let default = git::remote_default_branch(&path, remote);
+
+        let base = match base.map(<[u8]>::to_vec).or_else(|| default.clone()) {
+            Some(base) => base,
+            None => fallback_base(path, remote)?,
+        };
+
+        let branches = git::remote_branches(&path, remote, RefName(&base))?;
+
+        if !branches.iter().any(|branch| branch.name == base) {
+            return Err(RemotePruneError::MissingBase {
+                remote: remote.to_string(),
+                base: RefName(&base).to_string(),
+            });
+        }
+
+        let mut protected = protected.to_vec();
+        protected.extend(default.into_iter().map(Pattern::new));
+        protected.push(Pattern::new(base.as_slice()));
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs());
+
+        Ok(RemotePrunePlan {
+            remote: remote.as_bytes().to_vec(),
+            base,
+            branches: prunable(&branches, now, min_age_days, &protected),
+        })
+    }
+
+    /// Deletes the branches from the remote, batch_size at a time, until interrupted
+    ///
+    /// A batch git refuses is reported, and the next one is still pushed
+    pub fn run(&self, path: &(impl Location + ?Sized), batch_size: usize) -> RemotePruneOutcome {
+        let remote = RefName(&self.remote);
+        let mut outcome = RemotePruneOutcome {
+            deleted: Vec::new(),
+            failures: Vec::new(),
+        };
+
+        for batch in self.branches.chunks(batch_size.max(1)) {
+            if execute::is_interrupted() {
+                break;
+            }
+
+            let leases: Vec<(RefName, &str)> = batch
+                .iter()
+                .map(|branch| (RefName(&branch.name), branch.commit.as_str()))
+                .collect();
+            let names = batch.iter().map(|branch| branch.name.clone());
+
+            let error = match git::push_delete(&path, remote, &leases) {
+                Ok(()) => {
+                    outcome.deleted.extend(names);
+                    continue;
+                }
+                Err(error) => error,
+            };
+
+            // Git deletes what it can and drops the tracking refs of what it deleted, which tells them apart
+            let remaining = git::tracking_refs(&path, Some(remote)).ok();
+            let (kept, deleted): (Vec<_>, Vec<_>) = names.partition(|name| {
+                let tracking_ref = [remote.as_bytes(), b"/", name].concat();
+                remaining
+                    .as_ref()
+                    .is_none_or(|remaining| remaining.contains(&tracking_ref))
+            });
+
+            outcome.deleted.extend(deleted);
+            outcome.failures.push(BatchFailure {
+                branches: kept,
+                error,
+            });
+        }
+
+        outcome
+    }
+}
+
+fn fallback_base(
+    path: &(impl Location + ?Sized),
+    remote: RefName,
+) -> Result<Vec<u8>, RemotePruneError> {
+    let tracking_refs = git::tracking_refs(&path, Some(remote))?;
+
+    FALLBACK_DEFAULT_BRANCHES
+        .into_iter()
+        .find(|fallback| {
+            let tracking_ref = [remote.as_bytes(), b"/", fallback.as_bytes()].concat();
+            tracking_refs.contains(&tracking_ref)
+        })
+        .map(|fallback| fallback.as_bytes().to_vec())
+        .ok_or_else(|| RemotePruneError::NoBase {
+            remote: remote.to_string(),
+        })
+}
+
+fn prunable(
+    branches: &[RemoteBranchInfo],
+    now: u64,
+    min_age_days: u64,
+    protected: &[Pattern],
+) -> Vec<PlannedBranch> {
+    let min_age = min_age_days.saturating_mul(SECONDS_PER_DAY);
+
+    let mut planned: Vec<PlannedBranch> = branches
+        .iter()
+        .filter(|branch| branch.merged)
+        .filter(|branch| now.saturating_sub(branch.committer_timestamp) >= min_age)
+        .filter(|branch| !classify::is_protected(RefName(&branch.name), protected))
+        .map(|branch| PlannedBranch {
+            name: branch.name.clone(),
+            commit: branch.commit.clone(),
+        })
+        .collect();
+
+    planned.sort_by(|left, right| left.name.cmp(&right.name));
+    planned
+}
+
+#[cfg(test)]
+fn remote_branch(name: &str, days_ago: u64, merged: bool) -> RemoteBranchInfo {
+    RemoteBranchInfo {
+        name: name.as_bytes().to_vec(),
+        commit: format!("{name} commit"),
+        committer_timestamp: 1_700_000_000 - days_ago * SECONDS_PER_DAY,
+        merged,
+    }
+}
+
+#[test]
+fn prunable_merged_and_old_enough() {
+    let branches = [
+        remote_branch("main", 100, true),
+        remote_branch("old", 40, true),
+        remote_branch("recent", 10, true),
+        remote_branch("unmerged", 100, false),
+        remote_branch("release/1.0", 100, true),
+        remote_branch("fix/login", 30, true),
+    ];
+    let protected = [Pattern::new("main"), Pattern::new("release/*")];
+
+    let sut = prunable(&branches, 1_700_000_000, 30, &protected);
+
+    let expected = [
+        PlannedBranch {
+            name: b"fix/login".to_vec(),
+            commit: "fix/login commit".to_string(),
+        },
+        PlannedBranch {
+            name: b"old".to_vec(),
+            commit: "old commit".to_string(),
+        },
+    ];
+
+    assert_eq!(sut, expected);
+}
+
+#[test]
+fn prunable_any_age() {
+    let branches = [remote_branch("today", 0, true)];
+
+    let sut = prunable(&branches, 1_700_000_000, 0, &[]);
+
+    let expected = [PlannedBranch {
+        name: b"today".to_vec(),
+        commit: "today commit".to_string(),
+    }];
+
+    assert_eq!(sut, expected);
+}
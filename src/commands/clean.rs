@@ -1,68 +1,223 @@
-use crate::args::CleanOption;
-use crate::execute;
-use crate::git::{Branch, RemoteBranch, RemoteBranchStatus, Repository};
+use crate::args::{self, CleanArguments, CleanOption};
+use git_cleanup::clean::{CleanupError, CleanupPlan, CurrentBranch, Policy, Step};
+use git_cleanup::execute::{self, Context, ExecuteError};
+use git_cleanup::git::{self, GitError, OrphanReason, OrphanedRef, RefName, Repository};
 use std::io;
-use std::path::Path;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+pub fn clean(path: &Context, repository: &Repository, arguments: &CleanArguments) {
+    let switch_to = arguments.switch_to.as_deref();
+    let _ = clean_with_policy(path, repository, arguments, switch_to, &Policy::default());
+}
+
+// What a cleanup did, for the summary of a cleanup of many repositories
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Cleaned {
+    pub deleted: usize,
+    // The cleanup was aborted by an error, which is already printed
+    pub failed: bool,
+}
+
+impl Cleaned {
+    fn failed(deleted: usize) -> Self {
+        Cleaned {
+            deleted,
+            failed: true,
+        }
+    }
+}
+
+pub fn clean_with_policy(
+    path: &Context,
+    repository: &Repository,
+    arguments: &CleanArguments,
+    switch_to: Option<&str>,
+    policy: &Policy,
+) -> Cleaned {
+    let option = &arguments.option;
 
-pub fn clean(path: &Path, repository: Repository, option: &CleanOption) {
     if *option == CleanOption::Help {
-        print_help();
-        return;
+        print!("{}", args::command_help("clean"));
+        return Cleaned::default();
     }
 
-    for branch in repository.branches.iter() {
-        match branch {
-            Branch::Tracking {
-                name,
-                remote:
-                    RemoteBranch {
-                        status: RemoteBranchStatus::Gone,
-                        ..
-                    },
-                ..
-            } => {
-                if *option == CleanOption::Step && !notify_step(name) {
-                    break;
-                }
+    if arguments.remote_refs {
+        return clean_remote_refs(path, option, arguments.untracked);
+    }
 
-                if !delete_branch(path, name) {
-                    break;
-                }
-            }
-            _ => continue,
+    let switch_to = switch_to.map(str::as_bytes);
+
+    let plan = match CleanupPlan::with_policy(path, repository, switch_to, policy) {
+        Ok(plan) => plan,
+        Err(error) => {
+            println!(
+                "An error occurred while checking the working tree, aborting cleanup: {error}"
+            );
+            return Cleaned::failed(0);
+        }
+    };
+
+    match &plan.current {
+        Some(CurrentBranch::NoTarget { name }) => {
+            println!("Current branch {name} is gone, but there is no other branch to switch to. Use --switch-to <BRANCH> to delete it");
         }
+        Some(CurrentBranch::Dirty { name, target }) => {
+            let target = RefName(target);
+            println!("Current branch {name} is gone, but the working tree has uncommitted changes. Refusing to switch to {target}");
+        }
+        Some(CurrentBranch::Bare { name }) => {
+            println!("Current branch {name} is gone, but it is the HEAD of this bare repository, which its clones check out. Point HEAD to another branch to delete it");
+        }
+        Some(CurrentBranch::Switch { .. }) | None => {}
+    }
+
+    let outcome = plan.run(path, |step| {
+        *option != CleanOption::Step || confirm_step(step)
+    });
+
+    if let Some(error) = &outcome.error {
+        print_error(error);
+    }
+
+    if execute::is_interrupted() {
+        print_interrupted(&outcome.deleted, &plan.not_deleted(&outcome.deleted));
     }
-}
 
-// We want to keep the alignment equal among all strings so that we can visually identify wrong indentation
-#[rustfmt::skip]
-fn print_help() {
-    println!("Options:");
-    println!("    --help:      Print clean options");
-    println!("    --step:      Ask for user confirmation before deleting each branch (default option)");
-    println!("    --automatic: Delete branches without asking for user confirmation");
+    Cleaned {
+        deleted: outcome.deleted.len(),
+        failed: outcome.error.is_some(),
+    }
 }
 
-fn delete_branch(path: &Path, branch_name: &str) -> bool {
-    let result = execute::execute(&path, "git", &["branch", "-d", branch_name]);
+/*
+    Orphaned refs are deleted without fetching, so this works when the remote can't be reached
+    The policy is about branches, so it doesn't apply to them. Like branches, each one is confirmed with --step
+*/
+fn clean_remote_refs(path: &Context, option: &CleanOption, untracked: bool) -> Cleaned {
+    let orphans = match super::orphaned_refs(path, untracked) {
+        Ok(orphans) => orphans,
+        Err(error) => {
+            println!(
+                "An error occurred while listing remote-tracking refs, aborting cleanup: {error}"
+            );
+            return Cleaned::failed(0);
+        }
+    };
+
+    let mut deleted = Vec::new();
+    let mut failed = false;
+
+    for orphan in &orphans {
+        let name = RefName(&orphan.name);
+
+        if execute::is_interrupted()
+            || *option == CleanOption::Step && !confirm(&orphan_message(orphan))
+        {
+            break;
+        }
+
+        if let Err(error) = git::delete_tracking_ref(&path, name) {
+            println!("An error occurred while deleting remote-tracking ref {name}, aborting cleanup: {error}");
+            failed = true;
+            break;
+        }
+
+        deleted.push(name);
+    }
+
+    if execute::is_interrupted() {
+        let not_deleted: Vec<RefName> = orphans
+            .iter()
+            .map(|orphan| RefName(&orphan.name))
+            .filter(|name| !deleted.contains(name))
+            .collect();
 
-    if result.is_err() {
-        println!("An error occurred while deleting branch {branch_name}, aborting cleanup")
+        print_interrupted(&deleted, &not_deleted);
     }
 
-    result.is_ok()
+    Cleaned {
+        deleted: deleted.len(),
+        failed,
+    }
 }
 
-fn notify_step(branch_name: &str) -> bool {
-    println!("About to delete branch {branch_name}, type y and press enter to continue");
+fn orphan_message(orphan: &OrphanedRef) -> String {
+    let reason = match orphan.reason {
+        OrphanReason::Untracked => "which no local branch tracks",
+        OrphanReason::UnknownRemote => "whose remote is no longer configured",
+    };
 
-    let mut line = String::new();
-    let result = io::stdin().read_line(&mut line);
+    format!(
+        "About to delete remote-tracking ref {}, {reason}, type y and press enter to continue",
+        RefName(&orphan.name)
+    )
+}
 
-    if result.is_err() {
-        println!("An error occured, aborting cleanup");
-        return false;
+fn confirm_step(step: Step) -> bool {
+    let message = match step {
+        Step::Switch { name, target } => format!(
+            "Current branch {name} is gone. About to switch to {target} and delete {name}, type y and press enter to continue"
+        ),
+        Step::Delete { name } => {
+            format!("About to delete branch {name}, type y and press enter to continue")
+        }
+    };
+
+    confirm(&message)
+}
+
+fn print_error(error: &CleanupError) {
+    match error {
+        CleanupError::Switch { target, source } => {
+            println!(
+                "An error occurred while switching to branch {target}, aborting cleanup: {source}"
+            );
+        }
+        CleanupError::Delete {
+            branch,
+            source:
+                GitError::Query {
+                    source: ExecuteError::BranchNotFullyMerged { .. },
+                },
+        } => {
+            println!("Branch {branch} is not fully merged, aborting cleanup. Use git branch -D {branch} to delete it anyway");
+        }
+        CleanupError::Delete { branch, source } => {
+            println!(
+                "An error occurred while deleting branch {branch}, aborting cleanup: {source}"
+            );
+        }
+    }
+}
+
+fn print_interrupted(deleted: &[RefName], not_deleted: &[RefName]) {
+    println!("Cleanup interrupted");
+    println!("    Deleted branches: {}", names_or_none(deleted));
+    println!("    Branches not deleted: {}", names_or_none(not_deleted));
+}
+
+fn names_or_none(names: &[RefName]) -> String {
+    if names.is_empty() {
+        "none".to_string()
+    } else {
+        let names: Vec<_> = names.iter().map(|name| name.to_string_lossy()).collect();
+        names.join(", ")
     }
+}
+
+pub(super) fn confirm(message: &str) -> bool {
+    println!("{message}");
+
+    let line = match read_answer() {
+        Answer::Line(line) => line,
+        Answer::Interrupted => return false,
+        Answer::Error => {
+            println!("An error occured, aborting cleanup");
+            return false;
+        }
+    };
 
     if line != "y\n" {
         println!("Understood, aborting cleanup");
@@ -71,3 +226,32 @@ fn notify_step(branch_name: &str) -> bool {
 
     true
 }
+
+enum Answer {
+    Line(String),
+    Interrupted,
+    Error,
+}
+
+// Stdin is read in another thread, since a blocked read would otherwise keep Ctrl-C from aborting the prompt
+fn read_answer() -> Answer {
+    let (sender, receiver) = mpsc::channel();
+
+    thread::spawn(move || {
+        let mut line = String::new();
+        let result = io::stdin().read_line(&mut line).map(|_| line);
+        let _ = sender.send(result);
+    });
+
+    loop {
+        match receiver.recv_timeout(Duration::from_millis(50)) {
+            Ok(Ok(line)) => return Answer::Line(line),
+            Ok(Err(_)) | Err(RecvTimeoutError::Disconnected) => return Answer::Error,
+            Err(RecvTimeoutError::Timeout) => {
+                if execute::is_interrupted() {
+                    return Answer::Interrupted;
+                }
+            }
+        }
+    }
+}
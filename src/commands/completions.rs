@@ -0,0 +1,418 @@
+use crate::args::{
+    self, visible_commands, CompleteOption, CompletionsOption, OptionSpec, ValueKind,
+    GLOBAL_OPTIONS,
+};
+use git_cleanup::execute::Context;
+use git_cleanup::git::{self, Branch, GitQuery, Head};
+use std::io::{self, Write};
+
+/*
+    Completion scripts are generated from the same grammar the parser uses, so they never drift
+    Values that depend on the repository, like branch names, are completed by calling back
+    into the binary through the hidden __complete command
+*/
+const BINARY: &str = "git-cleanup";
+
+// Forwarded to __complete, so that values come from the repository the completed command will run in
+const REPOSITORY_OPTIONS: &[&str] = &["--path", "--git-dir"];
+
+pub fn completions(option: &CompletionsOption) {
+    match option {
+        CompletionsOption::Help => print!("{}", args::command_help("completions")),
+        CompletionsOption::Bash => print!("{}", bash()),
+        CompletionsOption::Zsh => print!("{}", zsh()),
+        CompletionsOption::Fish => print!("{}", fish()),
+    }
+}
+
+// Errors are ignored, since shells can't do anything useful with them while completing
+pub fn complete(path: &Context, option: &CompleteOption) {
+    let names = match option {
+        CompleteOption::Branches => branch_names(path),
+        CompleteOption::Remotes => git::remotes(&path).ok(),
+    };
+
+    let Some(mut names) = names else {
+        return;
+    };
+
+    names.sort();
+
+    // Names are written unchanged, so that a completed name is found by git
+    let mut stdout = io::stdout().lock();
+    for name in names {
+        let _ = stdout.write_all(&name);
+        let _ = stdout.write_all(b"\n");
+    }
+}
+
+fn branch_names(path: &Context) -> Option<Vec<Vec<u8>>> {
+    let query = GitQuery::query(&path).ok()?;
+    let repository = query.to_repository().ok()?;
+
+    let head = match &repository.head {
+        Head::Branch(branch) => Some(branch),
+        Head::Detached | Head::Unborn => None,
+    };
+
+    let names = head
+        .into_iter()
+        .chain(&repository.branches)
+        .map(|branch| match branch {
+            Branch::Tracking { name, .. } | Branch::Local { name } => name.as_bytes().to_vec(),
+        })
+        .collect();
+
+    Some(names)
+}
+
+fn option_names(options: &[OptionSpec]) -> Vec<&'static str> {
+    options
+        .iter()
+        .flat_map(|option| std::iter::once(option.name).chain(option.short))
+        .collect()
+}
+
+fn repository_options() -> Vec<&'static str> {
+    GLOBAL_OPTIONS
+        .iter()
+        .filter(|option| REPOSITORY_OPTIONS.contains(&option.name))
+        .flat_map(|option| std::iter::once(option.name).chain(option.short))
+        .collect()
+}
+
+// The same options given as --option=value, which carry their value in the same word
+fn inline_repository_options() -> Vec<String> {
+    REPOSITORY_OPTIONS
+        .iter()
+        .map(|name| format!("{name}=*"))
+        .collect()
+}
+
+fn all_options() -> impl Iterator<Item = &'static OptionSpec> {
+    GLOBAL_OPTIONS
+        .iter()
+        .chain(visible_commands().flat_map(|command| command.options))
+}
+
+// Shell snippet that lists candidate words for a value, or None if the value can't be completed
+fn bash_value(kind: &ValueKind) -> Option<String> {
+    match kind {
+        ValueKind::ExistingPath => Some("compgen -d -- \"$cur\"".to_string()),
+        ValueKind::File => Some("compgen -f -- \"$cur\"".to_string()),
+        ValueKind::Number => None,
+        ValueKind::Branch => Some(format!(
+            "compgen -W \"$({BINARY} \"${{repository[@]}}\" __complete branches 2>/dev/null)\" -- \"$cur\""
+        )),
+        ValueKind::Remote => Some(format!(
+            "compgen -W \"$({BINARY} \"${{repository[@]}}\" __complete remotes 2>/dev/null)\" -- \"$cur\""
+        )),
+        ValueKind::OneOf(values) => {
+            Some(format!("compgen -W \"{}\" -- \"$cur\"", values.join(" ")))
+        }
+    }
+}
+
+fn bash() -> String {
+    let commands: Vec<&str> = visible_commands().map(|command| command.name).collect();
+    let globals = option_names(GLOBAL_OPTIONS);
+
+    let mut script = String::from("_git_cleanup() {\n");
+    script.push_str("    local cur=\"${COMP_WORDS[COMP_CWORD]}\"\n");
+    script.push_str("    local prev=\"${COMP_WORDS[COMP_CWORD-1]}\"\n");
+    script.push_str("    local command=\"\" i\n");
+    script.push_str("    local -a repository=()\n\n");
+
+    /*
+        Values are passed the way they were typed, except for a leading ~ which the shell would have expanded
+        Bash splits --option=value into three words, the middle one being =
+    */
+    script.push_str("    for ((i = 1; i < COMP_CWORD; i++)); do\n");
+    script.push_str("        case \"${COMP_WORDS[i]}\" in\n");
+    script.push_str(&format!(
+        "            {})\n",
+        repository_options().join("|")
+    ));
+    script.push_str("                local value=\"${COMP_WORDS[i+1]}\"\n");
+    script.push_str("                [[ \"$value\" == \"=\" ]] && value=\"${COMP_WORDS[i+2]}\"\n");
+    script.push_str(
+        "                repository+=(\"${COMP_WORDS[i]}\" \"${value/#\\~/$HOME}\") ;;\n",
+    );
+    script.push_str(&format!(
+        "            {}) [[ -z \"$command\" ]] && command=\"${{COMP_WORDS[i]}}\" ;;\n",
+        commands.join("|")
+    ));
+    script.push_str("        esac\n");
+    script.push_str("    done\n\n");
+
+    script.push_str("    case \"$prev\" in\n");
+    for option in all_options() {
+        let Some(value) = &option.value else {
+            continue;
+        };
+
+        let names = option_names(std::slice::from_ref(option)).join("|");
+
+        match bash_value(&value.kind) {
+            Some(words) => script.push_str(&format!(
+                "        {names}) COMPREPLY=($({words})); return ;;\n"
+            )),
+            None => script.push_str(&format!("        {names}) return ;;\n")),
+        }
+    }
+    script.push_str("    esac\n\n");
+
+    script.push_str("    case \"$command\" in\n");
+    for command in visible_commands() {
+        let mut words = option_names(command.options);
+        words.extend(&globals);
+
+        if let Some(ValueKind::OneOf(values)) = command.positional.as_ref().map(|value| &value.kind)
+        {
+            words.extend(values.iter());
+        }
+
+        script.push_str(&format!(
+            "        {}) COMPREPLY=($(compgen -W \"{}\" -- \"$cur\")) ;;\n",
+            command.name,
+            words.join(" ")
+        ));
+    }
+    script.push_str(&format!(
+        "        *) COMPREPLY=($(compgen -W \"{} {}\" -- \"$cur\")) ;;\n",
+        commands.join(" "),
+        globals.join(" ")
+    ));
+    script.push_str("    esac\n");
+    script.push_str("}\n\n");
+
+    script.push_str(&format!("complete -F _git_cleanup {BINARY}\n"));
+
+    script
+}
+
+fn zsh_value(kind: &ValueKind) -> Option<String> {
+    match kind {
+        ValueKind::ExistingPath => Some("_files -/".to_string()),
+        ValueKind::File => Some("_files".to_string()),
+        ValueKind::Number => None,
+        ValueKind::Branch => Some(format!(
+            "compadd -- ${{(f)\"$({BINARY} \"${{repository[@]}}\" __complete branches 2>/dev/null)\"}}"
+        )),
+        ValueKind::Remote => Some(format!(
+            "compadd -- ${{(f)\"$({BINARY} \"${{repository[@]}}\" __complete remotes 2>/dev/null)\"}}"
+        )),
+        ValueKind::OneOf(values) => Some(format!("compadd -- {}", values.join(" "))),
+    }
+}
+
+fn zsh() -> String {
+    let commands: Vec<&str> = visible_commands().map(|command| command.name).collect();
+    let globals = option_names(GLOBAL_OPTIONS);
+
+    let mut script = format!("#compdef {BINARY}\n\n");
+    script.push_str("_git-cleanup() {\n");
+    script.push_str(&format!(
+        "    local command=${{words[(r)({})]}}\n",
+        commands.join("|")
+    ));
+    script.push_str("    local -a repository\n");
+    script.push_str("    local i\n\n");
+
+    // Words keep the quoting they were typed with, which (Q) removes, before expanding a leading ~
+    script.push_str("    for ((i = 2; i < CURRENT; i++)); do\n");
+    script.push_str("        case \"$words[i]\" in\n");
+    script.push_str(&format!(
+        "            {}) repository+=(\"$words[i]\" \"${{${{(Q)words[i+1]}}/#\\~/$HOME}}\") ;;\n",
+        repository_options().join("|")
+    ));
+    script.push_str(&format!(
+        "            {}) repository+=(\"${{(Q)words[i]}}\") ;;\n",
+        inline_repository_options().join("|")
+    ));
+    script.push_str("        esac\n");
+    script.push_str("    done\n\n");
+
+    script.push_str("    case \"$words[CURRENT-1]\" in\n");
+    for option in all_options() {
+        let Some(value) = &option.value else {
+            continue;
+        };
+
+        let names = option_names(std::slice::from_ref(option)).join("|");
+
+        match zsh_value(&value.kind) {
+            Some(action) => script.push_str(&format!("        {names}) {action}; return ;;\n")),
+            None => script.push_str(&format!("        {names}) return ;;\n")),
+        }
+    }
+    script.push_str("    esac\n\n");
+
+    script.push_str("    case \"$command\" in\n");
+    for command in visible_commands() {
+        let mut words = option_names(command.options);
+        words.extend(&globals);
+
+        if let Some(ValueKind::OneOf(values)) = command.positional.as_ref().map(|value| &value.kind)
+        {
+            words.extend(values.iter());
+        }
+
+        script.push_str(&format!(
+            "        {}) compadd -- {} ;;\n",
+            command.name,
+            words.join(" ")
+        ));
+    }
+    script.push_str(&format!(
+        "        *) compadd -- {} {} ;;\n",
+        commands.join(" "),
+        globals.join(" ")
+    ));
+    script.push_str("    esac\n");
+    script.push_str("}\n\n");
+
+    // Works both when sourced and when autoloaded from $fpath
+    script.push_str("if [ \"$funcstack[1]\" = \"_git-cleanup\" ]; then\n");
+    script.push_str("    _git-cleanup \"$@\"\n");
+    script.push_str("else\n");
+    script.push_str(&format!("    compdef _git-cleanup {BINARY}\n"));
+    script.push_str("fi\n");
+
+    script
+}
+
+fn fish_value(kind: &ValueKind) -> String {
+    match kind {
+        ValueKind::ExistingPath => " -x -a '(__fish_complete_directories)'".to_string(),
+        ValueKind::File => " -r -F".to_string(),
+        ValueKind::Number => " -x".to_string(),
+        ValueKind::Branch => " -x -a '(__git_cleanup_complete branches)'".to_string(),
+        ValueKind::Remote => " -x -a '(__git_cleanup_complete remotes)'".to_string(),
+        ValueKind::OneOf(values) => format!(" -x -a '{}'", values.join(" ")),
+    }
+}
+
+// Fish wants option names without dashes, single dash options being short options
+fn fish_option(option: &OptionSpec) -> String {
+    let mut names = String::new();
+
+    for name in std::iter::once(option.name).chain(option.short) {
+        match name.strip_prefix("--") {
+            Some(long) => names.push_str(&format!(" -l {long}")),
+            None => names.push_str(&format!(" -s {}", name.trim_start_matches('-'))),
+        }
+    }
+
+    match &option.value {
+        Some(value) => names + &fish_value(&value.kind),
+        None => names,
+    }
+}
+
+fn fish() -> String {
+    let commands: Vec<&str> = visible_commands().map(|command| command.name).collect();
+
+    let mut script = format!("complete -c {BINARY} -f\n\n");
+
+    // Tokens are already unquoted and expanded by commandline -o
+    script.push_str("function __git_cleanup_complete\n");
+    script.push_str("    set -l tokens (commandline -opc)\n");
+    script.push_str("    set -l repository\n");
+    script.push_str("    for i in (seq 2 (count $tokens))\n");
+    script.push_str("        switch $tokens[$i]\n");
+    script.push_str(&format!(
+        "            case {}\n",
+        repository_options().join(" ")
+    ));
+    script.push_str("                if test $i -lt (count $tokens)\n");
+    script.push_str("                    set -a repository $tokens[$i] $tokens[(math $i + 1)]\n");
+    script.push_str("                end\n");
+    script.push_str(&format!(
+        "            case {}\n",
+        inline_repository_options().join(" ")
+    ));
+    script.push_str("                set -a repository $tokens[$i]\n");
+    script.push_str("        end\n");
+    script.push_str("    end\n");
+    script.push_str(&format!(
+        "    {BINARY} $repository __complete $argv 2>/dev/null\n"
+    ));
+    script.push_str("end\n\n");
+
+    for command in &commands {
+        script.push_str(&format!(
+            "complete -c {BINARY} -n '__fish_use_subcommand' -a {command}\n"
+        ));
+    }
+
+    for option in GLOBAL_OPTIONS {
+        script.push_str(&format!("complete -c {BINARY}{}\n", fish_option(option)));
+    }
+
+    for command in visible_commands() {
+        let condition = format!("-n '__fish_seen_subcommand_from {}'", command.name);
+
+        for option in command.options {
+            script.push_str(&format!(
+                "complete -c {BINARY} {condition}{}\n",
+                fish_option(option)
+            ));
+        }
+
+        if let Some(value) = &command.positional {
+            script.push_str(&format!(
+                "complete -c {BINARY} {condition}{}\n",
+                fish_value(&value.kind)
+            ));
+        }
+    }
+
+    script
+}
+
+#[test]
+fn bash_completes_every_command() {
+    let sut = bash();
+
+    for command in visible_commands() {
+        assert!(sut.contains(&format!("        {}) COMPREPLY", command.name)));
+    }
+
+    assert!(!sut.contains("__complete)"));
+    assert!(sut.contains(
+        "--switch-to) COMPREPLY=($(compgen -W \"$(git-cleanup \"${repository[@]}\" __complete branches"
+    ));
+    assert!(sut.contains("            --path|-C|--git-dir)\n"));
+    assert!(sut.contains("--path|-C) COMPREPLY=($(compgen -d"));
+}
+
+#[test]
+fn zsh_completes_formats() {
+    let sut = zsh();
+
+    assert!(sut.starts_with("#compdef git-cleanup\n"));
+    assert!(sut.contains("--format) compadd -- text porcelain markdown csv json; return ;;"));
+}
+
+#[test]
+fn fish_short_and_long_options() {
+    let sut = fish();
+
+    assert!(sut
+        .contains("complete -c git-cleanup -l path -s C -x -a '(__fish_complete_directories)'\n"));
+    assert!(sut.contains("complete -c git-cleanup -n '__fish_seen_subcommand_from list' -s z\n"));
+    assert!(sut.contains("complete -c git-cleanup -n '__fish_seen_subcommand_from completions' -x -a 'bash zsh fish'\n"));
+}
+
+#[test]
+fn complete_in_the_repository_of_the_command_line() {
+    let sut = fish();
+
+    assert!(sut.contains("            case --path -C --git-dir\n"));
+    assert!(sut.contains("            case --path=* --git-dir=*\n"));
+    assert!(sut.contains("    git-cleanup $repository __complete $argv 2>/dev/null\n"));
+    assert!(sut.contains("-l switch-to -x -a '(__git_cleanup_complete branches)'\n"));
+
+    assert!(zsh().contains("            --path|-C|--git-dir) repository+=("));
+}
@@ -0,0 +1,38 @@
+use crate::args::{self, DiffOption};
+use git_cleanup::execute::Context;
+use git_cleanup::git::{Repository, Snapshot, SnapshotError};
+use git_cleanup::history::{self, HistoryError};
+use git_cleanup::report;
+
+// The current state is recorded once shown, so that the next diff starts from here
+pub fn diff(
+    path: &Context,
+    repository: &Repository,
+    option: &DiffOption,
+) -> Result<(), HistoryError> {
+    if *option == DiffOption::Help {
+        print!("{}", args::command_help("diff"));
+        return Ok(());
+    }
+
+    let current = Snapshot::from(repository);
+
+    // A state written by another version, or damaged, would otherwise fail every diff until deleted by hand
+    match history::load_state(path) {
+        Ok(Some(recorded)) => print!(
+            "{}",
+            report::changes_text(&history::changes(&recorded, &current))
+        ),
+        Ok(None) => {
+            println!("No recorded state yet, the current branches are recorded for the next diff")
+        }
+        Err(HistoryError::Snapshot(
+            error @ (SnapshotError::Version { .. } | SnapshotError::Format { .. }),
+        )) => {
+            println!("The recorded state can't be read ({error}), the current branches are recorded for the next diff")
+        }
+        Err(error) => return Err(error),
+    }
+
+    history::save_state(path, &current)
+}
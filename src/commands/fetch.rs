@@ -0,0 +1,155 @@
+use git_cleanup::execute::{self, Context};
+use git_cleanup::git::{self, GitError, RefName, Snapshot};
+use git_cleanup::history::{self, HistoryError};
+
+// The remotes --fetch-prune fetches
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Remotes {
+    // The one git fetch picks by itself, usually origin
+    Default,
+    Named(Vec<Vec<u8>>),
+    All,
+}
+
+// What a --fetch-prune did, including whether the branches could be recorded before
+#[derive(Debug)]
+pub struct Fetched {
+    pub recorded: Result<(), HistoryError>,
+    pub remotes: Vec<FetchedRemote>,
+}
+
+// What fetching one remote pruned, or why it couldn't be fetched. No name stands for the default remote
+#[derive(Debug)]
+pub struct FetchedRemote {
+    pub remote: Option<Vec<u8>>,
+    pub pruned: Result<Vec<Vec<u8>>, GitError>,
+}
+
+/*
+    Named remotes are fetched by a single git fetch, up to --jobs at once, rather than by the worker pool,
+    since fetches running side by side in the same repository fail to lock the refs they update
+    An unreachable remote is still reported on its own, while the others are fetched
+*/
+pub fn fetch_prune(path: &Context, remotes: &Remotes) -> Result<Fetched, GitError> {
+    let recorded = record_first_state(path);
+
+    let names = match remotes {
+        Remotes::Default => {
+            let remotes = vec![FetchedRemote {
+                remote: None,
+                pruned: git::fetch_prune(&path, None),
+            }];
+
+            return Ok(Fetched { recorded, remotes });
+        }
+        Remotes::Named(names) => names.clone(),
+        Remotes::All => git::remotes(&path)?,
+    };
+
+    let refs: Vec<RefName> = names.iter().map(|name| RefName(name)).collect();
+    let pruned = git::fetch_prune_multiple(&path, &refs, execute::jobs())?;
+
+    let remotes = names
+        .into_iter()
+        .zip(pruned)
+        .map(|(remote, pruned)| FetchedRemote {
+            remote: Some(remote),
+            pruned,
+        })
+        .collect();
+
+    Ok(Fetched { recorded, remotes })
+}
+
+/*
+    Without a recorded state, the branches are recorded before fetching, so that diff shows which upstreams the fetch pruned
+    A recorded state is kept, since it is where the next diff starts from, and changes since it include the ones of the fetch
+    Failing to record the branches is reported, but doesn't stop the fetch
+*/
+fn record_first_state(path: &Context) -> Result<(), HistoryError> {
+    if history::is_recorded(path)? {
+        return Ok(());
+    }
+
+    history::save_state(path, &Snapshot::query(path)?)
+}
+
+// One line per remote, after the error recording the branches if any
+pub fn fetch_report(fetched: &Fetched) -> String {
+    let mut report = String::new();
+
+    if let Err(error) = &fetched.recorded {
+        report.push_str(&format!(
+            "Error recording the branches before fetching: {error}\n"
+        ));
+    }
+
+    for fetched in &fetched.remotes {
+        let remote = match &fetched.remote {
+            Some(remote) => String::from_utf8_lossy(remote).into_owned(),
+            None => "the default remote".to_string(),
+        };
+
+        let line = match &fetched.pruned {
+            Ok(pruned) if pruned.is_empty() => format!("Nothing pruned from {remote}"),
+            Ok(pruned) => {
+                let names: Vec<_> = pruned
+                    .iter()
+                    .map(|name| String::from_utf8_lossy(name))
+                    .collect();
+
+                format!("Pruned from {remote}: {}", names.join(", "))
+            }
+            Err(error) => format!("Error fetching {remote}: {error}"),
+        };
+
+        report.push_str(&line);
+        report.push('\n');
+    }
+
+    report
+}
+
+#[test]
+fn report_per_remote() {
+    let fetched = Fetched {
+        recorded: Ok(()),
+        remotes: vec![
+            FetchedRemote {
+                remote: Some(b"origin".to_vec()),
+                pruned: Ok(vec![b"origin/old".to_vec(), b"origin/fix/login".to_vec()]),
+            },
+            FetchedRemote {
+                remote: None,
+                pruned: Ok(Vec::new()),
+            },
+        ],
+    };
+
+    let sut = fetch_report(&fetched);
+
+    assert_eq!(
+        sut,
+        "Pruned from origin: origin/old, origin/fix/login\nNothing pruned from the default remote\n"
+    );
+}
+
+#[test]
+fn report_recording_error_first() {
+    let fetched = Fetched {
+        recorded: Err(HistoryError::Snapshot(git::SnapshotError::Version {
+            version: "3".to_string(),
+        })),
+        remotes: vec![FetchedRemote {
+            remote: Some(b"origin".to_vec()),
+            pruned: Ok(Vec::new()),
+        }],
+    };
+
+    let sut = fetch_report(&fetched);
+
+    assert_eq!(
+        sut,
+        "Error recording the branches before fetching: Snapshot format 3 is not supported\nNothing pruned from origin\n"
+    );
+}
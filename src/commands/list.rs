@@ -1,110 +1,216 @@
-use crate::args::ListOption;
-use crate::git::{Branch, Head, RemoteBranch, RemoteBranchStatus, Repository};
-use std::fmt::Display;
-use std::iter::Iterator;
+use crate::args::{self, ListArguments, ListFormat, ListOption};
+use git_cleanup::classify::{self, records, Record};
+use git_cleanup::execute::Context;
+use git_cleanup::git::{
+    self, Branch, BranchInfo, DetachedHead, GitError, Head, OrphanReason, OrphanedRef, Repository,
+};
+use git_cleanup::report::{self, Json, Section};
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
 
-pub fn list(repository: &Repository, option: &ListOption) {
-    match option {
-        ListOption::Help => print_help(),
-        ListOption::All => {
-            print_local(repository);
-            print_tracked(repository);
-        }
-        ListOption::Local => print_local(repository),
-        ListOption::Tracked => print_tracked(repository),
-        ListOption::Gone => print_gone(repository),
-        ListOption::Diverged => print_diverged(repository),
-    }
+#[derive(thiserror::Error, Debug)]
+pub enum ListError {
+    #[error("Error querying branch details")]
+    Git {
+        #[from]
+        source: GitError,
+    },
+
+    #[error("list --remote-refs only supports the text, porcelain and json formats")]
+    RemoteRefsFormat,
+
+    #[error("Error writing list output")]
+    Io {
+        #[from]
+        source: io::Error,
+    },
 }
 
-// We want to keep the alignment equal among all strings so that we can visually identify wrong indentation
-#[rustfmt::skip]
-fn print_help() {
-    println!("Options:");
-    println!("    --help:     Print list options");
-    println!("    --gone:     List tracking branches that are gone from origin (default option)");
-    println!("    --diverged: List tracking branches that diverged from origin");
-    println!("    --all:      List all local and tracked branches");
-    println!("    --tracked:  List all tracked branches");
-    println!("    --local:    List local branches");
+pub fn list(
+    path: &Context,
+    repository: &Repository,
+    arguments: &ListArguments,
+) -> Result<(), ListError> {
+    if arguments.option == ListOption::Help {
+        print!("{}", args::command_help("list"));
+        return Ok(());
+    }
+
+    let mut output = render(path, repository, arguments)?;
+
+    if arguments.format == ListFormat::Json {
+        output.push(b'\n');
+    }
+
+    write_output(arguments.output.as_deref(), &output)
 }
 
-fn print_local(repository: &Repository) {
-    print_branches(repository, "Local branches", |b| {
-        matches!(b, Branch::Local { .. })
-    })
+// The listing of one repository, which is also what a listing of many repositories is made of
+pub fn render(
+    path: &Context,
+    repository: &Repository,
+    arguments: &ListArguments,
+) -> Result<Vec<u8>, ListError> {
+    if let ListOption::RemoteRefs { untracked } = arguments.option {
+        return render_orphans(path, &arguments.format, untracked);
+    }
+
+    let records = branch_records(repository, &arguments.option);
+
+    // Porcelain output keeps branch names byte for byte, the other formats are for reading and show them lossily
+    let output = match arguments.format {
+        ListFormat::Text => {
+            let detached = detached_head(path, repository)?;
+            report::text(
+                repository,
+                detached.as_ref(),
+                sections_of(&arguments.option),
+            )
+            .into_bytes()
+        }
+        ListFormat::Porcelain => report::porcelain(&records, b'\n'),
+        ListFormat::PorcelainNul => report::porcelain(&records, b'\0'),
+        ListFormat::Tree { depth } => report::branch_tree(records, depth).into_bytes(),
+        ListFormat::Markdown => {
+            report::markdown(&records, &BranchInfo::query(&path, &repository.head)?).into_bytes()
+        }
+        ListFormat::Csv => {
+            report::csv(&records, &BranchInfo::query(&path, &repository.head)?).into_bytes()
+        }
+        ListFormat::Json => report::records_json(&records).to_string().into_bytes(),
+    };
+
+    Ok(output)
 }
 
-fn print_tracked(repository: &Repository) {
-    print_branches(repository, "Tracked branches", |b| {
-        matches!(b, Branch::Tracking { .. })
-    })
+// The JSON listing of one repository as a value, which a listing of many repositories embeds in its document
+pub fn render_json(
+    path: &Context,
+    repository: &Repository,
+    arguments: &ListArguments,
+) -> Result<Json, ListError> {
+    if let ListOption::RemoteRefs { untracked } = arguments.option {
+        return Ok(report::orphans_json(&orphaned_refs(path, untracked)?));
+    }
+
+    let records = branch_records(repository, &arguments.option);
+
+    Ok(report::records_json(&records))
 }
 
-fn print_gone(repository: &Repository) {
-    print_branches(repository, "Gone branches", |b| {
-        matches!(
-            b,
-            Branch::Tracking {
-                remote: RemoteBranch {
-                    status: RemoteBranchStatus::Gone,
-                    ..
-                },
-                ..
-            }
-        )
-    })
+fn detached_head(
+    path: &Context,
+    repository: &Repository,
+) -> Result<Option<DetachedHead>, GitError> {
+    match repository.head {
+        Head::Detached => Ok(Some(DetachedHead::query(&path)?)),
+        Head::Branch(_) | Head::Unborn => Ok(None),
+    }
 }
 
-fn print_diverged(repository: &Repository) {
-    print_branches(repository, "Diverged branches", |b| {
-        matches!(
-            b,
-            Branch::Tracking {
-                remote: RemoteBranch {
-                    status: RemoteBranchStatus::Diverged,
-                    ..
-                },
-                ..
-            }
-        )
-    })
+// A detached HEAD is not a branch, so only the text listing shows it, and scripts only get branches
+fn branch_records<'a>(repository: &'a Repository, option: &ListOption) -> Vec<Record<'a>> {
+    let filter = filter_of(option).unwrap_or(classify::is_gone);
+
+    records(repository, filter)
 }
 
-fn print_branches(repository: &Repository, message: &str, filter: impl Fn(&&Branch) -> bool) {
-    println!("{message}:");
+// Remote-tracking refs aren't branches, so they only have the formats that don't need branch details
+fn render_orphans(
+    path: &Context,
+    format: &ListFormat,
+    untracked: bool,
+) -> Result<Vec<u8>, ListError> {
+    let orphans = orphaned_refs(path, untracked)?;
 
-    if let Head::Branch(branch) = &repository.head {
-        if filter(&branch) {
-            println!("    *{}", &repository.head);
+    let output = match format {
+        ListFormat::Text => report::orphans_text(&orphans, reasons_of(untracked)).into_bytes(),
+        ListFormat::Porcelain => report::orphans_porcelain(&orphans, b'\n'),
+        ListFormat::PorcelainNul => report::orphans_porcelain(&orphans, b'\0'),
+        ListFormat::Json => report::orphans_json(&orphans).to_string().into_bytes(),
+        ListFormat::Tree { .. } | ListFormat::Markdown | ListFormat::Csv => {
+            return Err(ListError::RemoteRefsFormat)
         }
     };
 
-    for branch in repository.branches.iter().filter(filter) {
-        println!("    {branch}");
+    Ok(output)
+}
+
+pub fn orphaned_refs(path: &Context, untracked: bool) -> Result<Vec<OrphanedRef>, GitError> {
+    let mut orphans = git::orphaned_refs(&path)?;
+    orphans.retain(|orphan| reasons_of(untracked).contains(&orphan.reason));
+
+    Ok(orphans)
+}
+
+// A ref no local branch tracks may just be a branch that was never checked out, so those are only listed on request
+fn reasons_of(untracked: bool) -> &'static [OrphanReason] {
+    if untracked {
+        &[OrphanReason::Untracked, OrphanReason::UnknownRemote]
+    } else {
+        &[OrphanReason::UnknownRemote]
     }
 }
 
-impl<'a> Display for Head<'a> {
-    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Head::Branch(branch) => branch.fmt(formatter),
-            Head::Detached => write!(formatter, "Detached"),
-        }
+pub fn write_output(file: Option<&Path>, output: &[u8]) -> Result<(), ListError> {
+    match file {
+        Some(file) => fs::write(file, output)?,
+        None => write_stdout(output)?,
     }
+
+    Ok(())
 }
 
-impl<'a> Display for Branch<'a> {
-    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Branch::Tracking { name, remote } => {
-                write!(
-                    formatter,
-                    "{} => {}/{}",
-                    name, remote.remote_name, remote.branch_name
-                )
-            }
-            Branch::Local { name } => write!(formatter, "{}", name),
-        }
+fn write_stdout(output: &[u8]) -> io::Result<()> {
+    match io::stdout().lock().write_all(output) {
+        // The pipe was closed by the reader (e.g. head), which is not an error for a listing
+        Err(error) if error.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        result => result,
+    }
+}
+
+fn filter_of(option: &ListOption) -> Option<fn(&&Branch) -> bool> {
+    match option {
+        ListOption::Help | ListOption::RemoteRefs { .. } => None,
+        ListOption::All => Some(classify::is_any),
+        ListOption::Local => Some(classify::is_local),
+        ListOption::Tracked => Some(classify::is_tracked),
+        ListOption::Gone => Some(classify::is_gone),
+        ListOption::Diverged => Some(classify::is_diverged),
+    }
+}
+
+fn sections_of(option: &ListOption) -> &'static [Section] {
+    match option {
+        ListOption::All => &[Section::Local, Section::Tracked],
+        ListOption::Local => &[Section::Local],
+        ListOption::Tracked => &[Section::Tracked],
+        ListOption::Gone => &[Section::Gone],
+        ListOption::Diverged => &[Section::Diverged],
+        ListOption::Help | ListOption::RemoteRefs { .. } => &[],
     }
 }
+
+#[test]
+fn porcelain_of_detached_head_lists_only_branches() {
+    let snapshot = git::Snapshot::parse(
+        b"git-cleanup snapshot 2\nhead\tdetached\n\
+          \tfeature\tgone\torigin\tfeature\t\t\n\
+          \tlocal\tlocal\t\t\t\t\n",
+    )
+    .unwrap();
+    let repository = snapshot.repository();
+
+    let arguments = ListArguments {
+        option: ListOption::Gone,
+        format: ListFormat::Porcelain,
+        output: None,
+    };
+
+    // Nothing is queried for porcelain output, so the directory doesn't matter
+    let path = Context::new(Path::new("."), &git_cleanup::execute::System);
+    let sut = render(&path, &repository, &arguments).unwrap();
+
+    assert_eq!(sut, b"\tfeature\tgone\torigin\tfeature\n");
+}
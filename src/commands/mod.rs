@@ -1,5 +1,28 @@
+/*
+    The commands are a front end to the library: they turn parsed arguments into library calls,
+    then print the results and talk to the user
+*/
+
 mod list;
 pub use list::*;
 
 mod clean;
 pub use clean::*;
+
+mod stats;
+pub use stats::*;
+
+mod diff;
+pub use diff::*;
+
+mod fetch;
+pub use fetch::*;
+
+mod prune_remote;
+pub use prune_remote::*;
+
+mod repositories;
+pub use repositories::*;
+
+mod completions;
+pub use completions::*;
@@ -0,0 +1,114 @@
+use super::clean::confirm;
+use super::repositories::plural;
+use crate::args::{self, PruneRemoteArguments, PruneRemoteOption};
+use git_cleanup::classify::Pattern;
+use git_cleanup::clean::{RemotePruneError, RemotePrunePlan};
+use git_cleanup::execute::{self, Context};
+use git_cleanup::git::RefName;
+
+/*
+    Unlike clean, a single confirmation covers every branch, since a remote may have hundreds of them
+    The branches are taken from the tracking refs, so combining with --fetch-prune works on fresh ones
+*/
+pub fn prune_remote(
+    path: &Context,
+    arguments: &PruneRemoteArguments,
+) -> Result<(), RemotePruneError> {
+    if arguments.option == PruneRemoteOption::Help {
+        print!("{}", args::command_help("prune-remote"));
+        return Ok(());
+    }
+
+    let protected: Vec<Pattern> = arguments
+        .protected
+        .iter()
+        .map(|pattern| Pattern::new(pattern.as_str()))
+        .collect();
+
+    let plan = RemotePrunePlan::new(
+        path,
+        RefName(arguments.remote.as_bytes()),
+        arguments.base.as_deref().map(str::as_bytes),
+        arguments.older_than,
+        &protected,
+    )?;
+
+    let remote = RefName(&plan.remote);
+    let mut description = format!("merged into {remote}/{}", RefName(&plan.base));
+
+    if arguments.older_than > 0 {
+        description.push_str(&format!(
+            " and at least {} {} old",
+            arguments.older_than,
+            plural(arguments.older_than as usize, "day", "days")
+        ));
+    }
+
+    if plan.branches.is_empty() {
+        println!("No branch of {remote} is {description}");
+        return Ok(());
+    }
+
+    println!("Branches of {remote} {description}:");
+    for branch in &plan.branches {
+        println!("    {}", RefName(&branch.name));
+    }
+
+    let count = plan.branches.len();
+
+    match arguments.option {
+        PruneRemoteOption::DryRun => {
+            println!("Dry run, nothing was deleted");
+            return Ok(());
+        }
+        PruneRemoteOption::Confirm => {
+            let message = format!(
+                "About to delete {count} {} from {remote}, type y and press enter to continue",
+                plural(count, "branch", "branches")
+            );
+
+            if !confirm(&message) {
+                return Ok(());
+            }
+        }
+        PruneRemoteOption::Automatic | PruneRemoteOption::Help => {}
+    }
+
+    let outcome = plan.run(path, arguments.batch_size);
+
+    for failure in &outcome.failures {
+        println!(
+            "An error occurred while deleting {} from {remote}: {}",
+            names(&failure.branches),
+            failure.error
+        );
+    }
+
+    let deleted = outcome.deleted.len();
+    println!(
+        "Deleted {deleted} {} from {remote}",
+        plural(deleted, "branch", "branches")
+    );
+
+    if execute::is_interrupted() {
+        let not_deleted: Vec<Vec<u8>> = plan
+            .branches
+            .iter()
+            .map(|branch| branch.name.clone())
+            .filter(|name| !outcome.deleted.contains(name))
+            .collect();
+
+        println!("Interrupted, branches not deleted: {}", names(&not_deleted));
+    }
+
+    Ok(())
+}
+
+fn names(names: &[Vec<u8>]) -> String {
+    let names: Vec<_> = names
+        .iter()
+        .map(|name| RefName(name).to_string_lossy())
+        .collect();
+
+    names.join(", ")
+}
@@ -0,0 +1,448 @@
+use super::{clean_with_policy, fetch_prune, fetch_report, list, ListError, Remotes};
+use crate::args::{self, CleanArguments, CleanOption, ListArguments, ListFormat, ListOption};
+use git_cleanup::clean::Policy;
+use git_cleanup::execute::{self, Context, GitDir, System};
+use git_cleanup::git::{self, GitError, GitQuery, Repository};
+use git_cleanup::report::{self, RepositoryListing};
+use git_cleanup::scan;
+use git_cleanup::workspace::{Workspace, WorkspaceError};
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/*
+    Runs list or clean in many repositories: found by --recursive, listed by --workspace, or submodules
+    Repositories are fetched and queried by the worker pool, then their output is shown in the order they were found
+    Each repository is queried on its own, so that a broken one is reported and the others still run
+*/
+
+// A repository to run in, how output refers to it, and its overrides from a workspace manifest
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Target {
+    pub path: PathBuf,
+    pub label: String,
+    pub policy: Policy,
+    pub base: Option<String>,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum RepositoriesError {
+    #[error("Error scanning {} for repositories", path.display())]
+    Scan { path: PathBuf, source: io::Error },
+
+    #[error("No git repository found under {}", path.display())]
+    NoRepository { path: PathBuf },
+
+    #[error(transparent)]
+    Workspace(#[from] WorkspaceError),
+
+    #[error("{failed} of {total} repositories failed")]
+    Failed { failed: usize, total: usize },
+
+    #[error("Error writing list output")]
+    Output {
+        #[from]
+        source: ListError,
+    },
+}
+
+#[derive(thiserror::Error, Debug)]
+enum RepositoryError {
+    // Manifests can list repositories that were never cloned on this machine
+    #[error("{} is not a directory", path.display())]
+    Missing { path: PathBuf },
+
+    #[error("Error fetching: {0}")]
+    Fetch(GitError),
+
+    #[error("{0}")]
+    Query(#[from] GitError),
+
+    #[error("{0}")]
+    List(#[from] ListError),
+}
+
+impl Target {
+    pub fn new(path: PathBuf, label: impl Into<String>) -> Self {
+        Target {
+            path,
+            label: label.into(),
+            policy: Policy::default(),
+            base: None,
+        }
+    }
+
+    // Each target is its own repository, even when GIT_DIR or GIT_WORK_TREE are exported
+    pub fn location(&self) -> Context<'_> {
+        Context::new(&self.path, &System).with_git_dir(GitDir::Discover)
+    }
+}
+
+// Labels are relative to root, which makes headers short and the same on every machine
+pub fn scan_targets(root: &Path, nested: bool) -> Result<Vec<Target>, RepositoriesError> {
+    let paths = scan::repositories(root, nested).map_err(|source| RepositoriesError::Scan {
+        path: root.to_path_buf(),
+        source,
+    })?;
+
+    if paths.is_empty() {
+        return Err(RepositoriesError::NoRepository {
+            path: root.to_path_buf(),
+        });
+    }
+
+    let targets = paths
+        .into_iter()
+        .map(|path| {
+            let label = match path.strip_prefix(root) {
+                Ok(relative) if relative.as_os_str().is_empty() => ".".to_string(),
+                Ok(relative) => relative.display().to_string(),
+                Err(_) => path.display().to_string(),
+            };
+
+            Target::new(path, label)
+        })
+        .collect();
+
+    Ok(targets)
+}
+
+pub fn workspace_targets(file: &Path) -> Result<Vec<Target>, RepositoriesError> {
+    let workspace = Workspace::load(file)?;
+
+    let targets = workspace
+        .repositories
+        .into_iter()
+        .map(|repository| Target {
+            path: repository.path,
+            label: repository.label,
+            policy: repository.policy,
+            base: repository.base,
+        })
+        .collect();
+
+    Ok(targets)
+}
+
+/*
+    Adds the initialised submodules of every target right after it, labelled with their path
+    Submodules are repositories of their own, so the overrides of their parent don't apply to them
+    A submodule also found by --include-nested is only run once
+*/
+pub fn with_submodules(targets: Vec<Target>) -> Vec<Target> {
+    let submodules = execute::map_ordered(&targets, |target| git::submodules(&target.location()));
+    let mut expanded = Vec::with_capacity(targets.len());
+
+    for (target, submodules) in targets.into_iter().zip(submodules) {
+        let submodules = match submodules {
+            Ok(submodules) => submodules,
+            // A missing repository is reported when it is queried
+            Err(_) if !target.path.is_dir() => Vec::new(),
+            Err(error) => {
+                eprintln!("Error listing the submodules of {}: {error}", target.label);
+                Vec::new()
+            }
+        };
+
+        let parent = target.clone();
+        expanded.push(target);
+
+        for submodule in submodules {
+            let relative = git::os_str(&submodule.path);
+            let label = match parent.label.as_str() {
+                "." => relative.to_string_lossy().into_owned(),
+                label => format!("{label}/{}", relative.to_string_lossy()),
+            };
+
+            if !submodule.initialized {
+                eprintln!("Skipping submodule {label}, which is not initialised");
+                continue;
+            }
+
+            expanded.push(Target::new(parent.path.join(&*relative), label));
+        }
+    }
+
+    let mut seen = HashSet::new();
+    expanded.retain(|target| seen.insert(target.path.clone()));
+
+    expanded
+}
+
+// Listings are collected first, so that they can be written as a single document
+pub fn list_repositories(
+    targets: &[Target],
+    arguments: &ListArguments,
+    fetch: Option<&Remotes>,
+) -> Result<(), RepositoriesError> {
+    if arguments.option == ListOption::Help {
+        print!("{}", args::command_help("list"));
+        return Ok(());
+    }
+
+    let (output, failed) = match arguments.format {
+        ListFormat::Json => json_listing(targets, arguments, fetch),
+        ListFormat::Porcelain | ListFormat::PorcelainNul | ListFormat::Csv => {
+            labelled_listing(targets, arguments, fetch)
+        }
+        ListFormat::Text | ListFormat::Tree { .. } | ListFormat::Markdown => {
+            headed_listing(targets, arguments, fetch)
+        }
+    };
+
+    list::write_output(arguments.output.as_deref(), &output)?;
+
+    summary(failed, targets.len())
+}
+
+fn json_listing(
+    targets: &[Target],
+    arguments: &ListArguments,
+    fetch: Option<&Remotes>,
+) -> (Vec<u8>, usize) {
+    let listings = list_all(targets, fetch, |path, repository| {
+        list::render_json(path, repository, arguments)
+    });
+    let failed = count_failed(&listings);
+
+    let listings: Vec<RepositoryListing> = listings
+        .into_iter()
+        .map(|(target, result)| RepositoryListing {
+            path: target.label.clone(),
+            listing: result
+                .map(|(_, json)| json)
+                .map_err(|error| error.to_string()),
+        })
+        .collect();
+
+    let output = format!("{}\n", report::repositories_json(&listings)).into_bytes();
+    (output, failed)
+}
+
+// Formats for scripts keep one record per line, so the label leads every record instead of a header
+fn labelled_listing(
+    targets: &[Target],
+    arguments: &ListArguments,
+    fetch: Option<&Remotes>,
+) -> (Vec<u8>, usize) {
+    let listings = list_all(targets, fetch, |path, repository| {
+        list::render(path, repository, arguments)
+    });
+    let failed = count_failed(&listings);
+
+    let rendered: Vec<(&str, Vec<u8>)> = listings
+        .into_iter()
+        .filter_map(|(target, result)| match result {
+            Ok((_, listing)) => Some((target.label.as_str(), listing)),
+            Err(error) => {
+                eprintln!("Error in repository {}: {error}", target.label);
+                None
+            }
+        })
+        .collect();
+
+    let output = match arguments.format {
+        ListFormat::Csv => {
+            let reports: Vec<(&str, Cow<str>)> = rendered
+                .iter()
+                .map(|(label, listing)| (*label, String::from_utf8_lossy(listing)))
+                .collect();
+
+            report::repositories_csv(&reports).into_bytes()
+        }
+        ListFormat::PorcelainNul => report::repositories_porcelain(&rendered, b'\0'),
+        _ => report::repositories_porcelain(&rendered, b'\n'),
+    };
+
+    (output, failed)
+}
+
+fn headed_listing(
+    targets: &[Target],
+    arguments: &ListArguments,
+    fetch: Option<&Remotes>,
+) -> (Vec<u8>, usize) {
+    let listings = list_all(targets, fetch, |path, repository| {
+        list::render(path, repository, arguments)
+    });
+    let failed = count_failed(&listings);
+    let mut output = Vec::new();
+
+    for (index, (target, result)) in listings.into_iter().enumerate() {
+        if index > 0 {
+            output.push(b'\n');
+        }
+
+        output.extend_from_slice(header(target).as_bytes());
+
+        match result {
+            Ok((_, listing)) => output.extend_from_slice(&listing),
+            Err(error) => eprintln!("Error in repository {}: {error}", target.label),
+        }
+    }
+
+    (output, failed)
+}
+
+/*
+    Only fetching and querying run in the pool, the cleanups run one after the other
+    so that the prompts of clean --step follow each other across repositories, in the order they were found
+*/
+pub fn clean_repositories(
+    targets: &[Target],
+    arguments: &CleanArguments,
+    fetch: Option<&Remotes>,
+) -> Result<(), RepositoriesError> {
+    if arguments.option == CleanOption::Help {
+        print!("{}", args::command_help("clean"));
+        return Ok(());
+    }
+
+    let queries = execute::map_ordered(targets, |target| query_one(target, fetch));
+    let mut failed = 0;
+    let mut deleted = 0;
+
+    for (index, (target, query)) in targets.iter().zip(queries).enumerate() {
+        if execute::is_interrupted() {
+            break;
+        }
+
+        if index > 0 {
+            println!();
+        }
+
+        print!("{}", header(target));
+
+        // The base branch of the manifest is more specific than --switch-to
+        let switch_to = target.base.as_deref().or(arguments.switch_to.as_deref());
+
+        let cleaned = query.and_then(|(fetched, query)| {
+            print_fetched(target, &fetched);
+
+            let repository = query.to_repository()?;
+            Ok(clean_with_policy(
+                &target.location(),
+                &repository,
+                arguments,
+                switch_to,
+                &target.policy,
+            ))
+        });
+
+        match cleaned {
+            Ok(cleaned) => {
+                deleted += cleaned.deleted;
+                failed += usize::from(cleaned.failed);
+            }
+            Err(error) => {
+                eprintln!("Error in repository {}: {error}", target.label);
+                failed += 1;
+            }
+        }
+    }
+
+    println!(
+        "\nSummary: {deleted} {} deleted in {} {}",
+        plural(deleted, "branch", "branches"),
+        targets.len(),
+        plural(targets.len(), "repository", "repositories")
+    );
+
+    summary(failed, targets.len())
+}
+
+// A target with its fetch report and rendered listing, or why it failed
+type Listing<'a, T> = (&'a Target, Result<(String, T), RepositoryError>);
+
+// Every target rendered by the pool, in order. Fetch reports are printed here, since workers can't print themselves
+fn list_all<'a, T: Send>(
+    targets: &'a [Target],
+    fetch: Option<&Remotes>,
+    render: impl Fn(&Context, &Repository) -> Result<T, ListError> + Sync,
+) -> Vec<Listing<'a, T>> {
+    let results = execute::map_ordered(targets, |target| list_one(target, fetch, &render));
+    let listings: Vec<_> = targets.iter().zip(results).collect();
+
+    for (target, result) in &listings {
+        if let Ok((fetched, _)) = result {
+            print_fetched(target, fetched);
+        }
+    }
+
+    listings
+}
+
+fn count_failed<T>(listings: &[Listing<T>]) -> usize {
+    listings
+        .iter()
+        .filter(|(_, result)| result.is_err())
+        .count()
+}
+
+// Along with the listing comes the fetch report
+fn list_one<T>(
+    target: &Target,
+    fetch: Option<&Remotes>,
+    render: impl Fn(&Context, &Repository) -> Result<T, ListError>,
+) -> Result<(String, T), RepositoryError> {
+    let (fetched, query) = query_one(target, fetch)?;
+    let repository = query.to_repository()?;
+
+    Ok((fetched, render(&target.location(), &repository)?))
+}
+
+fn query_one(
+    target: &Target,
+    fetch: Option<&Remotes>,
+) -> Result<(String, GitQuery), RepositoryError> {
+    if !target.path.is_dir() {
+        return Err(RepositoryError::Missing {
+            path: target.path.clone(),
+        });
+    }
+
+    let fetched = match fetch {
+        Some(remotes) => fetch_report(
+            &fetch_prune(&target.location(), &target_remotes(target, remotes))
+                .map_err(RepositoryError::Fetch)?,
+        ),
+        None => String::new(),
+    };
+
+    Ok((fetched, GitQuery::query(&target.location())?))
+}
+
+// The remote of a workspace manifest replaces the default one, but not the ones given on the command line
+fn target_remotes(target: &Target, remotes: &Remotes) -> Remotes {
+    match (remotes, &target.policy.remote) {
+        (Remotes::Default, Some(remote)) => Remotes::Named(vec![remote.clone()]),
+        (remotes, _) => remotes.clone(),
+    }
+}
+
+fn print_fetched(target: &Target, fetched: &str) {
+    for line in fetched.lines() {
+        eprintln!("Repository {}: {line}", target.label);
+    }
+}
+
+fn header(target: &Target) -> String {
+    format!("Repository {}:\n", target.label)
+}
+
+pub(super) fn plural(count: usize, one: &'static str, many: &'static str) -> &'static str {
+    if count == 1 {
+        one
+    } else {
+        many
+    }
+}
+
+fn summary(failed: usize, total: usize) -> Result<(), RepositoriesError> {
+    if failed > 0 {
+        Err(RepositoriesError::Failed { failed, total })
+    } else {
+        Ok(())
+    }
+}
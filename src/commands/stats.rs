@@ -0,0 +1,24 @@
+use crate::args::{self, StatsOption};
+use git_cleanup::execute::Context;
+use git_cleanup::git::{GitError, Repository};
+use git_cleanup::report;
+
+pub fn stats(
+    path: &Context,
+    repository: &Repository,
+    option: &StatsOption,
+) -> Result<(), GitError> {
+    if *option == StatsOption::Help {
+        print!("{}", args::command_help("stats"));
+        return Ok(());
+    }
+
+    let stats = report::stats(path, repository)?;
+
+    match option {
+        StatsOption::Json => println!("{}", stats.to_json()),
+        _ => print!("{}", stats.to_text()),
+    }
+
+    Ok(())
+}
@@ -1,51 +1,571 @@
-use std::process::{Command, ExitStatus};
+use std::borrow::Cow;
+use std::ffi::OsStr;
+use std::io::Read;
+use std::process::{Child, Command, ExitStatus, Output, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicU8, Ordering};
+use std::sync::{Mutex, PoisonError};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
 use thiserror::Error;
 
-pub fn execute<P>(path: &P, command: &str, args: &[&str]) -> Result<String, ExecuteError>
+mod location;
+mod pool;
+pub use location::*;
+pub use pool::*;
+
+/*
+    How much is logged about each executed command
+    It is process wide state, set once from the command line, so that every caller is covered
+    Logs go to stderr so they never mix with the output of commands, e.g. JSON documents
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    Quiet = 0,
+    Verbose = 1,
+    Trace = 2,
+}
+
+static VERBOSITY: AtomicU8 = AtomicU8::new(Verbosity::Quiet as u8);
+
+// In milliseconds, so that timeouts below a second are kept. Zero means commands may run forever
+static TIMEOUT_MILLIS: AtomicU64 = AtomicU64::new(DEFAULT_TIMEOUT_SECONDS * 1000);
+
+// Only commands reaching a remote may prompt for credentials, and only in interactive runs
+static INTERACTIVE: AtomicBool = AtomicBool::new(false);
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+// Held while a command that may prompt runs, so that two prompts from the worker pool never share the terminal
+static PROMPT: Mutex<()> = Mutex::new(());
+
+// Whether a command may ask the user for credentials. Local commands never need any
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Prompt {
+    Never,
+    Interactive,
+}
+
+pub const DEFAULT_TIMEOUT_SECONDS: u64 = 120;
+
+// Children are polled starting with a short interval, so that fast commands don't wait for the poll
+const FIRST_POLL_INTERVAL: Duration = Duration::from_millis(1);
+const MAX_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+pub fn set_verbosity(verbosity: Verbosity) {
+    VERBOSITY.store(verbosity as u8, Ordering::Relaxed);
+}
+
+fn verbosity() -> Verbosity {
+    match VERBOSITY.load(Ordering::Relaxed) {
+        0 => Verbosity::Quiet,
+        1 => Verbosity::Verbose,
+        _ => Verbosity::Trace,
+    }
+}
+
+pub fn set_timeout(timeout: Option<Duration>) {
+    TIMEOUT_MILLIS.store(timeout_millis(timeout), Ordering::Relaxed);
+}
+
+// Rounded up to the next millisecond, so that a short timeout is never mistaken for none
+fn timeout_millis(timeout: Option<Duration>) -> u64 {
+    timeout.map_or(0, |timeout| {
+        let millis = timeout.as_nanos().div_ceil(1_000_000).max(1);
+        u64::try_from(millis).unwrap_or(u64::MAX)
+    })
+}
+
+fn timeout() -> Option<Duration> {
+    match TIMEOUT_MILLIS.load(Ordering::Relaxed) {
+        0 => None,
+        millis => Some(Duration::from_millis(millis)),
+    }
+}
+
+pub fn set_interactive(interactive: bool) {
+    INTERACTIVE.store(interactive, Ordering::Relaxed);
+}
+
+// Meant to be called from a signal handler, the running command is killed and no other command is started
+pub fn interrupt() {
+    INTERRUPTED.store(true, Ordering::Relaxed);
+}
+
+pub fn is_interrupted() -> bool {
+    INTERRUPTED.load(Ordering::Relaxed)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Completion {
+    // The command is killed when the user interrupts the program
+    Interruptible,
+    /*
+        The command always runs to the end, for operations that must not be left halfway
+        It runs in its own process group, so that the SIGINT of Ctrl-C, sent to the whole foreground group, never reaches it
+        That also keeps it from reading the terminal, so it must not be a command that can prompt
+    */
+    ToCompletion,
+}
+
+/// How a command ended
+#[derive(Debug)]
+pub enum Waited {
+    Exited(Output),
+    /// Killed after running longer than the timeout
+    TimedOut(Duration),
+    /// Killed because the user interrupted the program
+    Interrupted,
+}
+
+/// Runs the commands issued through a [`Location`]
+///
+/// [`System`] spawns them as child processes, while tests may serve recorded output instead.
+/// Commands may be issued from several threads at once, see [`map_ordered`]
+pub trait Executor: Sync {
+    fn run(&self, invocation: Invocation) -> std::io::Result<Waited>;
+}
+
+/// Runs every command as a child process
+pub struct System;
+
+impl Executor for System {
+    fn run(&self, invocation: Invocation) -> std::io::Result<Waited> {
+        invocation.spawn()
+    }
+}
+
+/// A command ready to run, with the settings it runs with
+pub struct Invocation<'a> {
+    command: Command,
+    directory: &'a std::path::Path,
+    command_line: &'a str,
+    timeout: Option<Duration>,
+    completion: Completion,
+}
+
+impl Invocation<'_> {
+    pub fn directory(&self) -> &std::path::Path {
+        self.directory
+    }
+
+    /// The command and its arguments, quoted just enough to tell them apart
+    pub fn command_line(&self) -> &str {
+        self.command_line
+    }
+
+    pub fn completion(&self) -> Completion {
+        self.completion
+    }
+
+    /// Spawns the command, then waits for it to exit, time out or be interrupted
+    pub fn spawn(mut self) -> std::io::Result<Waited> {
+        self.command
+            .spawn()
+            .and_then(|child| wait(child, self.timeout, self.completion))
+    }
+}
+
+/*
+    Output is returned as bytes, since git prints ref and file names as they are, which may not be UTF-8
+    Arguments are OsStr for the same reason, so that names are passed back to git unchanged
+*/
+pub fn execute<P, A>(path: &P, command: &str, args: &[A]) -> Result<Vec<u8>, ExecuteError>
+where
+    P: Location + ?Sized,
+    A: AsRef<OsStr>,
+{
+    execute_with(
+        path,
+        command,
+        args,
+        timeout(),
+        Completion::Interruptible,
+        Prompt::Never,
+    )
+}
+
+/*
+    For commands reaching a remote, like fetch and push, which may prompt for credentials in interactive runs
+    Those run one at a time, while local commands from the worker pool still run concurrently
+*/
+pub fn execute_remote<P, A>(path: &P, command: &str, args: &[A]) -> Result<Vec<u8>, ExecuteError>
+where
+    P: Location + ?Sized,
+    A: AsRef<OsStr>,
+{
+    execute_with(
+        path,
+        command,
+        args,
+        timeout(),
+        Completion::Interruptible,
+        Prompt::Interactive,
+    )
+}
+
+// Still honors the timeout, since a hung command would otherwise never complete
+pub fn execute_to_completion<P, A>(
+    path: &P,
+    command: &str,
+    args: &[A],
+) -> Result<Vec<u8>, ExecuteError>
 where
-    P: AsRef<std::path::Path>,
+    P: Location + ?Sized,
+    A: AsRef<OsStr>,
 {
+    execute_with(
+        path,
+        command,
+        args,
+        timeout(),
+        Completion::ToCompletion,
+        Prompt::Never,
+    )
+}
+
+fn execute_with<P, A>(
+    path: &P,
+    command: &str,
+    args: &[A],
+    timeout: Option<Duration>,
+    completion: Completion,
+    prompt: Prompt,
+) -> Result<Vec<u8>, ExecuteError>
+where
+    P: Location + ?Sized,
+    A: AsRef<OsStr>,
+{
+    let directory = path.directory();
+    let command_line = command_line(command, args);
+    let is_git = command == "git";
+
+    if completion == Completion::Interruptible && is_interrupted() {
+        return Err(ExecuteError::Interrupted {
+            command: command_line,
+        });
+    }
+
     let mut command = Command::new(command);
 
-    command.current_dir(path).args(args);
+    command
+        .current_dir(directory)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+
+    let interactive = prompt == Prompt::Interactive && INTERACTIVE.load(Ordering::Relaxed);
+
+    if !interactive {
+        command.env("GIT_TERMINAL_PROMPT", "0");
+    }
+
+    if is_git {
+        match path.git_dir() {
+            GitDir::Environment => {}
+            GitDir::Discover => {
+                command.env_remove("GIT_DIR").env_remove("GIT_WORK_TREE");
+            }
+            GitDir::At(git_dir) => {
+                command.env("GIT_DIR", git_dir);
+            }
+        }
+
+        // Git messages are localized, and classify only recognizes the English ones
+        command.env("LC_ALL", "C");
+    }
+
+    #[cfg(unix)]
+    if completion == Completion::ToCompletion {
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+    }
+
+    let _prompt = interactive.then(|| PROMPT.lock().unwrap_or_else(PoisonError::into_inner));
+
+    let start = Instant::now();
+    let waited = path.executor().run(Invocation {
+        command,
+        directory,
+        command_line: &command_line,
+        timeout,
+        completion,
+    });
+
+    if verbosity() > Verbosity::Quiet {
+        log(directory, &command_line, start.elapsed(), &waited);
+    }
+
+    let waited = waited.map_err(|source| ExecuteError::Io {
+        command: command_line.clone(),
+        source,
+    })?;
+
+    let output = match waited {
+        Waited::Exited(output) => output,
+        Waited::TimedOut(timeout) => {
+            return Err(ExecuteError::Timeout {
+                command: command_line,
+                timeout,
+            })
+        }
+        Waited::Interrupted => {
+            return Err(ExecuteError::Interrupted {
+                command: command_line,
+            })
+        }
+    };
+
+    check_for_success(&command_line, output.status, &output.stderr)?;
+
+    Ok(output.stdout)
+}
+
+/*
+    Command::output blocks until the child exits, so instead the child is polled while
+    its output is read in background threads, which keeps it from blocking on a full pipe
+*/
+fn wait(
+    mut child: Child,
+    timeout: Option<Duration>,
+    completion: Completion,
+) -> std::io::Result<Waited> {
+    let stdout = read_in_background(child.stdout.take());
+    let stderr = read_in_background(child.stderr.take());
+
+    let start = Instant::now();
+    let mut poll_interval = FIRST_POLL_INTERVAL;
+
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Waited::Exited(Output {
+                status,
+                stdout: stdout.join().unwrap_or_default(),
+                stderr: stderr.join().unwrap_or_default(),
+            }));
+        }
+
+        let timed_out = timeout.filter(|timeout| start.elapsed() >= *timeout);
+        let interrupted = completion == Completion::Interruptible && is_interrupted();
 
-    let output = command.output()?;
+        if timed_out.is_some() || interrupted {
+            // Killing fails if the child exited in the meantime, which is fine
+            let _ = child.kill();
+            let _ = child.wait();
 
-    check_for_success(output.status)?;
+            return Ok(match timed_out {
+                Some(timeout) => Waited::TimedOut(timeout),
+                None => Waited::Interrupted,
+            });
+        }
+
+        thread::sleep(poll_interval);
+        poll_interval = (poll_interval * 2).min(MAX_POLL_INTERVAL);
+    }
+}
+
+fn read_in_background(stream: Option<impl Read + Send + 'static>) -> JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut bytes = Vec::new();
+
+        if let Some(mut stream) = stream {
+            let _ = stream.read_to_end(&mut bytes);
+        }
+
+        bytes
+    })
+}
+
+// Each entry is written at once, so that entries of commands running in parallel don't mix
+fn log(
+    path: &std::path::Path,
+    command_line: &str,
+    elapsed: Duration,
+    waited: &std::io::Result<Waited>,
+) {
+    let output = match waited {
+        Ok(Waited::Exited(output)) => output,
+        Ok(Waited::TimedOut(_)) => {
+            eprintln!(
+                "+ {command_line} (in {}) killed after timing out in {elapsed:?}",
+                path.display()
+            );
+            return;
+        }
+        Ok(Waited::Interrupted) => {
+            eprintln!(
+                "+ {command_line} (in {}) killed after being interrupted in {elapsed:?}",
+                path.display()
+            );
+            return;
+        }
+        Err(error) => {
+            eprintln!(
+                "+ {command_line} (in {}) failed to start after {elapsed:?}: {error}",
+                path.display()
+            );
+            return;
+        }
+    };
+
+    let mut entry = format!(
+        "+ {command_line} (in {}) {} after {elapsed:?}\n",
+        path.display(),
+        output.status
+    );
+
+    if verbosity() == Verbosity::Trace {
+        entry.push_str(&trace_stream("stdout", &output.stdout));
+        entry.push_str(&trace_stream("stderr", &output.stderr));
+    }
+
+    eprint!("{entry}");
+}
+
+// Streams are indented below their name, so that they are easy to tell apart from the log lines
+fn trace_stream(name: &str, bytes: &[u8]) -> String {
+    if bytes.is_empty() {
+        return String::new();
+    }
+
+    let text = String::from_utf8_lossy(bytes);
+
+    let mut trace = format!("  {name}:\n");
+    for line in text.lines() {
+        trace.push_str(&format!("    {line}\n"));
+    }
 
-    Ok(String::from_utf8(output.stdout)?)
+    trace
 }
 
-fn check_for_success(status: ExitStatus) -> Result<(), ExecuteError> {
+// Only meant to be read by humans, so arguments are quoted just enough to tell them apart
+fn command_line<A: AsRef<OsStr>>(command: &str, args: &[A]) -> String {
+    std::iter::once(Cow::from(command))
+        .chain(args.iter().map(|arg| arg.as_ref().to_string_lossy()))
+        .map(|word| {
+            if word.is_empty() || word.contains(char::is_whitespace) {
+                format!("'{word}'")
+            } else {
+                word.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn check_for_success(command: &str, status: ExitStatus, stderr: &[u8]) -> Result<(), ExecuteError> {
     if status.success() {
         return Ok(());
     }
 
+    let command = command.to_string();
+    let stderr = String::from_utf8_lossy(stderr).trim_end().to_string();
+
+    if let Some(error) = classify(&command, &stderr) {
+        return Err(error);
+    }
+
     match status.code() {
-        Some(code) => Err(ExecuteError::CommandErrorCode { code }),
-        _ => Err(ExecuteError::CommandError),
+        Some(code) => Err(ExecuteError::CommandErrorCode {
+            command,
+            code,
+            stderr,
+        }),
+        _ => Err(ExecuteError::CommandError { command, stderr }),
     }
 }
 
+/*
+    Recognizes the git failures callers may want to react to, from the messages git prints in the C locale
+    Anything not recognized here is reported as a plain status code error
+*/
+fn classify(command: &str, stderr: &str) -> Option<ExecuteError> {
+    let lowercase = stderr.to_lowercase();
+    let command = command.to_string();
+    let stderr = stderr.to_string();
+
+    if lowercase.contains("not a git repository") {
+        return Some(ExecuteError::NotARepository { command, stderr });
+    }
+
+    if lowercase.contains("is not fully merged") {
+        let branch = quoted(&stderr).unwrap_or_default();
+        return Some(ExecuteError::BranchNotFullyMerged {
+            branch,
+            command,
+            stderr,
+        });
+    }
+
+    if lowercase.contains("error: branch '") && lowercase.contains("not found") {
+        let branch = quoted(&stderr).unwrap_or_default();
+        return Some(ExecuteError::BranchNotFound {
+            branch,
+            command,
+            stderr,
+        });
+    }
+
+    if lowercase.contains("could not read from remote repository")
+        || lowercase.contains("unable to access")
+    {
+        return Some(ExecuteError::RemoteUnreachable { command, stderr });
+    }
+
+    None
+}
+
+// Git quotes the names it refers to in its messages, e.g. error: branch 'feature' not found
+fn quoted(message: &str) -> Option<String> {
+    let (_, rest) = message.split_once('\'')?;
+    let (quoted, _) = rest.split_once('\'')?;
+
+    Some(quoted.to_string())
+}
+
 #[derive(Error, Debug)]
 pub enum ExecuteError {
-    #[error("Error executing command")]
+    #[error("Error executing command: {command}")]
     Io {
-        #[from]
+        command: String,
         source: std::io::Error,
     },
 
-    #[error("Error parsing git command output")]
-    Parse {
-        #[from]
-        source: std::string::FromUtf8Error,
+    #[error("Error executing command, status code {code}: {command}\n{stderr}")]
+    CommandErrorCode {
+        command: String,
+        code: i32,
+        stderr: String,
+    },
+
+    #[error("Error executing command: {command}\n{stderr}")]
+    CommandError { command: String, stderr: String },
+
+    #[error("Not a git repository: {command}\n{stderr}")]
+    NotARepository { command: String, stderr: String },
+
+    #[error("Branch {branch} is not fully merged: {command}\n{stderr}")]
+    BranchNotFullyMerged {
+        branch: String,
+        command: String,
+        stderr: String,
+    },
+
+    #[error("Branch {branch} not found: {command}\n{stderr}")]
+    BranchNotFound {
+        branch: String,
+        command: String,
+        stderr: String,
     },
 
-    #[error("Error executing command, status code {code}")]
-    CommandErrorCode { code: i32 },
+    #[error("Remote repository is unreachable: {command}\n{stderr}")]
+    RemoteUnreachable { command: String, stderr: String },
 
-    #[error("Error executing command")]
-    CommandError,
+    #[error("Command killed after timing out in {timeout:?}: {command}")]
+    Timeout { command: String, timeout: Duration },
+
+    #[error("Command interrupted: {command}")]
+    Interrupted { command: String },
 }
 
 #[test]
@@ -53,7 +573,7 @@ pub enum ExecuteError {
 fn success_execution() {
     let some_dir = std::env::current_dir().unwrap();
     let sut = execute(&some_dir, "echo", &["Hello world\nMultiple lines"]).unwrap();
-    let expected = "Hello world\nMultiple lines\n";
+    let expected = b"Hello world\nMultiple lines\n";
     assert_eq!(sut, expected);
 }
 
@@ -64,84 +584,113 @@ fn error_execution() {
     execute(&some_dir, "git", &["something"]).expect_err("Execute should've failed");
 }
 
-#[cfg(test)]
-#[allow(unused_macros)]
-// Limitation: Command must receive an argument, otherwise the compiler will hit recursion limit
-macro_rules! sequence_execute {
-    ( $path:ident : ($command:literal, $($arg:expr),*) ) => {
-        // SAFETY: Since this is only used for tests, it is OK to panic if an error occurred
-        let _ = $crate::execute::execute(&$path, $command, &[$($arg),*]).unwrap();
-    };
+#[test]
+#[cfg(feature = "testbin")]
+fn kill_on_timeout() {
+    let some_dir = std::env::current_dir().unwrap();
+    let start = Instant::now();
 
-    ( $path:ident : $($command_and_args:tt),+ ) => {
-        $(
-            $crate::execute::sequence_execute! {
-                $path:
-                    $command_and_args
-            }
-        )+
-    };
+    let sut = execute_with(
+        &some_dir,
+        "sleep",
+        &["5"],
+        Some(Duration::from_millis(100)),
+        Completion::Interruptible,
+        Prompt::Never,
+    )
+    .unwrap_err();
 
-    ( $($path:ident : $($command_and_args:tt),+)+ ) => {
-        $(
-            $crate::execute::sequence_execute! {
-                $path:
-                    $($command_and_args),+
-            }
-        )+
-    };
+    assert!(matches!(sut, ExecuteError::Timeout { .. }));
+    assert!(start.elapsed() < Duration::from_secs(5));
 }
 
 #[test]
 #[cfg(feature = "testbin")]
-fn test_sequence_execute() {
-    use crate::test_support::TempDir;
-
-    let root = TempDir::new().unwrap();
-    let subdir_one = root.join("subdir_one");
-    let subdir_two = root.join("subdir_two");
-
-    sequence_execute! {
-        root:
-            ("touch", "root_file"),
-            ("mkdir", "subdir_one"),
-            ("mkdir", "subdir_two")
-
-        subdir_one:
-            ("touch", "subdir_one_file1"),
-            ("touch", "subdir_one_file2")
-
-        subdir_two:
-            ("touch", "subdir_two_file1"),
-            ("touch", "subdir_two_file2")
-    }
-
-    let paths = [
-        root.file_path("root_file"),
-        subdir_one.file_path("subdir_one_file1"),
-        subdir_one.file_path("subdir_one_file2"),
-        subdir_two.file_path("subdir_two_file1"),
-        subdir_two.file_path("subdir_two_file2"),
-    ];
-
-    for path in paths {
-        match path.try_exists() {
-            Ok(exists) => {
-                if !exists {
-                    panic!(
-                        "Error executing file_sequence!. File ({}) does not exist",
-                        path.to_string_lossy()
-                    );
-                }
-            }
-            Err(error) => panic!(
-                "Error while checking file ({}) exists: {error}",
-                path.to_string_lossy()
-            ),
-        }
+fn large_output_does_not_block() {
+    let some_dir = std::env::current_dir().unwrap();
+
+    let sut = execute_with(
+        &some_dir,
+        "head",
+        &["-c", "1000000", "/dev/zero"],
+        Some(Duration::from_secs(10)),
+        Completion::Interruptible,
+        Prompt::Never,
+    )
+    .unwrap();
+
+    assert_eq!(sut.len(), 1_000_000);
+}
+
+#[test]
+fn sub_second_timeout() {
+    assert_eq!(timeout_millis(Some(Duration::from_micros(1500))), 2);
+    assert_eq!(timeout_millis(Some(Duration::ZERO)), 1);
+    assert_eq!(timeout_millis(Some(Duration::from_secs(2))), 2000);
+    assert_eq!(timeout_millis(None), 0);
+}
+
+#[test]
+fn trace_stream_indents_lines() {
+    let sut = trace_stream("stdout", b"* main\n  feature\n");
+    assert_eq!(sut, "  stdout:\n    * main\n      feature\n");
+}
+
+#[test]
+fn trace_stream_skips_empty_output() {
+    assert_eq!(trace_stream("stderr", b""), "");
+}
+
+#[test]
+#[cfg(all(feature = "testbin", unix))]
+fn non_utf8_output_and_arguments() {
+    use std::os::unix::ffi::OsStrExt;
+
+    let some_dir = std::env::current_dir().unwrap();
+    let name = OsStr::from_bytes(b"caf\xe9");
+
+    let sut = execute(&some_dir, "echo", &[name]).unwrap();
+
+    assert_eq!(sut, b"caf\xe9\n");
+}
+
+#[test]
+fn quote_arguments_with_spaces() {
+    let sut = command_line("git", &["commit", "-m", "some message", ""]);
+    assert_eq!(sut, "git commit -m 'some message' ''");
+}
+
+#[test]
+fn classify_not_fully_merged() {
+    let sut = classify(
+        "git branch -d feature",
+        "error: The branch 'feature' is not fully merged.\nIf you are sure you want to delete it, run 'git branch -D feature'.",
+    );
+
+    match sut {
+        Some(ExecuteError::BranchNotFullyMerged { branch, .. }) => assert_eq!(branch, "feature"),
+        other => panic!("Unexpected classification: {other:?}"),
     }
 }
 
-#[cfg(test)]
-#[allow(unused_imports)]
-pub(crate) use sequence_execute;
+#[test]
+fn classify_branch_not_found() {
+    let sut = classify(
+        "git branch -d missing",
+        "error: branch 'missing' not found.",
+    );
+
+    match sut {
+        Some(ExecuteError::BranchNotFound { branch, .. }) => assert_eq!(branch, "missing"),
+        other => panic!("Unexpected classification: {other:?}"),
+    }
+}
+
+#[test]
+fn classify_unknown_failure() {
+    let sut = classify(
+        "git something",
+        "git: 'something' is not a git command. See 'git --help'.",
+    );
+    assert!(sut.is_none());
+}
@@ -0,0 +1,96 @@
+use super::{Executor, System};
+use std::path::{Path, PathBuf};
+
+/*
+    Where commands run, and what runs them
+    A plain path runs them as child processes, while a Context brings its own repository and executor,
+    which reach every command issued for it, including the ones issued from the worker pool
+*/
+
+/// A directory to run commands in, the repository git uses there, and the executor running them
+pub trait Location {
+    fn directory(&self) -> &Path;
+
+    fn git_dir(&self) -> GitDir<'_> {
+        GitDir::Environment
+    }
+
+    fn executor(&self) -> &dyn Executor {
+        &System
+    }
+}
+
+/// Which repository git commands use
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum GitDir<'a> {
+    /// The one git finds from the directory, honoring GIT_DIR and GIT_WORK_TREE like git itself
+    #[default]
+    Environment,
+    /// The one git finds from the directory, even when GIT_DIR or GIT_WORK_TREE are set,
+    /// e.g. when running in many repositories, which can't all be the one of GIT_DIR
+    Discover,
+    /// The repository at this path, like git --git-dir
+    At(&'a Path),
+}
+
+impl Location for Path {
+    fn directory(&self) -> &Path {
+        self
+    }
+}
+
+impl Location for PathBuf {
+    fn directory(&self) -> &Path {
+        self
+    }
+}
+
+impl<L: Location + ?Sized> Location for &L {
+    fn directory(&self) -> &Path {
+        (**self).directory()
+    }
+
+    fn git_dir(&self) -> GitDir<'_> {
+        (**self).git_dir()
+    }
+
+    fn executor(&self) -> &dyn Executor {
+        (**self).executor()
+    }
+}
+
+/// A directory with the repository and the executor its commands run with
+#[derive(Clone, Copy)]
+pub struct Context<'a> {
+    directory: &'a Path,
+    git_dir: GitDir<'a>,
+    executor: &'a dyn Executor,
+}
+
+impl<'a> Context<'a> {
+    pub fn new(directory: &'a Path, executor: &'a dyn Executor) -> Self {
+        Context {
+            directory,
+            git_dir: GitDir::Environment,
+            executor,
+        }
+    }
+
+    pub fn with_git_dir(self, git_dir: GitDir<'a>) -> Self {
+        Context { git_dir, ..self }
+    }
+}
+
+impl Location for Context<'_> {
+    fn directory(&self) -> &Path {
+        self.directory
+    }
+
+    fn git_dir(&self) -> GitDir<'_> {
+        self.git_dir
+    }
+
+    fn executor(&self) -> &dyn Executor {
+        self.executor
+    }
+}
@@ -0,0 +1,122 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+/*
+    A bounded pool of worker threads, for running git in many repositories or against many remotes
+    Commands already run as child processes, so plain threads waiting on them are all the concurrency needed
+    Results come back in the order of the items, whatever order the workers finish in, so output stays ordered
+*/
+
+// One means everything runs on the calling thread, like before there was a pool
+static JOBS: AtomicUsize = AtomicUsize::new(1);
+
+// Zero means one job per available CPU
+pub fn set_jobs(jobs: usize) {
+    let jobs = match jobs {
+        0 => thread::available_parallelism().map_or(1, usize::from),
+        jobs => jobs,
+    };
+
+    JOBS.store(jobs, Ordering::Relaxed);
+}
+
+pub fn jobs() -> usize {
+    JOBS.load(Ordering::Relaxed)
+}
+
+/// Calls work on every item, with as many threads as set by [`set_jobs`], and returns the results in order
+///
+/// Work must not talk to the user, since several calls may run at once
+pub fn map_ordered<T, R>(items: &[T], work: impl Fn(&T) -> R + Sync) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+{
+    map_with_jobs(items, jobs(), work)
+}
+
+fn map_with_jobs<T, R>(items: &[T], jobs: usize, work: impl Fn(&T) -> R + Sync) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+{
+    let jobs = jobs.min(items.len());
+
+    if jobs <= 1 {
+        return items.iter().map(work).collect();
+    }
+
+    // Workers take the next item when they are done with the previous one, so a slow item doesn't hold up the others
+    let next = AtomicUsize::new(0);
+
+    let mut results: Vec<(usize, R)> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..jobs)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut results = Vec::new();
+
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(item) = items.get(index) else {
+                            return results;
+                        };
+
+                        results.push((index, work(item)));
+                    }
+                })
+            })
+            .collect();
+
+        workers
+            .into_iter()
+            .flat_map(|worker| match worker.join() {
+                Ok(results) => results,
+                Err(panic) => std::panic::resume_unwind(panic),
+            })
+            .collect()
+    });
+
+    results.sort_by_key(|(index, _)| *index);
+
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+#[test]
+fn results_in_order_of_items() {
+    let items: Vec<u64> = (0..20).collect();
+
+    // Earlier items take longer, so they finish last
+    let sut = map_with_jobs(&items, 4, |item| {
+        thread::sleep(std::time::Duration::from_millis(20 - item));
+        item * 2
+    });
+
+    let expected: Vec<u64> = (0..20).map(|item| item * 2).collect();
+
+    assert_eq!(sut, expected);
+}
+
+#[test]
+fn concurrency_is_bounded() {
+    let running = AtomicUsize::new(0);
+    let most_running = AtomicUsize::new(0);
+    let items = [(); 12];
+
+    map_with_jobs(&items, 3, |_| {
+        let now_running = running.fetch_add(1, Ordering::SeqCst) + 1;
+        most_running.fetch_max(now_running, Ordering::SeqCst);
+        thread::sleep(std::time::Duration::from_millis(5));
+        running.fetch_sub(1, Ordering::SeqCst);
+    });
+
+    assert!(most_running.load(Ordering::SeqCst) <= 3);
+}
+
+#[test]
+fn single_job_runs_on_calling_thread() {
+    let caller = thread::current().id();
+
+    let sut = map_with_jobs(&[1, 2], 1, |_| thread::current().id());
+
+    assert_eq!(sut, [caller, caller]);
+}
@@ -1,6 +1,9 @@
 mod line_parser;
 use line_parser::*;
 
+mod ref_name;
+pub use ref_name::*;
+
 mod head;
 pub use head::*;
 
@@ -19,19 +22,38 @@ pub use remote_branch_status::*;
 mod branch;
 pub use branch::*;
 
-use crate::execute;
+mod branch_info;
+pub use branch_info::*;
+
+mod detached_head;
+pub use detached_head::*;
+
+mod switch;
+pub use switch::*;
+
+mod snapshot;
+pub use snapshot::*;
+
+mod submodule;
+pub use submodule::*;
+
+mod remote;
+pub use remote::*;
+
+use crate::execute::{self, Location};
 
 /*
     This struct stores the output of git branch -vv
     Repository and its underlying data structures point to
-    pieces of this string, preventing the allocation of many
+    pieces of this output, preventing the allocation of many
     Strings
+    The output is kept as bytes, since branch names don't have to be UTF-8
 */
-pub struct GitQuery(String);
+pub struct GitQuery(Vec<u8>);
 
 impl GitQuery {
-    fn lines(&self) -> impl Iterator<Item = impl LineParser> {
-        self.0.lines().map(new_line_parser)
+    fn lines(&self) -> impl Iterator<Item = impl LineParser<'_>> {
+        lines(&self.0).map(new_line_parser)
     }
 
     /*
@@ -39,17 +61,27 @@ impl GitQuery {
         size_hint implementation
     */
     fn count_lines(&self) -> usize {
-        self.0.lines().count()
+        lines(&self.0).count()
     }
 }
 
+// Like str::lines, but for bytes
+pub(crate) fn lines(bytes: &[u8]) -> impl Iterator<Item = &[u8]> {
+    bytes
+        .strip_suffix(b"\n")
+        .unwrap_or(bytes)
+        .split(|byte| *byte == b'\n')
+        .filter(|line| !bytes.is_empty() || !line.is_empty())
+        .map(|line| line.strip_suffix(b"\r").unwrap_or(line))
+}
+
 impl GitQuery {
-    pub fn query(path: &impl AsRef<std::path::Path>) -> Result<GitQuery, GitError> {
+    pub fn query(path: &impl Location) -> Result<GitQuery, GitError> {
         let output = execute::execute(path, "git", &["branch", "-vv"])?;
         Ok(GitQuery(output))
     }
 
-    pub fn to_repository(&self) -> Result<Repository, GitError> {
+    pub fn to_repository(&self) -> Result<Repository<'_>, GitError> {
         let repository = Repository::parse(self)?;
         Ok(repository)
     }
@@ -1,14 +1,14 @@
-use super::{GitParseError, LineComponents, LineParser, RemoteBranch};
+use super::{GitParseError, LineComponents, LineParser, RefName, RemoteBranch};
 
 #[derive(Debug, Clone, PartialEq, Hash, Eq)]
 pub enum Branch<'a> {
     Tracking {
-        name: &'a str,
+        name: RefName<'a>,
         remote: RemoteBranch<'a>,
     },
 
     Local {
-        name: &'a str,
+        name: RefName<'a>,
     },
 }
 
@@ -17,7 +17,7 @@ impl<'a> Branch<'a> {
         match parser.consume_components() {
             Some(components) => Ok(Branch::from_components(components)),
             None => Err(GitParseError::BranchPattern {
-                line: parser.line().to_string(),
+                line: String::from_utf8_lossy(parser.line()).to_string(),
             }),
         }
     }
@@ -25,11 +25,11 @@ impl<'a> Branch<'a> {
     fn from_components(component: LineComponents<'a>) -> Self {
         match RemoteBranch::parse(component.maybe_origin_branch) {
             Some(remote) => Branch::Tracking {
-                name: component.branch_name,
+                name: RefName(component.branch_name),
                 remote,
             },
             _ => Branch::Local {
-                name: component.branch_name,
+                name: RefName(component.branch_name),
             },
         }
     }
@@ -38,10 +38,10 @@ impl<'a> Branch<'a> {
 #[cfg(test)]
 #[allow(unused_macros)]
 macro_rules! tracking {
-    ($name:literal, remote ( $remote_name:literal, $remote_origin: literal, $remote_status:ident ) ) => {
+    ($name:literal, remote ( $remote_name:literal, $remote_origin: literal, $($remote_status:tt)+ ) ) => {
         $crate::git::Branch::Tracking {
-            name: $name,
-            remote: crate::git::remote!($remote_name, $remote_origin, $remote_status),
+            name: $crate::git::RefName($name.as_bytes()),
+            remote: crate::git::remote!($remote_name, $remote_origin, $($remote_status)+),
         }
     };
 }
@@ -54,7 +54,9 @@ pub(crate) use tracking;
 #[allow(unused_macros)]
 macro_rules! local {
     ($name:literal) => {
-        $crate::git::Branch::Local { name: $name }
+        $crate::git::Branch::Local {
+            name: $crate::git::RefName($name.as_bytes()),
+        }
     };
 }
 
@@ -81,19 +83,19 @@ pub(crate) use branch;
 #[test]
 fn tracking_branch() {
     let mut parser = super::line_parser::new_line_parser(
-        "main 1f02cc2 [origin/main: ahead by 2] Initial commit",
+        b"main 1f02cc2 [origin/main: ahead by 2] Initial commit",
     );
 
     let sut = Branch::new(&mut parser).unwrap();
 
-    let expected = tracking! {"main", remote("main", "origin", diverged)};
+    let expected = tracking! {"main", remote("main", "origin", diverged(2, 0))};
 
     assert_eq!(sut, expected);
 }
 
 #[test]
 fn local_branch() {
-    let mut parser = super::line_parser::new_line_parser("develop 1f02cc2 Initial commit");
+    let mut parser = super::line_parser::new_line_parser(b"develop 1f02cc2 Initial commit");
 
     let sut = Branch::new(&mut parser).unwrap();
 
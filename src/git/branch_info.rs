@@ -0,0 +1,162 @@
+use super::{lines, os_str, GitError, GitParseError, Head, RefName};
+use crate::execute::{self, Location};
+use std::collections::HashMap;
+use std::ffi::OsString;
+
+/*
+    Information about local branches that git branch -vv doesn't provide
+    Unlike Repository, this is queried through git for-each-ref, whose output format we control
+    Branches are keyed by the bytes of their name, like RefName
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BranchInfo {
+    pub committer_timestamp: u64,
+    pub merged: bool,
+    pub subject: String,
+}
+
+impl BranchInfo {
+    pub fn query(
+        path: &impl Location,
+        head: &Head,
+    ) -> Result<HashMap<Vec<u8>, BranchInfo>, GitError> {
+        let output = execute::execute(
+            path,
+            "git",
+            &[
+                "for-each-ref",
+                "--format=%(refname:lstrip=2)%09%(committerdate:unix)%09%(contents:subject)",
+                "refs/heads",
+            ],
+        )?;
+
+        // Nothing is merged into an unborn HEAD, which git can't even resolve
+        let merged = match head {
+            Head::Unborn => Vec::new(),
+            Head::Branch(_) | Head::Detached => execute::execute(
+                path,
+                "git",
+                &[
+                    "for-each-ref",
+                    "--merged=HEAD",
+                    "--format=%(refname:lstrip=2)",
+                    "refs/heads",
+                ],
+            )?,
+        };
+
+        Ok(BranchInfo::parse(&output, &merged)?)
+    }
+
+    fn parse(output: &[u8], merged: &[u8]) -> Result<HashMap<Vec<u8>, BranchInfo>, GitParseError> {
+        let merged: Vec<&[u8]> = lines(merged).collect();
+        let mut infos = HashMap::new();
+
+        for line in lines(output) {
+            let pattern_error = || GitParseError::ForEachRefPattern {
+                line: String::from_utf8_lossy(line).to_string(),
+            };
+
+            // The subject is the last field, so any tab inside it is kept
+            let mut fields = line.splitn(3, |byte| *byte == b'\t');
+
+            let (name, timestamp, subject) = match (fields.next(), fields.next(), fields.next()) {
+                (Some(name), Some(timestamp), Some(subject)) => (name, timestamp, subject),
+                _ => return Err(pattern_error()),
+            };
+
+            let committer_timestamp = match std::str::from_utf8(timestamp).map(str::parse) {
+                Ok(Ok(timestamp)) => timestamp,
+                _ => return Err(pattern_error()),
+            };
+
+            let info = BranchInfo {
+                committer_timestamp,
+                merged: merged.contains(&name),
+                subject: String::from_utf8_lossy(subject).to_string(),
+            };
+
+            infos.insert(name.to_vec(), info);
+        }
+
+        Ok(infos)
+    }
+}
+
+/*
+    Counts commits reachable from the given branches that no other ref can reach,
+    which are the commits that would become unreachable if those branches were deleted
+    It is only an estimate, since reflogs still keep those commits alive for a while
+    A current branch among them is switched away from before it is deleted, so then HEAD must not count as a ref
+*/
+pub fn count_exclusive_commits(
+    path: &impl Location,
+    branches: &[RefName],
+    exclude_head: bool,
+) -> Result<usize, GitError> {
+    if branches.is_empty() {
+        return Ok(0);
+    }
+
+    let mut args: Vec<OsString> = vec!["rev-list".into(), "--count".into()];
+    args.extend(
+        branches
+            .iter()
+            .map(|branch| branch.to_os_str().into_owned()),
+    );
+    args.push("--not".into());
+    args.extend(branches.iter().map(|branch| {
+        let exclude = [b"--exclude=refs/heads/", branch.as_bytes()].concat();
+        os_str(&exclude).into_owned()
+    }));
+    if exclude_head {
+        args.push("--exclude=HEAD".into());
+    }
+    args.push("--all".into());
+
+    let output = execute::execute(path, "git", &args)?;
+    let output = String::from_utf8_lossy(&output);
+
+    match output.trim().parse() {
+        Ok(count) => Ok(count),
+        Err(_) => Err(GitParseError::RevListCount {
+            output: output.to_string(),
+        }
+        .into()),
+    }
+}
+
+#[test]
+fn parse_branch_info() {
+    let sut = BranchInfo::parse(
+        b"main\t1700000000\tInitial commit\nfeature/one\t1600000000\tAdd\ttabs\n",
+        b"main\n",
+    )
+    .unwrap();
+
+    let expected = HashMap::from([
+        (
+            b"main".to_vec(),
+            BranchInfo {
+                committer_timestamp: 1700000000,
+                merged: true,
+                subject: "Initial commit".to_string(),
+            },
+        ),
+        (
+            b"feature/one".to_vec(),
+            BranchInfo {
+                committer_timestamp: 1600000000,
+                merged: false,
+                subject: "Add\ttabs".to_string(),
+            },
+        ),
+    ]);
+
+    assert_eq!(sut, expected);
+}
+
+#[test]
+fn parse_invalid_timestamp() {
+    BranchInfo::parse(b"main\tyesterday\tsubject", b"").expect_err("Timestamp should be invalid");
+}
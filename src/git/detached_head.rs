@@ -0,0 +1,104 @@
+use super::GitError;
+use crate::execute::{self, Location};
+
+/*
+    git branch -vv only tells us that HEAD is detached, so the commit and the refs
+    containing it are queried separately, and only when HEAD is actually detached
+    Everything here is only displayed, so names are converted to text right away
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DetachedHead {
+    pub label: String,
+    pub commit: String,
+    pub branches: Vec<String>,
+    pub tags: Vec<String>,
+}
+
+impl DetachedHead {
+    pub fn query(path: &impl Location) -> Result<Self, GitError> {
+        let commit = execute::execute(path, "git", &["rev-parse", "--short", "HEAD"])?;
+
+        let refs = execute::execute(
+            path,
+            "git",
+            &[
+                "for-each-ref",
+                "--contains=HEAD",
+                "--format=%(refname)",
+                "refs/heads",
+                "refs/tags",
+            ],
+        )?;
+
+        let commit = String::from_utf8_lossy(&commit);
+        let refs = String::from_utf8_lossy(&refs);
+
+        Ok(DetachedHead::parse(commit.trim(), &refs))
+    }
+
+    fn parse(commit: &str, refs: &str) -> Self {
+        let mut branches = Vec::new();
+        let mut tags = Vec::new();
+
+        for line in refs.lines() {
+            if let Some(branch) = line.strip_prefix("refs/heads/") {
+                branches.push(branch.to_string());
+            } else if let Some(tag) = line.strip_prefix("refs/tags/") {
+                tags.push(tag.to_string());
+            }
+        }
+
+        DetachedHead {
+            label: format!("(HEAD detached at {commit})"),
+            commit: commit.to_string(),
+            branches,
+            tags,
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        let containing: Vec<String> = self
+            .branches
+            .iter()
+            .cloned()
+            .chain(self.tags.iter().map(|tag| format!("tag {tag}")))
+            .collect();
+
+        if containing.is_empty() {
+            self.label.clone()
+        } else {
+            format!("{}, contained in: {}", self.label, containing.join(", "))
+        }
+    }
+}
+
+#[test]
+fn parse_detached_head() {
+    let sut = DetachedHead::parse("1f02cc2", "refs/heads/main\nrefs/tags/v1.0\n");
+
+    let expected = DetachedHead {
+        label: "(HEAD detached at 1f02cc2)".to_string(),
+        commit: "1f02cc2".to_string(),
+        branches: vec!["main".to_string()],
+        tags: vec!["v1.0".to_string()],
+    };
+
+    assert_eq!(sut, expected);
+}
+
+#[test]
+fn describe_detached_head() {
+    let sut = DetachedHead::parse("1f02cc2", "refs/heads/main\nrefs/tags/v1.0\n").describe();
+
+    assert_eq!(
+        sut,
+        "(HEAD detached at 1f02cc2), contained in: main, tag v1.0"
+    );
+}
+
+#[test]
+fn describe_orphan_detached_head() {
+    let sut = DetachedHead::parse("1f02cc2", "").describe();
+
+    assert_eq!(sut, "(HEAD detached at 1f02cc2)");
+}
@@ -1,4 +1,5 @@
 use crate::execute;
+use std::path::PathBuf;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -9,7 +10,8 @@ pub enum GitError {
         source: GitParseError,
     },
 
-    #[error("Error querying git")]
+    // The execute error already names the git command and carries its stderr
+    #[error(transparent)]
     Query {
         #[from]
         source: execute::ExecuteError,
@@ -21,6 +23,28 @@ pub enum GitParseError {
     #[error("Unrecognized pattern while parsing branch: {line}")]
     BranchPattern { line: String },
 
-    #[error("Current branch not found")]
-    CurrentBranch,
+    #[error("Unrecognized pattern while parsing git for-each-ref: {line}")]
+    ForEachRefPattern { line: String },
+
+    #[error("Unrecognized pattern while parsing git submodule status: {line}")]
+    SubmodulePattern { line: String },
+
+    #[error("Unrecognized commit count from git rev-list: {output}")]
+    RevListCount { output: String },
+}
+
+#[derive(Error, Debug)]
+pub enum SnapshotError {
+    #[error("Error reading or writing snapshot {}", path.display())]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    #[error("Unrecognized snapshot format at line {line}: {content}")]
+    Format { line: usize, content: String },
+
+    // Written by another version of git-cleanup
+    #[error("Snapshot format {version} is not supported")]
+    Version { version: String },
 }
@@ -4,6 +4,11 @@ use super::{Branch, GitParseError, LineParser};
 pub enum Head<'a> {
     Branch(Branch<'a>),
     Detached,
+    /*
+        HEAD points to a branch without commits, which git branch doesn't list, e.g. in a new repository
+        or in a bare repository whose HEAD branch was deleted
+    */
+    Unborn,
 }
 
 impl<'a> Head<'a> {
@@ -23,6 +28,10 @@ macro_rules! head {
         crate::git::Head::Detached
     };
 
+    ( unborn $($ignore:tt)* ) => {
+        crate::git::Head::Unborn
+    };
+
     ( $branch_type:ident $args:tt ) => {
         crate::git::Head::Branch($crate::git::branch! { $branch_type $args })
     };
@@ -35,7 +44,7 @@ pub(crate) use head;
 #[test]
 fn detached_head() {
     let mut parser =
-        super::line_parser::new_line_parser("(HEAD detached at 1f02cc2) 1f02cc2 Initial commit");
+        super::line_parser::new_line_parser(b"(HEAD detached at 1f02cc2) 1f02cc2 Initial commit");
 
     let sut = Head::new(&mut parser).unwrap();
 
@@ -47,12 +56,12 @@ fn detached_head() {
 #[test]
 fn regular_branch() {
     let mut parser = super::line_parser::new_line_parser(
-        "main  1f02cc2 [origin/main: ahead by 2] Initial commit",
+        b"main  1f02cc2 [origin/main: ahead by 2] Initial commit",
     );
 
     let sut = Head::new(&mut parser).unwrap();
 
-    let expected = head! { tracking {"main", remote("main", "origin", diverged)} };
+    let expected = head! { tracking {"main", remote("main", "origin", diverged(2, 0))} };
 
     assert_eq!(sut, expected);
 }
@@ -1,4 +1,4 @@
-use regex::Regex;
+use regex::bytes::Regex;
 use std::iter::Peekable;
 use std::sync::OnceLock;
 
@@ -8,19 +8,20 @@ pub(super) trait LineParser<'a> {
     fn consume_if_head(&mut self) -> bool;
     fn consume_if_detached(&mut self) -> bool;
     fn consume_components(&mut self) -> Option<LineComponents<'a>>;
-    fn line(&self) -> &'a str;
+    fn line(&self) -> &'a [u8];
 }
 
 #[derive(PartialEq, Eq, Debug)]
 pub(super) struct LineComponents<'a> {
-    pub branch_name: &'a str,
-    pub maybe_origin_branch: &'a str,
+    pub branch_name: &'a [u8],
+    pub maybe_origin_branch: &'a [u8],
 }
 
-pub(super) fn new_line_parser(line: &str) -> impl LineParser {
-    let regex = COMPONENTS_REGEX.get_or_init(|| Regex::new(r"(\[.*\])+|(\S)+").unwrap());
+pub(super) fn new_line_parser(line: &[u8]) -> impl LineParser<'_> {
+    // Unicode is disabled so that any byte matches, not only valid UTF-8
+    let regex = COMPONENTS_REGEX.get_or_init(|| Regex::new(r"(?-u)(\[.*\])+|(\S)+").unwrap());
     let find_iter = regex.find_iter(line);
-    let iter = find_iter.map(|m| m.as_str());
+    let iter = find_iter.map(|m| m.as_bytes());
     LineParserStruct {
         line,
         iter: iter.peekable(),
@@ -29,15 +30,15 @@ pub(super) fn new_line_parser(line: &str) -> impl LineParser {
 
 struct LineParserStruct<'a, I>
 where
-    I: Iterator<Item = &'a str>,
+    I: Iterator<Item = &'a [u8]>,
 {
     iter: Peekable<I>,
-    line: &'a str, // For debugging purposes
+    line: &'a [u8], // For debugging purposes
 }
 
 impl<'a, I> LineParser<'a> for LineParserStruct<'a, I>
 where
-    I: Iterator<Item = &'a str>,
+    I: Iterator<Item = &'a [u8]>,
 {
     fn consume_if_head(&mut self) -> bool {
         self.consume_if_token("*")
@@ -58,18 +59,18 @@ where
         })
     }
 
-    fn line(&self) -> &'a str {
+    fn line(&self) -> &'a [u8] {
         self.line
     }
 }
 
 impl<'a, I> LineParserStruct<'a, I>
 where
-    I: Iterator<Item = &'a str>,
+    I: Iterator<Item = &'a [u8]>,
 {
     fn consume_if_token(&mut self, expected_token: &'static str) -> bool {
         match self.iter.peek() {
-            Some(peeked_token) if *peeked_token == expected_token => {
+            Some(peeked_token) if *peeked_token == expected_token.as_bytes() => {
                 let _ = self.iter.next();
                 true
             }
@@ -80,32 +81,42 @@ where
 
 #[test]
 fn is_head() {
-    let mut sut = new_line_parser("* main 73b4084 commit message");
+    let mut sut = new_line_parser(b"* main 73b4084 commit message");
     assert!(sut.consume_if_head());
 }
 
 #[test]
 fn is_detached() {
-    let mut sut = new_line_parser("(HEAD and other stuff");
+    let mut sut = new_line_parser(b"(HEAD and other stuff");
     assert!(sut.consume_if_detached());
 }
 
 #[test]
 fn split_components() {
-    let mut sut = new_line_parser("develop 73b4084 [origin/develop] commit message");
+    let mut sut = new_line_parser(b"develop 73b4084 [origin/develop] commit message");
 
     let components = sut.consume_components().unwrap();
 
     let expected = LineComponents {
-        branch_name: "develop",
-        maybe_origin_branch: "[origin/develop]",
+        branch_name: b"develop",
+        maybe_origin_branch: b"[origin/develop]",
     };
 
     assert_eq!(components, expected);
 }
 
+#[test]
+fn non_utf8_branch_name() {
+    let mut sut = new_line_parser(b"caf\xe9 73b4084 [origin/caf\xe9: gone] commit message");
+
+    let components = sut.consume_components().unwrap();
+
+    assert_eq!(components.branch_name, b"caf\xe9");
+    assert_eq!(components.maybe_origin_branch, b"[origin/caf\xe9: gone]");
+}
+
 #[test]
 fn empty_line() {
-    let mut parser = new_line_parser("");
+    let mut parser = new_line_parser(b"");
     assert_eq!(parser.consume_components(), None);
 }
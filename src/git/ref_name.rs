@@ -0,0 +1,76 @@
+use std::borrow::Cow;
+use std::ffi::OsStr;
+
+/*
+    A branch or remote name exactly as git printed it
+    Git allows any bytes in ref names, so they are only converted to text when displayed,
+    and passed back to git unchanged, otherwise git wouldn't find the ref
+*/
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct RefName<'a>(pub &'a [u8]);
+
+impl<'a> RefName<'a> {
+    pub fn as_bytes(self) -> &'a [u8] {
+        self.0
+    }
+
+    pub fn to_string_lossy(self) -> Cow<'a, str> {
+        String::from_utf8_lossy(self.0)
+    }
+
+    pub fn to_os_str(self) -> Cow<'a, OsStr> {
+        os_str(self.0)
+    }
+}
+
+#[cfg(unix)]
+pub fn os_str(bytes: &[u8]) -> Cow<'_, OsStr> {
+    use std::os::unix::ffi::OsStrExt;
+    Cow::Borrowed(OsStr::from_bytes(bytes))
+}
+
+// Other platforms can't build an OsStr from arbitrary bytes, and git for Windows uses UTF-8 names anyway
+#[cfg(not(unix))]
+pub fn os_str(bytes: &[u8]) -> Cow<'_, OsStr> {
+    let name = String::from_utf8_lossy(bytes).into_owned();
+    Cow::Owned(name.into())
+}
+
+impl std::fmt::Display for RefName<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_string_lossy())
+    }
+}
+
+impl std::fmt::Debug for RefName<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.to_string_lossy())
+    }
+}
+
+impl PartialEq<str> for RefName<'_> {
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other.as_bytes()
+    }
+}
+
+impl PartialEq<&str> for RefName<'_> {
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == other.as_bytes()
+    }
+}
+
+#[test]
+fn display_is_lossy() {
+    let sut = RefName(b"caf\xe9");
+    assert_eq!(sut.to_string(), "caf\u{fffd}");
+}
+
+#[test]
+#[cfg(unix)]
+fn os_str_keeps_bytes() {
+    use std::os::unix::ffi::OsStrExt;
+
+    let sut = RefName(b"caf\xe9");
+    assert_eq!(sut.to_os_str().as_bytes(), b"caf\xe9");
+}
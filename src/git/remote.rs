@@ -0,0 +1,541 @@
+use super::{lines, os_str, GitError, GitParseError, RefName};
+use crate::execute::{self, Location};
+use std::ffi::OsStr;
+
+/*
+    Remotes and the tracking refs fetched from them
+    Tracking refs are named the way git branch -r prints them, e.g. origin/feature, and kept as bytes
+*/
+
+pub fn remotes(path: &impl Location) -> Result<Vec<Vec<u8>>, GitError> {
+    let output = execute::execute(path, "git", &["remote"])?;
+
+    Ok(lines(&output).map(<[u8]>::to_vec).collect())
+}
+
+/*
+    The tracking refs of remote, or of every remote. Symbolic refs like origin/HEAD aren't branches, so they are left out
+    The refs of a remote nested in it, like origin/upstream in origin, match its pattern too, so they are left out as well
+*/
+pub fn tracking_refs(
+    path: &impl Location,
+    remote: Option<RefName>,
+) -> Result<Vec<Vec<u8>>, GitError> {
+    let pattern = match remote {
+        Some(remote) => [b"refs/remotes/", remote.as_bytes()].concat(),
+        None => b"refs/remotes".to_vec(),
+    };
+
+    let args = [
+        OsStr::new("for-each-ref"),
+        OsStr::new("--format=%(refname:lstrip=2)%09%(symref)"),
+        &os_str(&pattern),
+    ];
+    let output = execute::execute(path, "git", &args)?;
+    let mut names = parse_tracking_refs(&output)?;
+
+    if let Some(remote) = remote {
+        let remotes = remotes_with(path, remote)?;
+        names.retain(|name| owner(name, &remotes) == Some(remote.as_bytes()));
+    }
+
+    Ok(names)
+}
+
+// The configured remotes, and remote even when it no longer is, so that its refs are still found
+fn remotes_with(path: &impl Location, remote: RefName) -> Result<Vec<Vec<u8>>, GitError> {
+    let mut remotes = remotes(path)?;
+
+    if !remotes.iter().any(|name| name == remote.as_bytes()) {
+        remotes.push(remote.as_bytes().to_vec());
+    }
+
+    Ok(remotes)
+}
+
+/*
+    The remote a tracking ref belongs to. Remote names may contain slashes, so it is the longest one the ref
+    starts with, e.g. origin/upstream/main belongs to origin/upstream, not to origin
+*/
+fn owner<'r>(tracking_ref: &[u8], remotes: &'r [Vec<u8>]) -> Option<&'r [u8]> {
+    remotes
+        .iter()
+        .map(Vec::as_slice)
+        .filter(|remote| {
+            tracking_ref
+                .strip_prefix(*remote)
+                .is_some_and(|rest| rest.starts_with(b"/"))
+        })
+        .max_by_key(|remote| remote.len())
+}
+
+/*
+    Runs git fetch --prune, for the default remote when remote is None, and returns the tracking refs it pruned
+    They are found by comparing the tracking refs before and after, since git reports them in the user's language
+*/
+pub fn fetch_prune(
+    path: &impl Location,
+    remote: Option<RefName>,
+) -> Result<Vec<Vec<u8>>, GitError> {
+    let before = tracking_refs(path, remote)?;
+
+    let mut args = vec![OsStr::new("fetch"), OsStr::new("--prune")];
+    let remote_name = remote.map(|remote| remote.to_os_str());
+    args.extend(remote_name.as_deref());
+
+    let _ = execute::execute_remote(path, "git", &args)?;
+
+    let after = tracking_refs(path, remote)?;
+
+    Ok(before
+        .into_iter()
+        .filter(|name| !after.contains(name))
+        .collect())
+}
+
+/// The tracking refs a fetch of one remote pruned, or why the remote couldn't be fetched
+pub type Pruned = Result<Vec<Vec<u8>>, GitError>;
+
+/*
+    Runs a single git fetch --prune --multiple, fetching up to jobs remotes at once, and returns what each remote pruned
+    Git fetches them from one process, so that no two fetches race for the refs of the repository
+    A failed fetch only tells which remotes failed in the user's language, so then the remotes are fetched
+    again one after the other, which reports the error of each failing one and finds nothing new for the others
+*/
+pub fn fetch_prune_multiple(
+    path: &impl Location,
+    remotes: &[RefName],
+    jobs: usize,
+) -> Result<Vec<Pruned>, GitError> {
+    let before = remotes
+        .iter()
+        .map(|remote| tracking_refs(path, Some(*remote)))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let jobs = format!("--jobs={jobs}");
+    let names: Vec<_> = remotes.iter().map(|remote| remote.to_os_str()).collect();
+    let mut args = vec![
+        OsStr::new("fetch"),
+        OsStr::new("--prune"),
+        OsStr::new("--multiple"),
+        OsStr::new(&jobs),
+    ];
+    args.extend(names.iter().map(|name| &**name));
+
+    let failed = remotes.is_empty() || execute::execute_remote(path, "git", &args).is_err();
+
+    let pruned = remotes
+        .iter()
+        .zip(before)
+        .map(|(remote, before)| {
+            if failed {
+                let args = [
+                    OsStr::new("fetch"),
+                    OsStr::new("--prune"),
+                    &remote.to_os_str(),
+                ];
+                let _ = execute::execute_remote(path, "git", &args)?;
+            }
+
+            let after = tracking_refs(path, Some(*remote))?;
+
+            Ok(before
+                .into_iter()
+                .filter(|name| !after.contains(name))
+                .collect())
+        })
+        .collect();
+
+    Ok(pruned)
+}
+
+/// A remote-tracking ref nothing refers to anymore, which fetching won't always remove
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrphanedRef {
+    /// Named like git branch -r prints it, e.g. origin/feature
+    pub name: Vec<u8>,
+    pub reason: OrphanReason,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrphanReason {
+    /// No local branch has it as upstream, and it isn't the default branch of its remote
+    Untracked,
+    /// It belongs to a remote that is no longer configured, e.g. one that was renamed
+    UnknownRemote,
+}
+
+impl OrphanReason {
+    // Stable names for the porcelain and JSON listings
+    pub fn name(&self) -> &'static str {
+        match self {
+            OrphanReason::Untracked => "untracked",
+            OrphanReason::UnknownRemote => "unknown-remote",
+        }
+    }
+}
+
+/*
+    Needs no network access, so it works when the remote can't be reached
+    The refs origin/HEAD and the like point to are kept, since deleting them leaves those dangling
+*/
+pub fn orphaned_refs(path: &impl Location) -> Result<Vec<OrphanedRef>, GitError> {
+    let tracking_refs = tracking_refs(path, None)?;
+    let remotes = remotes(path)?;
+
+    let upstreams = execute::execute(
+        path,
+        "git",
+        &["for-each-ref", "--format=%(upstream)", "refs/heads"],
+    )?;
+    let symref_targets = execute::execute(
+        path,
+        "git",
+        &["for-each-ref", "--format=%(symref)", "refs/remotes"],
+    )?;
+    let referenced: Vec<&[u8]> = lines(&upstreams)
+        .chain(lines(&symref_targets))
+        .filter_map(|name| name.strip_prefix(b"refs/remotes/"))
+        .collect();
+
+    Ok(find_orphans(&tracking_refs, &remotes, &referenced))
+}
+
+// Deletes the ref itself, so unlike git branch -rd, nothing depends on the fetch refspecs of a remote that may be gone
+pub fn delete_tracking_ref(path: &impl Location, name: RefName) -> Result<(), GitError> {
+    let full_name = [b"refs/remotes/", name.as_bytes()].concat();
+    let args = [
+        OsStr::new("update-ref"),
+        OsStr::new("-d"),
+        &os_str(&full_name),
+    ];
+
+    let _ = execute::execute_to_completion(path, "git", &args)?;
+    Ok(())
+}
+
+// Referenced refs are upstreams of local branches or targets of symbolic refs, which are never orphans
+fn find_orphans(
+    tracking_refs: &[Vec<u8>],
+    remotes: &[Vec<u8>],
+    referenced: &[&[u8]],
+) -> Vec<OrphanedRef> {
+    tracking_refs
+        .iter()
+        .filter(|name| !referenced.contains(&name.as_slice()))
+        .map(|name| {
+            let reason = if owner(name, remotes).is_some() {
+                OrphanReason::Untracked
+            } else {
+                OrphanReason::UnknownRemote
+            };
+
+            OrphanedRef {
+                name: name.clone(),
+                reason,
+            }
+        })
+        .collect()
+}
+
+/// A branch of a remote, as of the last fetch
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteBranchInfo {
+    /// The name of the branch on the remote, e.g. feature for origin/feature
+    pub name: Vec<u8>,
+    /// The full hash of the commit the tracking ref points to
+    pub commit: String,
+    pub committer_timestamp: u64,
+    /// Whether the branch is merged into the base branch of the same remote
+    pub merged: bool,
+}
+
+// Nothing is merged into a base branch the remote doesn't have, which git couldn't even resolve
+pub fn remote_branches(
+    path: &impl Location,
+    remote: RefName,
+    base: RefName,
+) -> Result<Vec<RemoteBranchInfo>, GitError> {
+    let pattern = [b"refs/remotes/", remote.as_bytes()].concat();
+    let args = [
+        OsStr::new("for-each-ref"),
+        OsStr::new(
+            "--format=%(refname:lstrip=2)%09%(symref)%09%(objectname)%09%(committerdate:unix)",
+        ),
+        &os_str(&pattern),
+    ];
+    let output = execute::execute(path, "git", &args)?;
+
+    let remotes = remotes_with(path, remote)?;
+    let mut branches = parse_remote_branches(&output, remote.as_bytes(), &remotes)?;
+
+    if !branches.iter().any(|branch| branch.name == base.as_bytes()) {
+        return Ok(branches);
+    }
+
+    let merged_into = [
+        b"--merged=refs/remotes/",
+        remote.as_bytes(),
+        b"/",
+        base.as_bytes(),
+    ]
+    .concat();
+    let args = [
+        OsStr::new("for-each-ref"),
+        &os_str(&merged_into),
+        OsStr::new("--format=%(refname:lstrip=2)%09%(symref)"),
+        &os_str(&pattern),
+    ];
+    let merged = parse_tracking_refs(&execute::execute(path, "git", &args)?)?;
+
+    for branch in &mut branches {
+        let tracking_ref = [remote.as_bytes(), b"/", &branch.name].concat();
+        branch.merged = merged.contains(&tracking_ref);
+    }
+
+    Ok(branches)
+}
+
+/*
+    Branches are deleted by their full name, so that a tag with the same name is never deleted instead
+    Each deletion is leased on the commit the branch is expected at, so that the remote refuses to delete
+    a branch someone pushed to since the last fetch, instead of losing their commits
+    Pushing may prompt for credentials, so unlike local deletions it can be interrupted
+*/
+pub fn push_delete(
+    path: &impl Location,
+    remote: RefName,
+    branches: &[(RefName, &str)],
+) -> Result<(), GitError> {
+    let full_names: Vec<Vec<u8>> = branches
+        .iter()
+        .map(|(branch, _)| [b"refs/heads/", branch.as_bytes()].concat())
+        .collect();
+
+    let mut args = vec![OsStr::new("push").to_os_string()];
+    args.extend(
+        full_names
+            .iter()
+            .zip(branches)
+            .map(|(full_name, (_, commit))| {
+                let lease = [
+                    b"--force-with-lease=",
+                    &full_name[..],
+                    b":",
+                    commit.as_bytes(),
+                ]
+                .concat();
+                os_str(&lease).into_owned()
+            }),
+    );
+    args.push(remote.to_os_str().into_owned());
+    args.push(OsStr::new("--delete").to_os_string());
+    args.extend(
+        full_names
+            .iter()
+            .map(|full_name| os_str(full_name).into_owned()),
+    );
+
+    let _ = execute::execute_remote(path, "git", &args)?;
+    Ok(())
+}
+
+// Lines of remotes nested in remote, like origin/upstream in origin, are skipped, since they belong to those
+fn parse_remote_branches(
+    output: &[u8],
+    remote: &[u8],
+    remotes: &[Vec<u8>],
+) -> Result<Vec<RemoteBranchInfo>, GitParseError> {
+    let mut branches = Vec::new();
+
+    for line in lines(output) {
+        let pattern_error = || GitParseError::ForEachRefPattern {
+            line: String::from_utf8_lossy(line).to_string(),
+        };
+
+        let mut fields = line.split(|byte| *byte == b'\t');
+
+        let (tracking_ref, symref, commit, timestamp) = match (
+            fields.next(),
+            fields.next(),
+            fields.next(),
+            fields.next(),
+            fields.next(),
+        ) {
+            (Some(tracking_ref), Some(symref), Some(commit), Some(timestamp), None) => {
+                (tracking_ref, symref, commit, timestamp)
+            }
+            _ => return Err(pattern_error()),
+        };
+
+        let name = tracking_ref
+            .strip_prefix(remote)
+            .and_then(|name| name.strip_prefix(b"/"))
+            .ok_or_else(pattern_error)?;
+
+        if !symref.is_empty() || owner(tracking_ref, remotes) != Some(remote) {
+            continue;
+        }
+
+        let committer_timestamp = match std::str::from_utf8(timestamp).map(str::parse) {
+            Ok(Ok(timestamp)) => timestamp,
+            _ => return Err(pattern_error()),
+        };
+
+        branches.push(RemoteBranchInfo {
+            name: name.to_vec(),
+            commit: String::from_utf8_lossy(commit).into_owned(),
+            committer_timestamp,
+            merged: false,
+        });
+    }
+
+    Ok(branches)
+}
+
+fn parse_tracking_refs(output: &[u8]) -> Result<Vec<Vec<u8>>, GitParseError> {
+    let mut names = Vec::new();
+
+    for line in lines(output) {
+        let Some(separator) = line.iter().position(|byte| *byte == b'\t') else {
+            return Err(GitParseError::ForEachRefPattern {
+                line: String::from_utf8_lossy(line).to_string(),
+            });
+        };
+
+        let (name, symref) = (&line[..separator], &line[separator + 1..]);
+
+        if symref.is_empty() {
+            names.push(name.to_vec());
+        }
+    }
+
+    Ok(names)
+}
+
+#[test]
+fn tracking_refs_without_symbolic_ones() {
+    let output = b"origin/HEAD\trefs/remotes/origin/main\norigin/main\t\nupstream/release/2.0\t\n";
+
+    let sut = parse_tracking_refs(output).unwrap();
+
+    assert_eq!(
+        sut,
+        [b"origin/main".to_vec(), b"upstream/release/2.0".to_vec()]
+    );
+}
+
+#[test]
+fn remote_branches_without_remote_prefix() {
+    let output = b"upstream/HEAD\trefs/remotes/upstream/main\t1f02cc2\t1700000000\n\
+                   upstream/main\t\t1f02cc2\t1700000000\n\
+                   upstream/release/2.0\t\t9a3e0b1\t1600000000\n";
+
+    let sut = parse_remote_branches(output, b"upstream", &[b"upstream".to_vec()]).unwrap();
+
+    let expected = [
+        RemoteBranchInfo {
+            name: b"main".to_vec(),
+            commit: "1f02cc2".to_string(),
+            committer_timestamp: 1700000000,
+            merged: false,
+        },
+        RemoteBranchInfo {
+            name: b"release/2.0".to_vec(),
+            commit: "9a3e0b1".to_string(),
+            committer_timestamp: 1600000000,
+            merged: false,
+        },
+    ];
+
+    assert_eq!(sut, expected);
+}
+
+#[test]
+fn remote_branches_invalid_lines() {
+    let remotes = [b"origin".to_vec()];
+
+    assert!(
+        parse_remote_branches(b"origin/main\t\t1f02cc2\tyesterday\n", b"origin", &remotes).is_err()
+    );
+    assert!(parse_remote_branches(
+        b"upstream/main\t\t1f02cc2\t1700000000\n",
+        b"origin",
+        &remotes
+    )
+    .is_err());
+    assert!(parse_remote_branches(b"origin/main\t\t1700000000\n", b"origin", &remotes).is_err());
+}
+
+#[test]
+fn remote_branches_of_nested_remotes() {
+    let output = b"origin/main\t\t1f02cc2\t1700000000\n\
+                   origin/upstream/main\t\t9a3e0b1\t1600000000\n";
+    let remotes = [b"origin".to_vec(), b"origin/upstream".to_vec()];
+
+    let sut = parse_remote_branches(output, b"origin", &remotes).unwrap();
+    let names: Vec<&[u8]> = sut.iter().map(|branch| branch.name.as_slice()).collect();
+    assert_eq!(names, [b"main"]);
+
+    let output = b"origin/upstream/main\t\t9a3e0b1\t1600000000\n";
+    let sut = parse_remote_branches(output, b"origin/upstream", &remotes).unwrap();
+    let names: Vec<&[u8]> = sut.iter().map(|branch| branch.name.as_slice()).collect();
+    assert_eq!(names, [b"main"]);
+    assert_eq!(sut[0].commit, "9a3e0b1");
+}
+
+#[test]
+fn owner_is_the_longest_remote() {
+    let remotes = [
+        b"origin".to_vec(),
+        b"origin/upstream".to_vec(),
+        b"up".to_vec(),
+    ];
+
+    assert_eq!(owner(b"origin/main", &remotes), Some(&b"origin"[..]));
+    assert_eq!(
+        owner(b"origin/upstream/main", &remotes),
+        Some(&b"origin/upstream"[..])
+    );
+    assert_eq!(owner(b"upstream/main", &remotes), None);
+}
+
+#[test]
+fn orphans_of_unknown_remotes_and_untracked() {
+    let tracking_refs = [
+        b"origin/main".to_vec(),
+        b"origin/old".to_vec(),
+        b"team/tools/main".to_vec(),
+        b"team/tools/develop".to_vec(),
+        b"renamed/main".to_vec(),
+        b"renamed/old".to_vec(),
+    ];
+    let remotes = [b"origin".to_vec(), b"team/tools".to_vec()];
+    // team/tools/develop and renamed/main are pointed to by team/tools/HEAD and renamed/HEAD
+    let referenced: [&[u8]; 4] = [
+        b"origin/main",
+        b"team/tools/main",
+        b"team/tools/develop",
+        b"renamed/main",
+    ];
+
+    let sut = find_orphans(&tracking_refs, &remotes, &referenced);
+
+    let expected = [
+        OrphanedRef {
+            name: b"origin/old".to_vec(),
+            reason: OrphanReason::Untracked,
+        },
+        OrphanedRef {
+            name: b"renamed/old".to_vec(),
+            reason: OrphanReason::UnknownRemote,
+        },
+    ];
+
+    assert_eq!(sut, expected);
+}
+
+#[test]
+fn tracking_refs_invalid_line() {
+    assert!(parse_tracking_refs(b"origin/main\n").is_err());
+}
@@ -1,46 +1,50 @@
-use super::RemoteBranchStatus;
+use super::{RefName, RemoteBranchStatus};
 
 #[derive(Debug, Clone, PartialEq, Hash, Eq)]
 pub struct RemoteBranch<'a> {
-    pub branch_name: &'a str,
-    pub remote_name: &'a str,
+    pub branch_name: RefName<'a>,
+    pub remote_name: RefName<'a>,
     pub status: RemoteBranchStatus,
 }
 
 impl<'a> RemoteBranch<'a> {
-    pub(super) fn parse(string: &'a str) -> Option<Self> {
+    pub(super) fn parse(string: &'a [u8]) -> Option<Self> {
         if !RemoteBranch::validate(string) {
             return None;
         }
 
         let brackless_string = &string[1..string.len() - 1];
 
-        let (left, right) = match brackless_string.split_once(':') {
+        let (left, right) = match split_once(brackless_string, b':') {
             Some(pair) => pair,
-            None => (brackless_string, ""),
+            None => (brackless_string, &b""[..]),
         };
 
         let (remote_name, branch_name) = RemoteBranch::parse_names(left);
-        let status = RemoteBranchStatus::parse(right);
+        let status = RemoteBranchStatus::parse(&String::from_utf8_lossy(right));
 
         Some(RemoteBranch {
-            remote_name,
-            branch_name,
+            remote_name: RefName(remote_name),
+            branch_name: RefName(branch_name),
             status,
         })
     }
 
-    fn validate(string: &str) -> bool {
-        string.starts_with('[') && string.ends_with(']') && string.find('/').is_some()
+    fn validate(string: &[u8]) -> bool {
+        string.starts_with(b"[") && string.ends_with(b"]") && string.contains(&b'/')
     }
 
-    fn parse_names(left: &str) -> (&str, &str) {
+    fn parse_names(left: &[u8]) -> (&[u8], &[u8]) {
         // SAFETY: validate() already checked that '/' exists here
-        let index_slash = left.find('/').unwrap();
-        (&left[..index_slash], &left[index_slash + 1..])
+        split_once(left, b'/').unwrap()
     }
 }
 
+fn split_once(bytes: &[u8], separator: u8) -> Option<(&[u8], &[u8])> {
+    let index = bytes.iter().position(|byte| *byte == separator)?;
+    Some((&bytes[..index], &bytes[index + 1..]))
+}
+
 impl<'a> std::fmt::Display for RemoteBranch<'a> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -55,31 +59,31 @@ impl<'a> std::fmt::Display for RemoteBranch<'a> {
 fn test_remote_branch() {
     let expected = remote!("branch2", "origin", synchronized);
 
-    let sut = RemoteBranch::parse("[origin/branch2]").unwrap();
+    let sut = RemoteBranch::parse(b"[origin/branch2]").unwrap();
 
     assert_eq!(sut, expected);
 }
 
 #[test]
 fn test_remote_branch_ahead_of_origin() {
-    let expected = remote!("main", "origin", diverged);
+    let expected = remote!("main", "origin", diverged(1, 0));
 
-    let sut = RemoteBranch::parse("[origin/main: ahead 1]").unwrap();
+    let sut = RemoteBranch::parse(b"[origin/main: ahead 1]").unwrap();
 
     assert_eq!(sut, expected);
 }
 
 #[test]
 fn test_parse_invalid_lines() {
-    if RemoteBranch::parse("origin/branch2]").is_some() {
+    if RemoteBranch::parse(b"origin/branch2]").is_some() {
         panic!("try_from_vv_column interpreted missing [ as a valid remote branch");
     }
 
-    if RemoteBranch::parse("[origin/branch2").is_some() {
+    if RemoteBranch::parse(b"[origin/branch2").is_some() {
         panic!("try_from_vv_column interpreted missing ] as a valid remote branch");
     }
 
-    if RemoteBranch::parse("originbranch2]").is_some() {
+    if RemoteBranch::parse(b"originbranch2]").is_some() {
         panic!("try_from_vv_column interpreted missing / as a valid remote branch");
     }
 }
@@ -87,11 +91,11 @@ fn test_parse_invalid_lines() {
 #[cfg(test)]
 #[allow(unused_macros)]
 macro_rules! remote {
-    ($branch_name:literal, $remote_name:literal, $status:ident) => {
+    ($branch_name:literal, $remote_name:literal, $($status:tt)+) => {
         $crate::git::RemoteBranch {
-            branch_name: $branch_name,
-            remote_name: $remote_name,
-            status: $crate::git::remote_status!($status),
+            branch_name: $crate::git::RefName($branch_name.as_bytes()),
+            remote_name: $crate::git::RefName($remote_name.as_bytes()),
+            status: $crate::git::remote_status!($($status)+),
         }
     };
 }
@@ -2,7 +2,8 @@
 #[derive(Debug, Clone, PartialEq, Hash, Eq)]
 pub enum RemoteBranchStatus {
     Gone,
-    Diverged,
+    // Commits the local branch has that the remote one doesn't, and the other way around
+    Diverged { ahead: u32, behind: u32 },
     Synchronized,
 }
 
@@ -10,7 +11,10 @@ impl RemoteBranchStatus {
     pub(super) fn parse(string: &str) -> Self {
         // I'm taking some shortcuts so I'm using contains because it is easier to code
         if string.contains("ahead") || string.contains("behind") {
-            return RemoteBranchStatus::Diverged;
+            return RemoteBranchStatus::Diverged {
+                ahead: count_after(string, "ahead"),
+                behind: count_after(string, "behind"),
+            };
         }
 
         if string.contains("gone") {
@@ -19,26 +23,59 @@ impl RemoteBranchStatus {
 
         RemoteBranchStatus::Synchronized
     }
+
+    // The name used by --porcelain and by snapshots, so it must never change
+    pub fn name(&self) -> &'static str {
+        match self {
+            RemoteBranchStatus::Gone => "gone",
+            RemoteBranchStatus::Diverged { .. } => "diverged",
+            RemoteBranchStatus::Synchronized => "synchronized",
+        }
+    }
+}
+
+// The number following word, as in "ahead 2, behind 1", or 0 when word isn't there
+fn count_after(string: &str, word: &str) -> u32 {
+    let Some(index) = string.find(word) else {
+        return 0;
+    };
+
+    let digits: String = string[index + word.len()..]
+        .chars()
+        .skip_while(|character| !character.is_ascii_digit())
+        .take_while(char::is_ascii_digit)
+        .collect();
+
+    digits.parse().unwrap_or(0)
 }
 
 #[test]
 fn ahead() {
     let sut = RemoteBranchStatus::parse("ahead 1");
-    let expected = RemoteBranchStatus::Diverged;
+    let expected = RemoteBranchStatus::Diverged {
+        ahead: 1,
+        behind: 0,
+    };
     assert_eq!(sut, expected);
 }
 
 #[test]
 fn behind() {
     let sut = RemoteBranchStatus::parse("behind 1");
-    let expected = RemoteBranchStatus::Diverged;
+    let expected = RemoteBranchStatus::Diverged {
+        ahead: 0,
+        behind: 1,
+    };
     assert_eq!(sut, expected);
 }
 
 #[test]
 fn ahead_and_behind() {
-    let sut = RemoteBranchStatus::parse("ahead 1, behind 1");
-    let expected = RemoteBranchStatus::Diverged;
+    let sut = RemoteBranchStatus::parse("ahead 12, behind 3");
+    let expected = RemoteBranchStatus::Diverged {
+        ahead: 12,
+        behind: 3,
+    };
     assert_eq!(sut, expected);
 }
 
@@ -62,8 +99,11 @@ macro_rules! remote_status {
     ( gone ) => {
         $crate::git::RemoteBranchStatus::Gone
     };
-    ( diverged ) => {
-        $crate::git::RemoteBranchStatus::Diverged
+    ( diverged($ahead:literal, $behind:literal) ) => {
+        $crate::git::RemoteBranchStatus::Diverged {
+            ahead: $ahead,
+            behind: $behind,
+        }
     };
     ( synchronized ) => {
         $crate::git::RemoteBranchStatus::Synchronized
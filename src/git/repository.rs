@@ -21,10 +21,10 @@ impl<'a> Repository<'a> {
             }
         }
 
-        match head {
-            Some(head) => Ok(Repository { head, branches }),
-            None => Err(GitParseError::CurrentBranch),
-        }
+        Ok(Repository {
+            head: head.unwrap_or(Head::Unborn),
+            branches,
+        })
     }
 }
 
@@ -68,7 +68,7 @@ pub(crate) use repository;
 
 #[test]
 fn one_branch() {
-    let query = GitQuery("* main 73b4084 [origin/main] commit message".to_string());
+    let query = GitQuery(b"* main 73b4084 [origin/main] commit message".to_vec());
 
     let sut = Repository::parse(&query).unwrap();
 
@@ -82,9 +82,9 @@ fn one_branch() {
 #[test]
 fn test_multiple_branches() {
     let query = GitQuery(
-        "* main 73b4084 [origin/main] commit message\n\
+        b"* main 73b4084 [origin/main] commit message\n\
          develop 73b4084 [origin/develop] commit message"
-            .to_string(),
+            .to_vec(),
     );
 
     let sut = Repository::parse(&query).unwrap();
@@ -100,9 +100,9 @@ fn test_multiple_branches() {
 #[test]
 fn test_local_branch() {
     let query = GitQuery(
-        "* main 73b4084 [origin/main] commit message\n\
+        b"* main 73b4084 [origin/main] commit message\n\
          local 73b4084 commit message"
-            .to_string(),
+            .to_vec(),
     );
 
     let sut = Repository::parse(&query).unwrap();
@@ -115,12 +115,49 @@ fn test_local_branch() {
     assert_eq!(sut, expected);
 }
 
+#[test]
+fn test_non_utf8_branch() {
+    let query = GitQuery(
+        b"* main 73b4084 [origin/main] commit message\n\
+          caf\xe9 73b4084 [origin/caf\xe9: gone] commit message"
+            .to_vec(),
+    );
+
+    let sut = Repository::parse(&query).unwrap();
+
+    let name = crate::git::RefName(b"caf\xe9");
+    let expected_branch = Branch::Tracking {
+        name,
+        remote: crate::git::RemoteBranch {
+            branch_name: name,
+            remote_name: crate::git::RefName(b"origin"),
+            status: crate::git::RemoteBranchStatus::Gone,
+        },
+    };
+
+    assert!(sut.branches.contains(&expected_branch));
+}
+
+#[test]
+fn test_unborn_head() {
+    let query = GitQuery(b"  local 73b4084 commit message".to_vec());
+
+    let sut = Repository::parse(&query).unwrap();
+
+    let expected = repository! {
+        *unborn,
+        local("local"),
+    };
+
+    assert_eq!(sut, expected);
+}
+
 #[test]
 fn test_dettached_branch() {
     let query = GitQuery(
-        "* (HEAD detached at 1f02cc2) 1f02cc2 Initial commit\n\
+        b"* (HEAD detached at 1f02cc2) 1f02cc2 Initial commit\n\
          local 73b4084 commit message"
-            .to_string(),
+            .to_vec(),
     );
 
     let sut = Repository::parse(&query).unwrap();
@@ -0,0 +1,413 @@
+use super::{
+    lines, Branch, GitError, GitQuery, Head, RefName, RemoteBranch, RemoteBranchStatus, Repository,
+    SnapshotError,
+};
+use crate::execute::Location;
+use std::fs;
+use std::path::Path;
+
+/*
+    An owned copy of a Repository, which unlike Repository can be cached, sent to other threads,
+    returned from the function that ran the query, and saved to a file
+
+    File format: a version line, the kind of HEAD (branch, detached or unborn), then one line
+    per branch sorted by name, with the tab separated fields of the porcelain format of list
+    followed by how far ahead and behind diverged branches are:
+        git-cleanup snapshot 2
+        head<TAB>branch
+        *<TAB>main<TAB>synchronized<TAB>origin<TAB>main<TAB><TAB>
+        <TAB>topic<TAB>diverged<TAB>origin<TAB>topic<TAB>2<TAB>0
+        <TAB>local<TAB>local<TAB><TAB><TAB><TAB>
+    Git doesn't allow tabs or newlines in ref names, so names are written as they are
+    Version 1 had no ahead and behind columns. Other versions are refused rather than guessed
+*/
+const HEADER: &[u8] = b"git-cleanup snapshot 2";
+const VERSION_PREFIX: &[u8] = b"git-cleanup snapshot ";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    pub head: SnapshotHead,
+    pub branches: Vec<SnapshotBranch>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SnapshotHead {
+    Branch(SnapshotBranch),
+    Detached,
+    Unborn,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SnapshotBranch {
+    Tracking {
+        name: Vec<u8>,
+        remote: SnapshotRemote,
+    },
+
+    Local {
+        name: Vec<u8>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotRemote {
+    pub remote_name: Vec<u8>,
+    pub branch_name: Vec<u8>,
+    pub status: RemoteBranchStatus,
+}
+
+impl Snapshot {
+    pub fn query(path: &impl Location) -> Result<Snapshot, GitError> {
+        let query = GitQuery::query(path)?;
+        let repository = query.to_repository()?;
+
+        Ok(Snapshot::from(&repository))
+    }
+
+    // Borrows the snapshot as a Repository, so that everything working on repositories works on snapshots too
+    pub fn repository(&self) -> Repository<'_> {
+        let head = match &self.head {
+            SnapshotHead::Branch(branch) => Head::Branch(branch.to_branch()),
+            SnapshotHead::Detached => Head::Detached,
+            SnapshotHead::Unborn => Head::Unborn,
+        };
+
+        Repository {
+            head,
+            branches: self
+                .branches
+                .iter()
+                .map(SnapshotBranch::to_branch)
+                .collect(),
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Snapshot, SnapshotError> {
+        let bytes = fs::read(path).map_err(|source| SnapshotError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+
+        Snapshot::parse(&bytes)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), SnapshotError> {
+        fs::write(path, self.serialize()).map_err(|source| SnapshotError::Io {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+
+    pub fn serialize(&self) -> Vec<u8> {
+        let (head_kind, head_branch) = match &self.head {
+            SnapshotHead::Branch(branch) => ("branch", Some(branch)),
+            SnapshotHead::Detached => ("detached", None),
+            SnapshotHead::Unborn => ("unborn", None),
+        };
+
+        let mut branches: Vec<(bool, &SnapshotBranch)> = head_branch
+            .map(|branch| (true, branch))
+            .into_iter()
+            .chain(self.branches.iter().map(|branch| (false, branch)))
+            .collect();
+        branches.sort_by(|(_, left), (_, right)| left.name().cmp(right.name()));
+
+        let mut bytes = [HEADER, b"\n"].concat();
+        bytes.extend_from_slice(format!("head\t{head_kind}\n").as_bytes());
+
+        for (head, branch) in branches {
+            bytes.extend_from_slice(&branch.fields(head).join(&b'\t'));
+            bytes.extend_from_slice(format!("\t{}\n", branch.counts()).as_bytes());
+        }
+
+        bytes
+    }
+
+    pub fn parse(bytes: &[u8]) -> Result<Snapshot, SnapshotError> {
+        let mut lines = lines(bytes)
+            .enumerate()
+            .map(|(index, line)| (index + 1, line));
+
+        match lines.next() {
+            Some((_, HEADER)) => {}
+            Some((_, line)) if line.starts_with(VERSION_PREFIX) => {
+                return Err(SnapshotError::Version {
+                    version: String::from_utf8_lossy(&line[VERSION_PREFIX.len()..]).to_string(),
+                })
+            }
+            Some((number, line)) => return Err(format_error(number, line)),
+            None => return Err(format_error(1, b"")),
+        }
+
+        let mut head = match lines.next() {
+            Some((_, b"head\tbranch")) => None,
+            Some((_, b"head\tdetached")) => Some(SnapshotHead::Detached),
+            Some((_, b"head\tunborn")) => Some(SnapshotHead::Unborn),
+            Some((number, line)) => return Err(format_error(number, line)),
+            None => return Err(format_error(2, b"")),
+        };
+
+        let mut branches = Vec::new();
+
+        for (number, line) in lines {
+            let (is_head, branch) =
+                SnapshotBranch::parse(line).ok_or_else(|| format_error(number, line))?;
+
+            match (is_head, &head) {
+                (false, _) => branches.push(branch),
+                (true, None) => head = Some(SnapshotHead::Branch(branch)),
+                // HEAD is listed twice, or listed although it isn't a branch
+                (true, Some(_)) => return Err(format_error(number, line)),
+            }
+        }
+
+        // A branch HEAD that isn't listed can't be recovered
+        let head = head.ok_or_else(|| format_error(2, b"head\tbranch"))?;
+
+        Ok(Snapshot { head, branches })
+    }
+}
+
+impl From<&Repository<'_>> for Snapshot {
+    fn from(repository: &Repository<'_>) -> Self {
+        let head = match &repository.head {
+            Head::Branch(branch) => SnapshotHead::Branch(SnapshotBranch::from(branch)),
+            Head::Detached => SnapshotHead::Detached,
+            Head::Unborn => SnapshotHead::Unborn,
+        };
+
+        let mut branches: Vec<SnapshotBranch> = repository
+            .branches
+            .iter()
+            .map(SnapshotBranch::from)
+            .collect();
+
+        // HashSet order changes between runs, while snapshots are compared and saved
+        branches.sort_by(|left, right| left.name().cmp(right.name()));
+
+        Snapshot { head, branches }
+    }
+}
+
+impl SnapshotBranch {
+    pub fn name(&self) -> &[u8] {
+        match self {
+            SnapshotBranch::Tracking { name, .. } | SnapshotBranch::Local { name } => name,
+        }
+    }
+
+    fn to_branch(&self) -> Branch<'_> {
+        match self {
+            SnapshotBranch::Tracking { name, remote } => Branch::Tracking {
+                name: RefName(name),
+                remote: RemoteBranch {
+                    branch_name: RefName(&remote.branch_name),
+                    remote_name: RefName(&remote.remote_name),
+                    status: remote.status.clone(),
+                },
+            },
+            SnapshotBranch::Local { name } => Branch::Local {
+                name: RefName(name),
+            },
+        }
+    }
+
+    // Same fields as a porcelain record
+    fn fields(&self, head: bool) -> [&[u8]; 5] {
+        let head: &[u8] = if head { b"*" } else { b"" };
+
+        match self {
+            SnapshotBranch::Tracking { name, remote } => [
+                head,
+                name,
+                remote.status.name().as_bytes(),
+                &remote.remote_name,
+                &remote.branch_name,
+            ],
+            SnapshotBranch::Local { name } => [head, name, b"local", b"", b""],
+        }
+    }
+
+    // The ahead and behind columns, empty unless the branch diverged
+    fn counts(&self) -> String {
+        match self {
+            SnapshotBranch::Tracking {
+                remote:
+                    SnapshotRemote {
+                        status: RemoteBranchStatus::Diverged { ahead, behind },
+                        ..
+                    },
+                ..
+            } => format!("{ahead}\t{behind}"),
+            _ => "\t".to_string(),
+        }
+    }
+
+    fn parse(line: &[u8]) -> Option<(bool, SnapshotBranch)> {
+        let fields: Vec<&[u8]> = line.split(|byte| *byte == b'\t').collect();
+
+        let [head, name, status, remote_name, branch_name, ahead, behind] = fields[..] else {
+            return None;
+        };
+
+        let head = match head {
+            b"*" => true,
+            b"" => false,
+            _ => return None,
+        };
+
+        if name.is_empty() {
+            return None;
+        }
+
+        let status = match status {
+            b"local" => {
+                return Some((
+                    head,
+                    SnapshotBranch::Local {
+                        name: name.to_vec(),
+                    },
+                ))
+            }
+            b"gone" => RemoteBranchStatus::Gone,
+            b"synchronized" => RemoteBranchStatus::Synchronized,
+            b"diverged" => RemoteBranchStatus::Diverged {
+                ahead: parse_count(ahead)?,
+                behind: parse_count(behind)?,
+            },
+            _ => return None,
+        };
+
+        let branch = SnapshotBranch::Tracking {
+            name: name.to_vec(),
+            remote: SnapshotRemote {
+                remote_name: remote_name.to_vec(),
+                branch_name: branch_name.to_vec(),
+                status,
+            },
+        };
+
+        Some((head, branch))
+    }
+}
+
+impl From<&Branch<'_>> for SnapshotBranch {
+    fn from(branch: &Branch<'_>) -> Self {
+        match branch {
+            Branch::Tracking { name, remote } => SnapshotBranch::Tracking {
+                name: name.as_bytes().to_vec(),
+                remote: SnapshotRemote {
+                    remote_name: remote.remote_name.as_bytes().to_vec(),
+                    branch_name: remote.branch_name.as_bytes().to_vec(),
+                    status: remote.status.clone(),
+                },
+            },
+            Branch::Local { name } => SnapshotBranch::Local {
+                name: name.as_bytes().to_vec(),
+            },
+        }
+    }
+}
+
+fn parse_count(field: &[u8]) -> Option<u32> {
+    std::str::from_utf8(field).ok()?.parse().ok()
+}
+
+fn format_error(line: usize, content: &[u8]) -> SnapshotError {
+    SnapshotError::Format {
+        line,
+        content: String::from_utf8_lossy(content).to_string(),
+    }
+}
+
+#[cfg(test)]
+fn sample() -> Repository<'static> {
+    crate::git::repository! {
+        *tracking { "main", remote("main", "origin", synchronized) },
+        tracking { "feature", remote("feature", "upstream", gone) },
+        tracking { "topic", remote("topic", "origin", diverged(2, 1)) },
+        local("local"),
+    }
+}
+
+#[test]
+fn round_trip_through_repository() {
+    let repository = sample();
+
+    let sut = Snapshot::from(&repository);
+
+    assert_eq!(sut.repository(), repository);
+}
+
+#[test]
+fn serialize_sorted_by_name() {
+    let sut = Snapshot::from(&sample()).serialize();
+
+    let expected = b"git-cleanup snapshot 2\n\
+                     head\tbranch\n\
+                     \tfeature\tgone\tupstream\tfeature\t\t\n\
+                     \tlocal\tlocal\t\t\t\t\n\
+                     *\tmain\tsynchronized\torigin\tmain\t\t\n\
+                     \ttopic\tdiverged\torigin\ttopic\t2\t1\n";
+
+    assert_eq!(sut, expected);
+}
+
+#[test]
+fn parse_serialized() {
+    let snapshot = Snapshot::from(&sample());
+
+    let sut = Snapshot::parse(&snapshot.serialize()).unwrap();
+
+    assert_eq!(sut, snapshot);
+}
+
+#[test]
+fn parse_detached_and_non_utf8() {
+    let sut =
+        Snapshot::parse(b"git-cleanup snapshot 2\nhead\tdetached\n\tcaf\xe9\tlocal\t\t\t\t\n")
+            .unwrap();
+
+    let expected = Snapshot {
+        head: SnapshotHead::Detached,
+        branches: vec![SnapshotBranch::Local {
+            name: b"caf\xe9".to_vec(),
+        }],
+    };
+
+    assert_eq!(sut, expected);
+}
+
+#[test]
+fn parse_errors() {
+    let inputs: [&[u8]; 7] = [
+        b"",
+        b"git-cleanup snapshot\nhead\tunborn\n",
+        b"git-cleanup snapshot 2\nhead\tbranch\n",
+        b"git-cleanup snapshot 2\nhead\tunborn\n*\tmain\tlocal\t\t\t\t\n",
+        b"git-cleanup snapshot 2\nhead\tunborn\n\tmain\tahead\torigin\tmain\t\t\n",
+        b"git-cleanup snapshot 2\nhead\tunborn\n\tmain\tdiverged\torigin\tmain\t\t\n",
+        b"git-cleanup snapshot 2\nhead\tunborn\n\tmain\tlocal\t\t\n",
+    ];
+
+    for input in inputs {
+        assert!(
+            Snapshot::parse(input).is_err(),
+            "{}",
+            String::from_utf8_lossy(input)
+        );
+    }
+}
+
+#[test]
+fn parse_other_version() {
+    let sut = Snapshot::parse(b"git-cleanup snapshot 1\nhead\tunborn\n\tmain\tlocal\t\t\n");
+
+    assert!(matches!(sut, Err(SnapshotError::Version { version }) if version == "1"));
+}
+
+#[test]
+fn snapshot_is_owned() {
+    fn assert_owned<T: Send + Sync + 'static>() {}
+    assert_owned::<Snapshot>();
+}
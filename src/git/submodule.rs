@@ -0,0 +1,102 @@
+use super::{lines, GitError, GitParseError};
+use crate::execute::{self, Location};
+
+/*
+    Submodules as listed by git submodule status --recursive, one per line:
+        -<sha> <path>                  not initialised
+        [ +U]<sha> <path> (<describe>)  initialised, the prefix telling whether it is at the recorded commit
+    Paths are relative to the top of the repository, and nested submodules are listed after their parent
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Submodule {
+    pub path: Vec<u8>,
+    pub initialized: bool,
+}
+
+pub fn submodules(path: &impl Location) -> Result<Vec<Submodule>, GitError> {
+    let output = execute::execute(path, "git", &["submodule", "status", "--recursive"])?;
+
+    let submodules = lines(&output)
+        .map(|line| {
+            parse_submodule(line).ok_or_else(|| GitParseError::SubmodulePattern {
+                line: String::from_utf8_lossy(line).to_string(),
+            })
+        })
+        .collect::<Result<_, _>>()?;
+
+    Ok(submodules)
+}
+
+fn parse_submodule(line: &[u8]) -> Option<Submodule> {
+    let (&state, rest) = line.split_first()?;
+    let initialized = match state {
+        b'-' => false,
+        b' ' | b'+' | b'U' => true,
+        _ => return None,
+    };
+
+    let separator = rest.iter().position(|byte| *byte == b' ')?;
+    let (sha, path) = (&rest[..separator], &rest[separator + 1..]);
+
+    if sha.is_empty() || !sha.iter().all(u8::is_ascii_hexdigit) {
+        return None;
+    }
+
+    // The description only follows initialised submodules, and paths may contain spaces themselves
+    let path = match path.strip_suffix(b")") {
+        Some(described) if initialized => {
+            let start = described.windows(2).rposition(|window| window == b" (")?;
+            &described[..start]
+        }
+        _ => path,
+    };
+
+    if path.is_empty() {
+        return None;
+    }
+
+    Some(Submodule {
+        path: path.to_vec(),
+        initialized,
+    })
+}
+
+#[test]
+fn initialized_submodule() {
+    let sut =
+        parse_submodule(b" df55057e30bce5545670fc0313772f6a07913502 libs/my lib (heads/main)");
+
+    let expected = Submodule {
+        path: b"libs/my lib".to_vec(),
+        initialized: true,
+    };
+
+    assert_eq!(sut, Some(expected));
+}
+
+#[test]
+fn uninitialized_submodule() {
+    let sut = parse_submodule(b"-df55057e30bce5545670fc0313772f6a07913502 vendor/tool");
+
+    let expected = Submodule {
+        path: b"vendor/tool".to_vec(),
+        initialized: false,
+    };
+
+    assert_eq!(sut, Some(expected));
+}
+
+#[test]
+fn modified_submodule() {
+    let sut = parse_submodule(b"+df55057e30bce5545670fc0313772f6a07913502 other (v1.0-2-gdf55057)");
+
+    assert_eq!(sut.map(|submodule| submodule.path), Some(b"other".to_vec()));
+}
+
+#[test]
+fn invalid_submodule_lines() {
+    assert_eq!(parse_submodule(b""), None);
+    assert_eq!(parse_submodule(b"?df55057 other"), None);
+    assert_eq!(parse_submodule(b" not-a-sha other"), None);
+    assert_eq!(parse_submodule(b" df55057 "), None);
+}
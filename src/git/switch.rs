@@ -0,0 +1,79 @@
+use super::{lines, os_str, GitError, RefName};
+use crate::execute::{self, Location};
+use std::ffi::OsStr;
+
+// Helpers to move away from the current branch, which git refuses to delete
+
+// The default branch of a remote is the one its HEAD points to, e.g. refs/remotes/origin/HEAD -> origin/main
+pub fn remote_default_branch(path: &impl Location, remote_name: RefName) -> Option<Vec<u8>> {
+    let remote_head = [b"refs/remotes/", remote_name.as_bytes(), b"/HEAD"].concat();
+    let args = [
+        OsStr::new("symbolic-ref"),
+        OsStr::new("--short"),
+        &os_str(&remote_head),
+    ];
+
+    // This fails when the remote HEAD is unknown, e.g. the repository was not cloned, which is not an error for us
+    let output = execute::execute(path, "git", &args).ok()?;
+    let symbolic_ref = lines(&output).next()?;
+
+    parse_remote_default_branch(symbolic_ref, remote_name.as_bytes())
+}
+
+fn parse_remote_default_branch(symbolic_ref: &[u8], remote_name: &[u8]) -> Option<Vec<u8>> {
+    let branch = symbolic_ref.strip_prefix(remote_name)?.strip_prefix(b"/")?;
+
+    if branch.is_empty() {
+        None
+    } else {
+        Some(branch.to_vec())
+    }
+}
+
+// Untracked files are ignored, since git itself refuses to switch branches if they would be overwritten
+pub fn is_worktree_dirty(path: &impl Location) -> Result<bool, GitError> {
+    let output = execute::execute(
+        path,
+        "git",
+        &["status", "--porcelain", "--untracked-files=no"],
+    )?;
+
+    Ok(output.iter().any(|byte| !byte.is_ascii_whitespace()))
+}
+
+pub fn is_bare_repository(path: &impl Location) -> Result<bool, GitError> {
+    let output = execute::execute(path, "git", &["rev-parse", "--is-bare-repository"])?;
+    Ok(output.trim_ascii() == b"true")
+}
+
+/*
+    A checkout killed halfway could leave the working tree partially updated, so it always runs to completion
+    The trailing -- makes git read the name as a branch, even when a file has the same name
+*/
+pub fn switch_branch(path: &impl Location, branch: RefName) -> Result<(), GitError> {
+    let args = [
+        OsStr::new("checkout"),
+        &branch.to_os_str(),
+        OsStr::new("--"),
+    ];
+    let _ = execute::execute_to_completion(path, "git", &args)?;
+    Ok(())
+}
+
+#[test]
+fn remote_default_branch_name() {
+    let sut = parse_remote_default_branch(b"origin/main", b"origin");
+    assert_eq!(sut, Some(b"main".to_vec()));
+}
+
+#[test]
+fn remote_default_branch_with_slash() {
+    let sut = parse_remote_default_branch(b"upstream/release/2.0", b"upstream");
+    assert_eq!(sut, Some(b"release/2.0".to_vec()));
+}
+
+#[test]
+fn remote_default_branch_other_remote() {
+    let sut = parse_remote_default_branch(b"origin/main", b"upstream");
+    assert_eq!(sut, None);
+}
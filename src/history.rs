@@ -0,0 +1,201 @@
+use crate::execute::{self, Location};
+use crate::git::{
+    self, GitError, RemoteBranchStatus, Snapshot, SnapshotBranch, SnapshotError, SnapshotHead,
+};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use thiserror::Error;
+
+/*
+    The branches as they were the last time they were recorded, kept in the git directory
+    diff records them each time, and a fetch with --fetch-prune records them first when nothing
+    is recorded yet, so that the first diff shows what the fetch changed too
+*/
+const STATE_DIRECTORY: &str = "git-cleanup";
+const STATE_FILE: &str = "snapshot";
+
+/// A difference between the recorded branches and the current ones
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change<'a> {
+    Appeared(&'a SnapshotBranch),
+    Disappeared(&'a SnapshotBranch),
+    /// The upstream was deleted from the remote, which is what a fetch with --prune reveals
+    UpstreamGone {
+        before: &'a SnapshotBranch,
+        after: &'a SnapshotBranch,
+    },
+    /// The upstream, or how far ahead or behind it the branch is, changed
+    Changed {
+        before: &'a SnapshotBranch,
+        after: &'a SnapshotBranch,
+    },
+}
+
+#[derive(Error, Debug)]
+pub enum HistoryError {
+    #[error(transparent)]
+    Git(#[from] GitError),
+
+    #[error(transparent)]
+    Snapshot(#[from] SnapshotError),
+}
+
+/// The file the state is recorded in, shared by every worktree of the repository
+pub fn state_path(path: &(impl Location + ?Sized)) -> Result<PathBuf, GitError> {
+    let output = execute::execute(&path, "git", &["rev-parse", "--git-common-dir"])?;
+    let git_dir = git::os_str(output.trim_ascii_end());
+
+    // git prints the directory relative to path, unless it is somewhere else
+    Ok(path
+        .directory()
+        .join(&*git_dir)
+        .join(STATE_DIRECTORY)
+        .join(STATE_FILE))
+}
+
+/// The recorded state, or None when nothing was recorded yet
+pub fn load_state(path: &(impl Location + ?Sized)) -> Result<Option<Snapshot>, HistoryError> {
+    let state_path = state_path(path)?;
+
+    if !state_path.exists() {
+        return Ok(None);
+    }
+
+    Ok(Some(Snapshot::load(&state_path)?))
+}
+
+/// Whether a state was recorded, even one that can't be loaded
+pub fn is_recorded(path: &(impl Location + ?Sized)) -> Result<bool, GitError> {
+    Ok(state_path(path)?.exists())
+}
+
+/// Records snapshot, replacing the previous state
+pub fn save_state(
+    path: &(impl Location + ?Sized),
+    snapshot: &Snapshot,
+) -> Result<(), HistoryError> {
+    let state_path = state_path(path)?;
+
+    if let Some(directory) = state_path.parent() {
+        std::fs::create_dir_all(directory).map_err(|source| SnapshotError::Io {
+            path: directory.to_path_buf(),
+            source,
+        })?;
+    }
+
+    Ok(snapshot.save(&state_path)?)
+}
+
+/// What changed from before to after, sorted by branch name
+pub fn changes<'a>(before: &'a Snapshot, after: &'a Snapshot) -> Vec<Change<'a>> {
+    let before = by_name(before);
+    let mut after = by_name(after);
+    let mut changes = Vec::new();
+
+    for (name, old) in before {
+        let change = match after.remove(name) {
+            None => Some(Change::Disappeared(old)),
+            Some(new) if is_upstream_gone(old, new) => Some(Change::UpstreamGone {
+                before: old,
+                after: new,
+            }),
+            Some(new) if old != new => Some(Change::Changed {
+                before: old,
+                after: new,
+            }),
+            Some(_) => None,
+        };
+
+        changes.extend(change.map(|change| (name, change)));
+    }
+
+    changes.extend(
+        after
+            .into_iter()
+            .map(|(name, new)| (name, Change::Appeared(new))),
+    );
+
+    changes.sort_by_key(|(name, _)| *name);
+    changes.into_iter().map(|(_, change)| change).collect()
+}
+
+// Every branch, including the current one
+fn by_name(snapshot: &Snapshot) -> BTreeMap<&[u8], &SnapshotBranch> {
+    let head = match &snapshot.head {
+        SnapshotHead::Branch(branch) => Some(branch),
+        _ => None,
+    };
+
+    head.into_iter()
+        .chain(&snapshot.branches)
+        .map(|branch| (branch.name(), branch))
+        .collect()
+}
+
+fn is_upstream_gone(before: &SnapshotBranch, after: &SnapshotBranch) -> bool {
+    let status = |branch: &SnapshotBranch| match branch {
+        SnapshotBranch::Tracking { remote, .. } => Some(remote.status.clone()),
+        SnapshotBranch::Local { .. } => None,
+    };
+
+    status(before).is_some_and(|status| status != RemoteBranchStatus::Gone)
+        && status(after) == Some(RemoteBranchStatus::Gone)
+}
+
+#[cfg(test)]
+fn snapshot(repository: git::Repository) -> Snapshot {
+    Snapshot::from(&repository)
+}
+
+#[test]
+fn changes_between_snapshots() {
+    let before = snapshot(git::repository! {
+        *tracking { "main", remote("main", "origin", synchronized) },
+        tracking { "feature", remote("feature", "origin", synchronized) },
+        tracking { "topic", remote("topic", "origin", diverged(1, 0)) },
+        local("old"),
+        local("same"),
+    });
+    let after = snapshot(git::repository! {
+        *tracking { "main", remote("main", "origin", synchronized) },
+        tracking { "feature", remote("feature", "origin", gone) },
+        tracking { "topic", remote("topic", "origin", diverged(2, 3)) },
+        local("new"),
+        local("same"),
+    });
+
+    let sut = changes(&before, &after);
+
+    let expected = [
+        Change::UpstreamGone {
+            before: &before.branches[0],
+            after: &after.branches[0],
+        },
+        Change::Appeared(&after.branches[1]),
+        Change::Disappeared(&before.branches[1]),
+        Change::Changed {
+            before: &before.branches[3],
+            after: &after.branches[3],
+        },
+    ];
+
+    assert_eq!(sut, expected);
+}
+
+#[test]
+fn head_is_compared_too() {
+    let before = snapshot(git::repository! {
+        *tracking { "main", remote("main", "origin", synchronized) },
+    });
+    let after = snapshot(git::repository! {
+        *detached,
+    });
+
+    let sut = changes(&before, &after);
+
+    let SnapshotHead::Branch(main) = &before.head else {
+        unreachable!()
+    };
+
+    assert_eq!(sut, [Change::Disappeared(main)]);
+}
@@ -0,0 +1,41 @@
+// Minimal JSON support, just enough to emit documents without pulling a serialization crate
+use std::fmt;
+
+/// A JSON value rendered by this crate, which documents of several repositories embed as it is
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Json(pub(crate) String);
+
+impl fmt::Display for Json {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(&self.0)
+    }
+}
+
+pub fn string(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len() + 2);
+
+    escaped.push('"');
+
+    for character in value.chars() {
+        match character {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped.push('"');
+
+    escaped
+}
+
+#[test]
+fn escape_string() {
+    let sut = string("a \"quoted\"\\path\n\u{1}");
+    let expected = r#""a \"quoted\"\\path\n\u0001""#;
+    assert_eq!(sut, expected);
+}
@@ -0,0 +1,37 @@
+//! Finds and deletes local git branches whose upstream is gone.
+//!
+//! The library is split the same way the git-cleanup command line works:
+//!
+//! - [`git`] queries a repository and parses the result into a [`git::Repository`]
+//! - [`classify`] sorts branches into local, gone, diverged and synchronized ones
+//! - [`clean`] plans which branches a cleanup deletes, and runs the plan
+//! - [`history`] records the branches between runs, and tells what changed since
+//! - [`report`] renders branch listings, statistics and changes
+//! - [`scan`] finds the repositories under a directory, and [`workspace`] reads them from a manifest
+//! - [`execute`] runs git through an [`execute::Executor`], and holds the process wide settings like the timeout
+//!
+//! ```no_run
+//! use git_cleanup::clean::CleanupPlan;
+//! use git_cleanup::git::GitQuery;
+//!
+//! let path = std::env::current_dir()?;
+//! let query = GitQuery::query(&path)?;
+//! let repository = query.to_repository()?;
+//!
+//! let plan = CleanupPlan::new(&path, &repository, None)?;
+//! for branch in &plan.branches {
+//!     println!("{branch} would be deleted");
+//! }
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+pub mod classify;
+pub mod clean;
+pub mod execute;
+pub mod git;
+pub mod history;
+pub mod report;
+pub mod scan;
+pub mod workspace;
+
+mod json;
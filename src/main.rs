@@ -1,64 +1,268 @@
 mod args;
 mod commands;
-mod execute;
-mod git;
-mod git_integration_tests;
-mod test_support;
 
-use args::{Arguments, ProgramCommand};
-use execute::ExecuteError;
-use git::GitQuery;
-use std::{env, path::PathBuf};
+use args::{
+    Arguments, CleanArguments, CleanOption, ManOption, ProgramCommand, PruneRemoteArguments,
+    PruneRemoteOption,
+};
+use git_cleanup::execute::{self, Context, GitDir, System, Verbosity};
+use git_cleanup::git::GitQuery;
+use std::path::{Path, PathBuf};
+use std::{env, time::Duration};
 
 fn main() -> anyhow::Result<()> {
     let arguments = args::parse_arguments()?;
 
+    execute::set_verbosity(verbosity_from(&arguments));
+    execute::set_timeout(timeout_from(&arguments));
+    execute::set_interactive(is_interactive(&arguments));
+    execute::set_jobs(jobs_from(&arguments));
+
+    // These commands check for the interruption themselves, so that they can stop cleanly. Others just exit
+    if handles_interruption(&arguments) {
+        ctrlc::set_handler(execute::interrupt)?;
+    }
+
     if print_help(&arguments) {
         return Ok(());
     }
 
-    let path = process_options(&arguments)?;
+    // These commands don't operate on the repository state
+    match &arguments.command {
+        Some(ProgramCommand::Completions(option)) => {
+            commands::completions(option);
+            return Ok(());
+        }
+        Some(ProgramCommand::Man(ManOption::Help)) => {
+            print!("{}", args::command_help("man"));
+            return Ok(());
+        }
+        Some(ProgramCommand::Man(ManOption::Print)) => {
+            print!("{}", args::man_page());
+            return Ok(());
+        }
+        Some(ProgramCommand::Complete(option)) => {
+            let (path, git_dir) = (path_from(&arguments)?, git_dir_from(&arguments)?);
+            commands::complete(&location(&path, git_dir.as_deref()), option);
+            return Ok(());
+        }
+        _ => {}
+    }
+
+    match repositories_from(&arguments)? {
+        Some(source) => run_many(&source, &arguments)?,
+        None => run(&arguments)?,
+    }
+
+    // Like other programs killed by SIGINT, report the interruption in the exit code
+    if execute::is_interrupted() {
+        std::process::exit(130);
+    }
+
+    Ok(())
+}
+
+fn run(arguments: &Arguments) -> anyhow::Result<()> {
+    let (path, git_dir) = (path_from(arguments)?, git_dir_from(arguments)?);
+    let path = location(&path, git_dir.as_deref());
+
+    process_options(&path, arguments)?;
 
     let git_query = GitQuery::query(&path)?;
     let repository = git_query.to_repository()?;
 
-    match arguments.command {
-        Some(ProgramCommand::List(option)) => commands::list(&repository, &option),
-        Some(ProgramCommand::Clean(option)) => commands::clean(&path, repository, &option),
+    match &arguments.command {
+        Some(ProgramCommand::List(arguments)) => commands::list(&path, &repository, arguments)?,
+        Some(ProgramCommand::Clean(arguments)) => commands::clean(&path, &repository, arguments),
+        Some(ProgramCommand::Stats(option)) => commands::stats(&path, &repository, option)?,
+        Some(ProgramCommand::Diff(option)) => commands::diff(&path, &repository, option)?,
+        Some(ProgramCommand::PruneRemote(arguments)) => commands::prune_remote(&path, arguments)?,
         _ => {}
     };
 
     Ok(())
 }
 
-// We want to keep the alignment equal among all strings so that we can visually identify wrong indentation
-#[rustfmt::skip]
+// Where --recursive, --workspace and --submodules take the repositories to run in from
+enum RepositorySource {
+    Scan(PathBuf),
+    Workspace(PathBuf),
+    // The repository of --path, whose submodules are added to it
+    Single(PathBuf),
+}
+
+impl RepositorySource {
+    // The option the repositories were given with, for errors
+    fn option(&self) -> &'static str {
+        match self {
+            RepositorySource::Scan(_) => "--recursive",
+            RepositorySource::Workspace(_) => "--workspace",
+            RepositorySource::Single(_) => "--submodules",
+        }
+    }
+}
+
+// Every repository is fetched, queried and listed or cleaned on its own
+fn run_many(source: &RepositorySource, arguments: &Arguments) -> anyhow::Result<()> {
+    if git_dir_from(arguments)?.is_some() {
+        anyhow::bail!("--recursive, --workspace and --submodules can't be combined with --git-dir");
+    }
+
+    let nested = arguments
+        .options
+        .contains(&args::ProgramOption::IncludeNested);
+    let fetch = remotes_from(arguments)?;
+
+    let submodules = arguments.options.contains(&args::ProgramOption::Submodules);
+
+    let targets = || -> Result<Vec<commands::Target>, commands::RepositoriesError> {
+        let targets = match source {
+            RepositorySource::Scan(root) => commands::scan_targets(root, nested)?,
+            RepositorySource::Workspace(file) => commands::workspace_targets(file)?,
+            RepositorySource::Single(path) => vec![commands::Target::new(path.clone(), ".")],
+        };
+
+        if submodules {
+            Ok(commands::with_submodules(targets))
+        } else {
+            Ok(targets)
+        }
+    };
+
+    match &arguments.command {
+        Some(ProgramCommand::List(list_arguments)) => {
+            commands::list_repositories(&targets()?, list_arguments, fetch.as_ref())?;
+        }
+        Some(ProgramCommand::Clean(clean_arguments)) => {
+            commands::clean_repositories(&targets()?, clean_arguments, fetch.as_ref())?;
+        }
+        _ => anyhow::bail!(
+            "{} only works with the list and clean commands",
+            source.option()
+        ),
+    }
+
+    Ok(())
+}
+
 fn print_help(arguments: &Arguments) -> bool {
     let print_help = arguments.options.is_empty() && arguments.command.is_none()
         || arguments.options.contains(&args::ProgramOption::Help);
 
     if print_help {
-        println!("Options:");
-        println!("    --help: Print help");
-        println!("    --path <PATH>: Execute operations in another path");
-        println!("    --fetch-prune: Execute git fetch --prune before executing specified command");
-        println!();
-        println!("Commands:");
-        println!("    list: List branches");
-        println!("    clean: Delete local branches that are gone from origin");
-        println!();
-        println!("Execute each comand with --help for available options");
+        print!("{}", args::Help::program().to_terminal());
     }
 
     print_help
 }
 
-fn process_options(arguments: &Arguments) -> anyhow::Result<PathBuf> {
-    let path = path_from(arguments)?;
+fn verbosity_from(arguments: &Arguments) -> Verbosity {
+    if arguments.options.contains(&args::ProgramOption::Trace) {
+        Verbosity::Trace
+    } else if arguments.options.contains(&args::ProgramOption::Verbose) {
+        Verbosity::Verbose
+    } else {
+        Verbosity::Quiet
+    }
+}
+
+// Cleanups stop between deletions and report what was deleted
+fn handles_interruption(arguments: &Arguments) -> bool {
+    matches!(
+        arguments.command,
+        Some(ProgramCommand::Clean(_) | ProgramCommand::PruneRemote(_))
+    )
+}
 
-    fetch_prune(&path, arguments)?;
+fn timeout_from(arguments: &Arguments) -> Option<Duration> {
+    let seconds = arguments
+        .options
+        .iter()
+        .find_map(|option| match option {
+            args::ProgramOption::Timeout(seconds) => Some(*seconds),
+            _ => None,
+        })
+        .unwrap_or(execute::DEFAULT_TIMEOUT_SECONDS);
 
-    Ok(path)
+    (seconds > 0).then(|| Duration::from_secs(seconds))
+}
+
+fn jobs_from(arguments: &Arguments) -> usize {
+    arguments
+        .options
+        .iter()
+        .find_map(|option| match option {
+            args::ProgramOption::Jobs(jobs) => Some(*jobs),
+            _ => None,
+        })
+        .unwrap_or(1)
+}
+
+// Only a stepped clean and a confirmed prune-remote talk to the user, so git may only prompt for credentials then
+fn is_interactive(arguments: &Arguments) -> bool {
+    matches!(
+        &arguments.command,
+        Some(ProgramCommand::Clean(CleanArguments {
+            option: CleanOption::Step,
+            ..
+        })) | Some(ProgramCommand::PruneRemote(PruneRemoteArguments {
+            option: PruneRemoteOption::Confirm,
+            ..
+        }))
+    )
+}
+
+fn process_options(path: &Context, arguments: &Arguments) -> anyhow::Result<()> {
+    if let Some(remotes) = remotes_from(arguments)? {
+        let fetched = commands::fetch_prune(path, &remotes)?;
+        eprint!("{}", commands::fetch_report(&fetched));
+    }
+
+    Ok(())
+}
+
+// Without --git-dir, git finds the repository from --path, honoring GIT_DIR and GIT_WORK_TREE
+fn location<'a>(path: &'a Path, git_dir: Option<&'a Path>) -> Context<'a> {
+    let location = Context::new(path, &System);
+
+    match git_dir {
+        Some(git_dir) => location.with_git_dir(GitDir::At(git_dir)),
+        None => location,
+    }
+}
+
+// The remotes to fetch, or None without --fetch-prune
+fn remotes_from(arguments: &Arguments) -> anyhow::Result<Option<commands::Remotes>> {
+    let mut named: Vec<Vec<u8>> = arguments
+        .options
+        .iter()
+        .filter_map(|option| match option {
+            args::ProgramOption::Remote(remote) => Some(remote.as_bytes().to_vec()),
+            _ => None,
+        })
+        .collect();
+
+    // Options are a set, so the order they were given in is lost anyway
+    named.sort();
+
+    let all = arguments.options.contains(&args::ProgramOption::AllRemotes);
+
+    if !arguments.options.contains(&args::ProgramOption::FetchPrune) {
+        if all || !named.is_empty() {
+            anyhow::bail!("--remote and --all-remotes only work with --fetch-prune");
+        }
+
+        return Ok(None);
+    }
+
+    let remotes = match (all, named.is_empty()) {
+        (true, false) => anyhow::bail!("--remote and --all-remotes can't be combined"),
+        (true, true) => commands::Remotes::All,
+        (false, true) => commands::Remotes::Default,
+        (false, false) => commands::Remotes::Named(named),
+    };
+
+    Ok(Some(remotes))
 }
 
 fn path_from(arguments: &Arguments) -> Result<PathBuf, std::io::Error> {
@@ -76,10 +280,43 @@ fn path_from(arguments: &Arguments) -> Result<PathBuf, std::io::Error> {
     Ok(path)
 }
 
-fn fetch_prune(path: &PathBuf, arguments: &Arguments) -> Result<(), ExecuteError> {
-    if arguments.options.contains(&args::ProgramOption::FetchPrune) {
-        let _ = execute::execute(path, "git", &["fetch", "--prune"])?;
+// Relative paths are resolved from the current directory, like --git-dir
+fn repositories_from(arguments: &Arguments) -> anyhow::Result<Option<RepositorySource>> {
+    let current_dir = env::current_dir()?;
+
+    let mut sources = arguments.options.iter().filter_map(|option| match option {
+        args::ProgramOption::Recursive(root) => {
+            Some(RepositorySource::Scan(current_dir.join(root)))
+        }
+        args::ProgramOption::Workspace(file) => {
+            Some(RepositorySource::Workspace(current_dir.join(file)))
+        }
+        _ => None,
+    });
+
+    let source = sources.next();
+
+    if sources.next().is_some() {
+        anyhow::bail!("--recursive and --workspace can't be combined");
     }
 
-    Ok(())
+    match source {
+        None if arguments.options.contains(&args::ProgramOption::Submodules) => {
+            Ok(Some(RepositorySource::Single(path_from(arguments)?)))
+        }
+        source => Ok(source),
+    }
+}
+
+// Relative paths are resolved from the current directory, like --path, not from --path
+fn git_dir_from(arguments: &Arguments) -> Result<Option<PathBuf>, std::io::Error> {
+    let git_dir = arguments.options.iter().find_map(|option| match option {
+        args::ProgramOption::GitDir(git_dir) => Some(git_dir),
+        _ => None,
+    });
+
+    match git_dir {
+        Some(git_dir) => Ok(Some(env::current_dir()?.join(git_dir))),
+        None => Ok(None),
+    }
 }
@@ -0,0 +1,24 @@
+/*
+    Renders branch listings, orphaned remote-tracking refs, statistics and the changes since the last recorded state
+    Everything returns the rendered output instead of printing it, so the caller decides where it goes
+*/
+
+pub use crate::json::Json;
+
+mod list;
+pub use list::*;
+
+mod tree;
+pub use tree::*;
+
+mod export;
+pub use export::*;
+
+mod stats;
+pub use stats::*;
+
+mod changes;
+pub use changes::*;
+
+mod orphans;
+pub use orphans::*;
@@ -0,0 +1,120 @@
+use crate::git::{RemoteBranchStatus, SnapshotBranch};
+use crate::history::Change;
+
+/// Human readable changes, grouped by kind, with vanished upstreams first since they are what clean deletes
+pub fn changes_text(changes: &[Change]) -> String {
+    if changes.is_empty() {
+        return "No changes since the last recorded state\n".to_string();
+    }
+
+    let mut output = String::new();
+
+    section(
+        &mut output,
+        "Upstream gone",
+        changes,
+        |change| match change {
+            Change::UpstreamGone { after, .. } => Some(describe(after)),
+            _ => None,
+        },
+    );
+    section(&mut output, "Appeared", changes, |change| match change {
+        Change::Appeared(branch) => Some(describe(branch)),
+        _ => None,
+    });
+    section(&mut output, "Disappeared", changes, |change| match change {
+        Change::Disappeared(branch) => Some(describe(branch)),
+        _ => None,
+    });
+    section(&mut output, "Changed", changes, |change| match change {
+        Change::Changed { before, after } => Some(format!(
+            "{}: {} -> {}",
+            String::from_utf8_lossy(after.name()),
+            upstream(before),
+            upstream(after)
+        )),
+        _ => None,
+    });
+
+    output
+}
+
+fn section(
+    output: &mut String,
+    heading: &str,
+    changes: &[Change],
+    row: impl Fn(&Change) -> Option<String>,
+) {
+    let rows: Vec<String> = changes.iter().filter_map(row).collect();
+
+    if rows.is_empty() {
+        return;
+    }
+
+    output.push_str(&format!("{heading}:\n"));
+
+    for row in rows {
+        output.push_str(&format!("    {row}\n"));
+    }
+}
+
+fn describe(branch: &SnapshotBranch) -> String {
+    match branch {
+        SnapshotBranch::Tracking { name, remote } => format!(
+            "{} => {}/{}",
+            String::from_utf8_lossy(name),
+            String::from_utf8_lossy(&remote.remote_name),
+            String::from_utf8_lossy(&remote.branch_name)
+        ),
+        SnapshotBranch::Local { name } => String::from_utf8_lossy(name).into_owned(),
+    }
+}
+
+fn upstream(branch: &SnapshotBranch) -> String {
+    let SnapshotBranch::Tracking { remote, .. } = branch else {
+        return "local".to_string();
+    };
+
+    let status = match remote.status {
+        RemoteBranchStatus::Diverged { ahead, behind } => format!("ahead {ahead}, behind {behind}"),
+        _ => remote.status.name().to_string(),
+    };
+
+    format!(
+        "{}/{} ({status})",
+        String::from_utf8_lossy(&remote.remote_name),
+        String::from_utf8_lossy(&remote.branch_name)
+    )
+}
+
+#[test]
+fn changes_grouped_by_kind() {
+    let before = crate::git::Snapshot::from(&crate::git::repository! {
+        *tracking { "main", remote("main", "origin", synchronized) },
+        tracking { "fix", remote("fix", "origin", synchronized) },
+        tracking { "topic", remote("topic", "origin", synchronized) },
+        local("old"),
+    });
+    let after = crate::git::Snapshot::from(&crate::git::repository! {
+        *tracking { "main", remote("main", "origin", synchronized) },
+        tracking { "fix", remote("fix", "origin", gone) },
+        tracking { "topic", remote("topic", "origin", diverged(2, 1)) },
+        local("new"),
+    });
+
+    let sut = changes_text(&crate::history::changes(&before, &after));
+
+    let expected = "Upstream gone:\n    fix => origin/fix\n\
+                    Appeared:\n    new\n\
+                    Disappeared:\n    old\n\
+                    Changed:\n    topic: origin/topic (synchronized) -> origin/topic (ahead 2, behind 1)\n";
+
+    assert_eq!(sut, expected);
+}
+
+#[test]
+fn no_changes() {
+    let sut = changes_text(&[]);
+
+    assert_eq!(sut, "No changes since the last recorded state\n");
+}
@@ -0,0 +1,183 @@
+use crate::classify::{Record, COLUMNS};
+use crate::git::BranchInfo;
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+/*
+    Markdown and CSV reports share the column model of --porcelain,
+    with the subject of the last commit appended since those reports are meant for humans
+*/
+const SUBJECT_COLUMN: &str = "subject";
+const REPOSITORY_COLUMN: &str = "repository";
+
+pub fn markdown(records: &[Record], infos: &HashMap<Vec<u8>, BranchInfo>) -> String {
+    let header: Vec<&str> = COLUMNS.iter().chain([&SUBJECT_COLUMN]).copied().collect();
+
+    let mut output = format!("| {} |\n", header.join(" | "));
+    output.push_str(&format!("|{}\n", " --- |".repeat(header.len())));
+
+    for record in records {
+        let cells: Vec<String> = fields_with_subject(record, infos)
+            .into_iter()
+            .map(|field| escape_markdown(&field))
+            .collect();
+
+        output.push_str(&format!("| {} |\n", cells.join(" | ")));
+    }
+
+    output
+}
+
+pub fn csv(records: &[Record], infos: &HashMap<Vec<u8>, BranchInfo>) -> String {
+    let header: Vec<&str> = COLUMNS.iter().chain([&SUBJECT_COLUMN]).copied().collect();
+
+    // RFC 4180 requires CRLF line endings
+    let mut output = format!("{}\r\n", header.join(","));
+
+    for record in records {
+        let cells: Vec<String> = fields_with_subject(record, infos)
+            .into_iter()
+            .map(|field| escape_csv(&field))
+            .collect();
+
+        output.push_str(&format!("{}\r\n", cells.join(",")));
+    }
+
+    output
+}
+
+/// CSV reports of several repositories as one table, with the path of the repository as first column
+///
+/// Reports are the output of [`csv`], whose rows never span lines, since names and subjects have no line breaks
+pub fn repositories_csv(reports: &[(&str, impl AsRef<str>)]) -> String {
+    let header: Vec<&str> = [REPOSITORY_COLUMN]
+        .iter()
+        .chain(COLUMNS.iter())
+        .chain([&SUBJECT_COLUMN])
+        .copied()
+        .collect();
+
+    let mut output = format!("{}\r\n", header.join(","));
+
+    for (path, report) in reports {
+        // Every report starts with its own header row
+        for row in report.as_ref().split_terminator("\r\n").skip(1) {
+            output.push_str(&format!("{},{row}\r\n", escape_csv(path)));
+        }
+    }
+
+    output
+}
+
+fn fields_with_subject<'a>(
+    record: &Record<'a>,
+    infos: &'a HashMap<Vec<u8>, BranchInfo>,
+) -> Vec<Cow<'a, str>> {
+    let subject = infos
+        .get(record.name.as_bytes())
+        .map_or("", |info| &info.subject);
+
+    let mut fields = record.text_fields().to_vec();
+    fields.push(Cow::Borrowed(subject));
+    fields
+}
+
+fn escape_markdown(field: &str) -> String {
+    let mut escaped = String::with_capacity(field.len());
+
+    for character in field.chars() {
+        match character {
+            '\\' | '|' | '*' | '_' | '`' | '[' | ']' | '<' | '>' => {
+                escaped.push('\\');
+                escaped.push(character);
+            }
+            '\r' | '\n' => escaped.push(' '),
+            _ => escaped.push(character),
+        }
+    }
+
+    escaped
+}
+
+fn escape_csv(field: &str) -> String {
+    if field.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+use crate::git::RefName;
+
+#[cfg(test)]
+fn sample() -> (Vec<Record<'static>>, HashMap<Vec<u8>, BranchInfo>) {
+    let records = vec![
+        Record {
+            head: true,
+            name: RefName(b"feature_one"),
+            status: "gone",
+            remote_name: RefName(b"origin"),
+            remote_branch: RefName(b"feature_one"),
+        },
+        Record {
+            head: false,
+            name: RefName(b"local"),
+            status: "local",
+            remote_name: RefName(b""),
+            remote_branch: RefName(b""),
+        },
+    ];
+
+    let infos = HashMap::from([(
+        b"feature_one".to_vec(),
+        BranchInfo {
+            committer_timestamp: 0,
+            merged: false,
+            subject: "Fix a | b, \"quoted\"".to_string(),
+        },
+    )]);
+
+    (records, infos)
+}
+
+#[test]
+fn markdown_report() {
+    let (records, infos) = sample();
+
+    let sut = markdown(&records, &infos);
+
+    let expected = "| head | name | status | remote | remote_branch | subject |\n\
+                    | --- | --- | --- | --- | --- | --- |\n\
+                    | \\* | feature\\_one | gone | origin | feature\\_one | Fix a \\| b, \"quoted\" |\n\
+                    |  | local | local |  |  |  |\n";
+
+    assert_eq!(sut, expected);
+}
+
+#[test]
+fn csv_report() {
+    let (records, infos) = sample();
+
+    let sut = csv(&records, &infos);
+
+    let expected = "head,name,status,remote,remote_branch,subject\r\n\
+                    *,feature_one,gone,origin,feature_one,\"Fix a | b, \"\"quoted\"\"\"\r\n\
+                    ,local,local,,,\r\n";
+
+    assert_eq!(sut, expected);
+}
+
+#[test]
+fn csv_report_of_repositories() {
+    let (records, infos) = sample();
+    let report = csv(&records, &infos);
+
+    let sut = repositories_csv(&[("services/api", report), ("web,app", csv(&[], &infos))]);
+
+    let expected = "repository,head,name,status,remote,remote_branch,subject\r\n\
+                    services/api,*,feature_one,gone,origin,feature_one,\"Fix a | b, \"\"quoted\"\"\"\r\n\
+                    services/api,,local,local,,,\r\n";
+
+    assert_eq!(sut, expected);
+}
@@ -0,0 +1,277 @@
+use super::Tree;
+use crate::classify::{self, Record, COLUMNS};
+use crate::git::{Branch, DetachedHead, Repository};
+use crate::json::{self, Json};
+
+/// The groups of branches the text listing can show, each under its own heading
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Section {
+    Local,
+    Tracked,
+    Gone,
+    Diverged,
+}
+
+impl Section {
+    fn heading(self) -> &'static str {
+        match self {
+            Section::Local => "Local branches",
+            Section::Tracked => "Tracked branches",
+            Section::Gone => "Gone branches",
+            Section::Diverged => "Diverged branches",
+        }
+    }
+
+    fn filter(self) -> fn(&&Branch) -> bool {
+        match self {
+            Section::Local => classify::is_local,
+            Section::Tracked => classify::is_tracked,
+            Section::Gone => classify::is_gone,
+            Section::Diverged => classify::is_diverged,
+        }
+    }
+}
+
+/// Human readable listing of the given sections
+///
+/// A detached HEAD is shown only once, along with the first section
+pub fn text(
+    repository: &Repository,
+    detached: Option<&DetachedHead>,
+    sections: &[Section],
+) -> String {
+    let mut output = String::new();
+
+    for (index, section) in sections.iter().enumerate() {
+        let detached = detached.filter(|_| index == 0);
+        output.push_str(&branches_section(repository, detached, *section));
+    }
+
+    output
+}
+
+/// Machine readable listing, with branch names kept byte for byte
+///
+/// Porcelain output is meant for scripts, so it is guaranteed to be stable between versions:
+/// one record per branch, fields separated by tab, records ended by the terminator (newline, or NUL for -z)
+/// Fields: head marker (* or empty), branch name, status (local, synchronized, diverged or gone), remote name, remote branch
+pub fn porcelain(records: &[Record], terminator: u8) -> Vec<u8> {
+    let mut output = Vec::new();
+
+    for record in records {
+        output.extend_from_slice(&record.fields().join(&b'\t'));
+        output.push(terminator);
+    }
+
+    output
+}
+
+/// The records as a JSON array of objects, keyed by the porcelain column names
+///
+/// JSON strings must be valid UTF-8, so names that aren't are shown lossily
+pub fn records_json(records: &[Record]) -> Json {
+    let objects: Vec<String> = records
+        .iter()
+        .map(|record| {
+            let values = record.text_fields();
+            let members: Vec<String> = COLUMNS
+                .iter()
+                .zip(values)
+                .map(|(column, value)| match *column {
+                    "head" => format!("\"head\":{}", record.head),
+                    _ => format!("{}:{}", json::string(column), json::string(&value)),
+                })
+                .collect();
+
+            format!("{{{}}}", members.join(","))
+        })
+        .collect();
+
+    Json(format!("[{}]", objects.join(",")))
+}
+
+/// The listing of one of several repositories: the output of [`records_json`] or [`orphans_json`](super::orphans_json), or why it failed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RepositoryListing {
+    pub path: String,
+    pub listing: Result<Json, String>,
+}
+
+/// Listings of several repositories as one JSON document
+pub fn repositories_json(listings: &[RepositoryListing]) -> String {
+    let objects: Vec<String> = listings
+        .iter()
+        .map(|repository| match &repository.listing {
+            Ok(branches) => format!(
+                "{{\"path\":{},\"branches\":{branches}}}",
+                json::string(&repository.path)
+            ),
+            Err(error) => format!(
+                "{{\"path\":{},\"error\":{}}}",
+                json::string(&repository.path),
+                json::string(error)
+            ),
+        })
+        .collect();
+
+    format!("{{\"repositories\":[{}]}}", objects.join(","))
+}
+
+/// Porcelain listings of several repositories as one, with the path of the repository as first field of every record
+///
+/// Listings are the output of [`porcelain`] or [`orphans_porcelain`](super::orphans_porcelain) with the same terminator
+pub fn repositories_porcelain(listings: &[(&str, impl AsRef<[u8]>)], terminator: u8) -> Vec<u8> {
+    let mut output = Vec::new();
+
+    for (path, listing) in listings {
+        for record in listing.as_ref().split(|byte| *byte == terminator) {
+            if record.is_empty() {
+                continue;
+            }
+
+            output.extend_from_slice(path.as_bytes());
+            output.push(b'\t');
+            output.extend_from_slice(record);
+            output.push(terminator);
+        }
+    }
+
+    output
+}
+
+/// Branches grouped by their /-separated prefix, with only depth levels of groups shown
+pub fn branch_tree(records: Vec<Record>, depth: Option<usize>) -> String {
+    let tree = Tree::new(records);
+
+    format!("Branch tree:\n{}", tree.render(depth))
+}
+
+fn branches_section(
+    repository: &Repository,
+    detached: Option<&DetachedHead>,
+    section: Section,
+) -> String {
+    let mut output = format!("{}:\n", section.heading());
+
+    if let Some(detached) = detached {
+        output.push_str(&format!("    *{}\n", detached.describe()));
+    }
+
+    for record in classify::records(repository, section.filter()) {
+        output.push_str(&format!("    {}\n", text_row(&record)));
+    }
+
+    output
+}
+
+fn text_row(record: &Record) -> String {
+    let head = if record.head { "*" } else { "" };
+
+    match record.status {
+        "local" => format!("{head}{}", record.name),
+        // clean has to switch branches before deleting it, so we make it stand out
+        "gone" if record.head => format!(
+            "{head}{} => {}/{} (current branch)",
+            record.name, record.remote_name, record.remote_branch
+        ),
+        _ => format!(
+            "{head}{} => {}/{}",
+            record.name, record.remote_name, record.remote_branch
+        ),
+    }
+}
+
+#[test]
+fn head_sorted_with_other_branches() {
+    let repository = crate::git::repository! {
+        *tracking { "main", remote("main", "origin", gone) },
+        tracking { "feature", remote("feature", "origin", gone) },
+        tracking { "other", remote("other", "origin", synchronized) },
+    };
+
+    let sut = text(&repository, None, &[Section::Gone]);
+
+    let expected =
+        "Gone branches:\n    feature => origin/feature\n    *main => origin/main (current branch)\n";
+
+    assert_eq!(sut, expected);
+}
+
+#[test]
+fn detached_head_only_in_first_section() {
+    let repository = crate::git::repository! {
+        *detached,
+        local("local"),
+    };
+
+    let detached = DetachedHead {
+        label: "(HEAD detached at 1f02cc2)".to_string(),
+        commit: "1f02cc2".to_string(),
+        branches: vec![],
+        tags: vec![],
+    };
+
+    let sut = text(
+        &repository,
+        Some(&detached),
+        &[Section::Local, Section::Tracked],
+    );
+
+    let expected = format!(
+        "Local branches:\n    *{}\n    local\nTracked branches:\n",
+        detached.describe()
+    );
+
+    assert_eq!(sut, expected);
+}
+
+#[test]
+fn records_as_json() {
+    let repository = crate::git::repository! {
+        *tracking { "main", remote("main", "origin", gone) },
+        local("caf\"e"),
+    };
+
+    let sut = records_json(&classify::records(&repository, classify::is_any)).to_string();
+
+    let expected = "[{\"head\":false,\"name\":\"caf\\\"e\",\"status\":\"local\",\"remote\":\"\",\"remote_branch\":\"\"},\
+                    {\"head\":true,\"name\":\"main\",\"status\":\"gone\",\"remote\":\"origin\",\"remote_branch\":\"main\"}]";
+
+    assert_eq!(sut, expected);
+}
+
+#[test]
+fn listings_of_repositories_as_json() {
+    let listings = [
+        RepositoryListing {
+            path: "api".to_string(),
+            listing: Ok(Json("[]".to_string())),
+        },
+        RepositoryListing {
+            path: "web".to_string(),
+            listing: Err("not a git repository".to_string()),
+        },
+    ];
+
+    let sut = repositories_json(&listings);
+
+    let expected = "{\"repositories\":[{\"path\":\"api\",\"branches\":[]},\
+                    {\"path\":\"web\",\"error\":\"not a git repository\"}]}";
+
+    assert_eq!(sut, expected);
+}
+
+#[test]
+fn porcelain_of_repositories() {
+    let listings: [(&str, &[u8]); 2] = [
+        ("api", b"*\tmain\tgone\torigin\tmain\0\tfix\tlocal\t\t\0"),
+        ("web", b""),
+    ];
+
+    let sut = repositories_porcelain(&listings, b'\0');
+
+    assert_eq!(
+        sut,
+        b"api\t*\tmain\tgone\torigin\tmain\0api\t\tfix\tlocal\t\t\0"
+    );
+}
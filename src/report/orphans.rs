@@ -0,0 +1,108 @@
+use crate::git::{OrphanReason, OrphanedRef, RefName};
+use crate::json::{self, Json};
+
+const REASONS: [(OrphanReason, &str); 2] = [
+    (
+        OrphanReason::Untracked,
+        "Remote-tracking refs no local branch tracks",
+    ),
+    (
+        OrphanReason::UnknownRemote,
+        "Remote-tracking refs of remotes no longer configured",
+    ),
+];
+
+/// Human readable listing of orphaned remote-tracking refs, under a heading for each of reasons
+pub fn orphans_text(orphans: &[OrphanedRef], reasons: &[OrphanReason]) -> String {
+    let mut output = String::new();
+
+    for (reason, heading) in REASONS {
+        if !reasons.contains(&reason) {
+            continue;
+        }
+
+        output.push_str(&format!("{heading}:\n"));
+
+        for orphan in orphans.iter().filter(|orphan| orphan.reason == reason) {
+            output.push_str(&format!("    {}\n", RefName(&orphan.name)));
+        }
+    }
+
+    output
+}
+
+/// Machine readable listing, one record per ref: name and reason (untracked or unknown-remote), separated by tab
+pub fn orphans_porcelain(orphans: &[OrphanedRef], terminator: u8) -> Vec<u8> {
+    let mut output = Vec::new();
+
+    for orphan in orphans {
+        output.extend_from_slice(&orphan.name);
+        output.push(b'\t');
+        output.extend_from_slice(orphan.reason.name().as_bytes());
+        output.push(terminator);
+    }
+
+    output
+}
+
+pub fn orphans_json(orphans: &[OrphanedRef]) -> Json {
+    let objects: Vec<String> = orphans
+        .iter()
+        .map(|orphan| {
+            format!(
+                "{{\"name\":{},\"reason\":{}}}",
+                json::string(&RefName(&orphan.name).to_string_lossy()),
+                json::string(orphan.reason.name())
+            )
+        })
+        .collect();
+
+    Json(format!("[{}]", objects.join(",")))
+}
+
+#[cfg(test)]
+fn orphans() -> Vec<OrphanedRef> {
+    vec![
+        OrphanedRef {
+            name: b"origin/old".to_vec(),
+            reason: OrphanReason::Untracked,
+        },
+        OrphanedRef {
+            name: b"renamed/main".to_vec(),
+            reason: OrphanReason::UnknownRemote,
+        },
+    ]
+}
+
+#[test]
+fn orphans_grouped_by_reason() {
+    let sut = orphans_text(
+        &orphans(),
+        &[OrphanReason::Untracked, OrphanReason::UnknownRemote],
+    );
+
+    let expected = "Remote-tracking refs no local branch tracks:\n    origin/old\n\
+                    Remote-tracking refs of remotes no longer configured:\n    renamed/main\n";
+
+    assert_eq!(sut, expected);
+
+    let sut = orphans_text(&orphans()[1..], &[OrphanReason::UnknownRemote]);
+
+    assert_eq!(
+        sut,
+        "Remote-tracking refs of remotes no longer configured:\n    renamed/main\n"
+    );
+}
+
+#[test]
+fn orphans_as_porcelain_and_json() {
+    assert_eq!(
+        orphans_porcelain(&orphans(), b'\0'),
+        b"origin/old\tuntracked\0renamed/main\tunknown-remote\0"
+    );
+
+    assert_eq!(
+        orphans_json(&orphans()).to_string(),
+        "[{\"name\":\"origin/old\",\"reason\":\"untracked\"},{\"name\":\"renamed/main\",\"reason\":\"unknown-remote\"}]"
+    );
+}
@@ -0,0 +1,256 @@
+use crate::classify::*;
+use crate::clean::{CleanupPlan, CurrentBranch};
+use crate::execute::Location;
+use crate::git::{self, Branch, BranchInfo, GitError, Head, RefName, Repository};
+use crate::json;
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+const TOP_REMOTES: usize = 5;
+
+// Upper bound in days (exclusive), JSON key and label of each age bucket
+const AGE_BUCKETS: [(u64, &str, &str); 4] = [
+    (30, "under_30_days", "Less than 30 days"),
+    (90, "30_to_90_days", "30 to 90 days"),
+    (180, "90_to_180_days", "90 to 180 days"),
+    (u64::MAX, "over_180_days", "More than 180 days"),
+];
+
+/// Counts the branches of the repository, querying git for the details git branch -vv doesn't show
+pub fn stats<'a>(
+    path: &(impl Location + ?Sized),
+    repository: &'a Repository<'a>,
+) -> Result<Stats<'a>, GitError> {
+    let infos = BranchInfo::query(&path, &repository.head)?;
+
+    // The branches clean would delete, including a gone current branch it switches away from
+    let plan = CleanupPlan::new(path, repository, None)?;
+    let switches = matches!(plan.current, Some(CurrentBranch::Switch { .. }));
+    let orphaned_commits = git::count_exclusive_commits(&path, &plan.deletions(), switches)?;
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+
+    Ok(Stats::new(repository, &infos, now, orphaned_commits))
+}
+
+/// Branch counts of a repository, rendered by the stats command
+#[derive(Debug, PartialEq, Eq)]
+pub struct Stats<'a> {
+    pub local: usize,
+    pub tracking: usize,
+    pub gone: usize,
+    pub diverged: usize,
+    pub synchronized: usize,
+    pub merged: usize,
+    // Branches per last commit age, from the most recent bucket to the oldest
+    pub ages: [usize; AGE_BUCKETS.len()],
+    pub top_remotes: Vec<(RefName<'a>, usize)>,
+    pub orphaned_commits: usize,
+}
+
+impl<'a> Stats<'a> {
+    fn new(
+        repository: &'a Repository<'a>,
+        infos: &HashMap<Vec<u8>, BranchInfo>,
+        now: u64,
+        orphaned_commits: usize,
+    ) -> Self {
+        let head = match &repository.head {
+            Head::Branch(branch) => Some(branch),
+            Head::Detached | Head::Unborn => None,
+        };
+
+        let branches: Vec<&Branch> = head.into_iter().chain(&repository.branches).collect();
+
+        let count = |filter: fn(&&Branch) -> bool| branches.iter().filter(|b| filter(b)).count();
+
+        let mut ages = [0; AGE_BUCKETS.len()];
+        let mut merged = 0;
+
+        for info in branches
+            .iter()
+            .filter_map(|b| infos.get(name_of(b).as_bytes()))
+        {
+            let days = now.saturating_sub(info.committer_timestamp) / SECONDS_PER_DAY;
+
+            // SAFETY: The last bucket has u64::MAX as upper bound, so a bucket is always found
+            let bucket = AGE_BUCKETS
+                .iter()
+                .position(|(upper_bound, _, _)| days < *upper_bound)
+                .unwrap();
+
+            ages[bucket] += 1;
+
+            if info.merged {
+                merged += 1;
+            }
+        }
+
+        Stats {
+            local: count(is_local),
+            tracking: count(is_tracked),
+            gone: count(is_gone),
+            diverged: count(is_diverged),
+            synchronized: count(is_synchronized),
+            merged,
+            ages,
+            top_remotes: top_remotes(&branches),
+            orphaned_commits,
+        }
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = format!(
+            "Branches:\n    \
+                Local:        {}\n    \
+                Tracking:     {}\n    \
+                Gone:         {}\n    \
+                Diverged:     {}\n    \
+                Synchronized: {}\n    \
+                Merged:       {}\n",
+            self.local, self.tracking, self.gone, self.diverged, self.synchronized, self.merged
+        );
+
+        text.push_str("Last commit age:\n");
+        for ((_, _, label), count) in AGE_BUCKETS.iter().zip(self.ages) {
+            text.push_str(&format!("    {label}: {count}\n"));
+        }
+
+        text.push_str("Top remotes:\n");
+        for (remote, count) in &self.top_remotes {
+            text.push_str(&format!("    {remote}: {count}\n"));
+        }
+
+        text.push_str(&format!(
+            "Commits orphaned by clean (estimate): {}\n",
+            self.orphaned_commits
+        ));
+
+        text
+    }
+
+    pub fn to_json(&self) -> String {
+        let ages: Vec<String> = AGE_BUCKETS
+            .iter()
+            .zip(self.ages)
+            .map(|((_, key, _), count)| format!("{}:{count}", json::string(key)))
+            .collect();
+
+        let remotes: Vec<String> = self
+            .top_remotes
+            .iter()
+            .map(|(remote, count)| {
+                format!(
+                    "{{\"remote\":{},\"branches\":{count}}}",
+                    json::string(&remote.to_string_lossy())
+                )
+            })
+            .collect();
+
+        format!(
+            "{{\"local\":{},\"tracking\":{},\"gone\":{},\"diverged\":{},\"synchronized\":{},\
+              \"merged\":{},\"age\":{{{}}},\"top_remotes\":[{}],\"orphaned_commits\":{}}}",
+            self.local,
+            self.tracking,
+            self.gone,
+            self.diverged,
+            self.synchronized,
+            self.merged,
+            ages.join(","),
+            remotes.join(","),
+            self.orphaned_commits
+        )
+    }
+}
+
+fn name_of<'a>(branch: &&Branch<'a>) -> RefName<'a> {
+    match branch {
+        Branch::Tracking { name, .. } | Branch::Local { name } => *name,
+    }
+}
+
+// Remotes sorted by how many branches track them, ties are broken by name to keep the output stable
+fn top_remotes<'a>(branches: &[&Branch<'a>]) -> Vec<(RefName<'a>, usize)> {
+    let mut counts = HashMap::<RefName, usize>::new();
+
+    for branch in branches {
+        if let Branch::Tracking { remote, .. } = branch {
+            *counts.entry(remote.remote_name).or_default() += 1;
+        }
+    }
+
+    let mut remotes: Vec<_> = counts.into_iter().collect();
+    remotes.sort_by(|(left, left_count), (right, right_count)| {
+        right_count.cmp(left_count).then(left.cmp(right))
+    });
+    remotes.truncate(TOP_REMOTES);
+
+    remotes
+}
+
+#[cfg(test)]
+fn info(days_ago: u64, merged: bool) -> BranchInfo {
+    BranchInfo {
+        committer_timestamp: 1000 * SECONDS_PER_DAY - days_ago * SECONDS_PER_DAY,
+        merged,
+        subject: String::new(),
+    }
+}
+
+#[test]
+fn collect_stats() {
+    let repository = git::repository! {
+        *tracking { "main", remote("main", "origin", synchronized) },
+        tracking { "gone", remote("gone", "origin", gone) },
+        tracking { "diverged", remote("diverged", "upstream", diverged(1, 2)) },
+        local("local"),
+    };
+
+    let infos = HashMap::from([
+        (b"main".to_vec(), info(1, true)),
+        (b"gone".to_vec(), info(45, true)),
+        (b"diverged".to_vec(), info(100, false)),
+        (b"local".to_vec(), info(365, false)),
+    ]);
+
+    let sut = Stats::new(&repository, &infos, 1000 * SECONDS_PER_DAY, 3);
+
+    let expected = Stats {
+        local: 1,
+        tracking: 3,
+        gone: 1,
+        diverged: 1,
+        synchronized: 1,
+        merged: 2,
+        ages: [1, 1, 1, 1],
+        top_remotes: vec![(RefName(b"origin"), 2), (RefName(b"upstream"), 1)],
+        orphaned_commits: 3,
+    };
+
+    assert_eq!(sut, expected);
+}
+
+#[test]
+fn stats_to_json() {
+    let sut = Stats {
+        local: 1,
+        tracking: 2,
+        gone: 1,
+        diverged: 0,
+        synchronized: 1,
+        merged: 1,
+        ages: [3, 0, 0, 0],
+        top_remotes: vec![(RefName(b"origin"), 2)],
+        orphaned_commits: 4,
+    };
+
+    let expected = "{\"local\":1,\"tracking\":2,\"gone\":1,\"diverged\":0,\"synchronized\":1,\
+                    \"merged\":1,\"age\":{\"under_30_days\":3,\"30_to_90_days\":0,\
+                    \"90_to_180_days\":0,\"over_180_days\":0},\
+                    \"top_remotes\":[{\"remote\":\"origin\",\"branches\":2}],\"orphaned_commits\":4}";
+
+    assert_eq!(sut.to_json(), expected);
+}
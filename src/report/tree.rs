@@ -0,0 +1,222 @@
+use crate::classify::Record;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+const INDENT: &str = "    ";
+
+/*
+    Groups records hierarchically by their /-separated name prefix
+    Every group keeps the counts of all branches below it, so that collapsed groups can still be summarized
+*/
+#[derive(Debug, Default)]
+pub struct Tree<'a> {
+    groups: BTreeMap<&'a [u8], Tree<'a>>,
+    leaves: Vec<(&'a [u8], Record<'a>)>,
+    counts: Counts,
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct Counts {
+    pub gone: usize,
+    pub diverged: usize,
+    pub local: usize,
+}
+
+impl Counts {
+    fn add(&mut self, record: &Record) {
+        match record.status {
+            "gone" => self.gone += 1,
+            "diverged" => self.diverged += 1,
+            "local" => self.local += 1,
+            _ => {}
+        }
+    }
+}
+
+impl<'a> Tree<'a> {
+    pub fn new(records: Vec<Record<'a>>) -> Self {
+        let mut tree = Tree::default();
+
+        for record in records {
+            tree.insert(record);
+        }
+
+        tree
+    }
+
+    fn insert(&mut self, record: Record<'a>) {
+        let mut node = self;
+        let mut segments = record
+            .name
+            .as_bytes()
+            .split(|byte| *byte == b'/')
+            .peekable();
+
+        while let Some(segment) = segments.next() {
+            node.counts.add(&record);
+
+            if segments.peek().is_none() {
+                node.leaves.push((segment, record));
+                return;
+            }
+
+            node = node.groups.entry(segment).or_default();
+        }
+    }
+
+    /*
+        Only max_depth levels of groups are rendered, and groups of the last level are collapsed:
+        only their name and counts are rendered
+        Branches that are not inside any group are always rendered
+    */
+    pub fn render(&self, max_depth: Option<usize>) -> String {
+        let mut output = String::new();
+        self.render_children(&mut output, 1, max_depth);
+        output
+    }
+
+    fn render_children(&self, output: &mut String, level: usize, max_depth: Option<usize>) {
+        let indent = INDENT.repeat(level);
+
+        for (name, group) in &self.groups {
+            let name = String::from_utf8_lossy(name);
+            let Counts {
+                gone,
+                diverged,
+                local,
+            } = group.counts;
+
+            let _ = writeln!(
+                output,
+                "{indent}{name}/ (gone: {gone}, diverged: {diverged}, local: {local})"
+            );
+
+            if max_depth.is_none_or(|max_depth| level < max_depth) {
+                group.render_children(output, level + 1, max_depth);
+            }
+        }
+
+        let mut leaves: Vec<_> = self.leaves.iter().collect();
+        leaves.sort_by_key(|(segment, _)| *segment);
+
+        for (segment, record) in leaves {
+            let segment = String::from_utf8_lossy(segment);
+            let head = if record.head { "*" } else { "" };
+
+            let _ = match record.status {
+                "local" => writeln!(output, "{indent}{head}{segment}"),
+                status => writeln!(
+                    output,
+                    "{indent}{head}{segment} => {}/{} [{status}]",
+                    record.remote_name, record.remote_branch
+                ),
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+use crate::git::RefName;
+
+#[cfg(test)]
+fn sample_records() -> Vec<Record<'static>> {
+    vec![
+        Record {
+            head: false,
+            name: RefName(b"team/feature/ticket-1"),
+            status: "gone",
+            remote_name: RefName(b"origin"),
+            remote_branch: RefName(b"team/feature/ticket-1"),
+        },
+        Record {
+            head: true,
+            name: RefName(b"team/fix/ticket-2"),
+            status: "diverged",
+            remote_name: RefName(b"origin"),
+            remote_branch: RefName(b"team/fix/ticket-2"),
+        },
+        Record {
+            head: false,
+            name: RefName(b"team/feature/ticket-3"),
+            status: "local",
+            remote_name: RefName(b""),
+            remote_branch: RefName(b""),
+        },
+        Record {
+            head: false,
+            name: RefName(b"main"),
+            status: "synchronized",
+            remote_name: RefName(b"origin"),
+            remote_branch: RefName(b"main"),
+        },
+    ]
+}
+
+#[test]
+fn group_counts() {
+    let sut = Tree::new(sample_records());
+
+    let team = &sut.groups[&b"team"[..]];
+
+    assert_eq!(
+        team.counts,
+        Counts {
+            gone: 1,
+            diverged: 1,
+            local: 1
+        }
+    );
+
+    assert_eq!(
+        team.groups[&b"feature"[..]].counts,
+        Counts {
+            gone: 1,
+            diverged: 0,
+            local: 1
+        }
+    );
+}
+
+#[test]
+fn render_full_tree() {
+    let sut = Tree::new(sample_records()).render(None);
+
+    let expected = "    team/ (gone: 1, diverged: 1, local: 1)\n\
+                    \x20       feature/ (gone: 1, diverged: 0, local: 1)\n\
+                    \x20           ticket-1 => origin/team/feature/ticket-1 [gone]\n\
+                    \x20           ticket-3\n\
+                    \x20       fix/ (gone: 0, diverged: 1, local: 0)\n\
+                    \x20           *ticket-2 => origin/team/fix/ticket-2 [diverged]\n\
+                    \x20   main => origin/main [synchronized]\n";
+
+    assert_eq!(sut, expected);
+}
+
+#[test]
+fn render_collapsed_tree() {
+    let sut = Tree::new(sample_records()).render(Some(1));
+
+    let expected = "    team/ (gone: 1, diverged: 1, local: 1)\n\
+                    \x20   main => origin/main [synchronized]\n";
+
+    assert_eq!(sut, expected);
+}
+
+#[test]
+fn render_one_level_of_groups() {
+    let sut = Tree::new(sample_records()).render(Some(2));
+
+    let expected = "    team/ (gone: 1, diverged: 1, local: 1)\n\
+                    \x20       feature/ (gone: 1, diverged: 0, local: 1)\n\
+                    \x20       fix/ (gone: 0, diverged: 1, local: 0)\n\
+                    \x20   main => origin/main [synchronized]\n";
+
+    assert_eq!(sut, expected);
+}
+
+#[test]
+fn render_depth_zero_collapses_every_group() {
+    let sut = Tree::new(sample_records()).render(Some(0));
+
+    assert_eq!(sut, Tree::new(sample_records()).render(Some(1)));
+}
@@ -0,0 +1,131 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/*
+    Discovery of the repositories under a directory, for running commands on many of them at once
+    A repository is a directory with a .git entry, which is a file for submodules and worktrees
+*/
+
+/// Every repository under root, including root itself, sorted by path
+///
+/// Repositories nested in other ones, like submodules, are only included when nested is set
+/// Symbolic links are not followed, so that links to parent directories can't loop forever
+pub fn repositories(root: &Path, nested: bool) -> io::Result<Vec<PathBuf>> {
+    let mut repositories = Vec::new();
+    let mut pending = vec![root.to_path_buf()];
+    let mut is_root = true;
+
+    while let Some(directory) = pending.pop() {
+        if directory.join(".git").exists() {
+            repositories.push(directory.clone());
+
+            if !nested {
+                continue;
+            }
+        }
+
+        let entries = match fs::read_dir(&directory) {
+            Ok(entries) => entries,
+            Err(error) if is_root => return Err(error),
+            // A directory we can't read can't hold a repository we could clean
+            Err(_) => continue,
+        };
+        is_root = false;
+
+        for entry in entries.flatten() {
+            let is_directory = entry.file_type().is_ok_and(|kind| kind.is_dir());
+
+            if is_directory && entry.file_name() != ".git" {
+                pending.push(entry.path());
+            }
+        }
+    }
+
+    repositories.sort();
+
+    Ok(repositories)
+}
+
+// Creates the directories under a new temporary root. Entries ending with " (file)" are files instead
+#[cfg(test)]
+fn layout(entries: &[&str]) -> PathBuf {
+    let root = std::env::temp_dir().join(format!("git-cleanup-scan-{}", rand::random::<u32>()));
+
+    for entry in entries {
+        match entry.strip_suffix(" (file)") {
+            Some(file) => {
+                let path = root.join(file);
+                fs::create_dir_all(path.parent().unwrap()).unwrap();
+                fs::write(path, "gitdir: elsewhere\n").unwrap();
+            }
+            None => fs::create_dir_all(root.join(entry)).unwrap(),
+        }
+    }
+
+    root
+}
+
+#[test]
+fn repositories_sorted_without_nested() {
+    let root = layout(&[
+        "web/.git",
+        "api/.git",
+        "api/vendor/lib/.git (file)",
+        "group/billing/.git (file)",
+        "plain/src",
+    ]);
+
+    let sut = repositories(&root, false).unwrap();
+
+    assert_eq!(
+        sut,
+        [
+            root.join("api"),
+            root.join("group/billing"),
+            root.join("web")
+        ]
+    );
+
+    fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn repositories_with_nested() {
+    let root = layout(&[
+        ".git/modules/lib/.git",
+        "api/.git",
+        "api/vendor/lib/.git (file)",
+    ]);
+
+    let sut = repositories(&root, true).unwrap();
+
+    // Nothing is looked for inside .git directories
+    assert_eq!(
+        sut,
+        [root.clone(), root.join("api"), root.join("api/vendor/lib")]
+    );
+
+    fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn root_must_be_readable() {
+    let root = std::env::temp_dir().join("git-cleanup-scan-missing");
+
+    assert!(repositories(&root, false).is_err());
+}
+
+#[test]
+#[cfg(unix)]
+fn symbolic_links_not_followed() {
+    let root = layout(&["api/.git", "links"]);
+    std::os::unix::fs::symlink(&root, root.join("links/root")).unwrap();
+    std::os::unix::fs::symlink(root.join("api"), root.join("links/api")).unwrap();
+
+    let sut = repositories(&root, true).unwrap();
+
+    assert_eq!(sut, [root.join("api")]);
+
+    fs::remove_dir_all(root).unwrap();
+}
@@ -0,0 +1,174 @@
+use crate::classify::Pattern;
+use crate::clean::Policy;
+use std::fs;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+// A manifest listing the repositories to manage together, one per line, with optional overrides:
+//     # Paths are relative to the directory of the manifest
+//     api
+//     services/billing protect=release/*,hotfix/* base=develop remote=upstream
+// Fields are separated by whitespace, so paths can't contain spaces
+
+/// The repositories of a workspace manifest, in the order they are listed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Workspace {
+    pub repositories: Vec<WorkspaceRepository>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkspaceRepository {
+    pub path: PathBuf,
+    /// The path as written in the manifest
+    pub label: String,
+    /// Protected patterns and remote, the base branch being protected too
+    pub policy: Policy,
+    /// The branch to switch to when the current branch is gone
+    pub base: Option<String>,
+}
+
+#[derive(Error, Debug)]
+pub enum WorkspaceError {
+    #[error("Error reading workspace manifest {}", path.display())]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    #[error("Invalid workspace manifest at line {line}: {reason}")]
+    Format { line: usize, reason: String },
+}
+
+impl Workspace {
+    pub fn load(file: &Path) -> Result<Workspace, WorkspaceError> {
+        let content = fs::read_to_string(file).map_err(|source| WorkspaceError::Io {
+            path: file.to_path_buf(),
+            source,
+        })?;
+
+        let directory = file.parent().unwrap_or(Path::new(""));
+
+        Workspace::parse(&content, directory)
+    }
+
+    // Relative paths are resolved from directory
+    pub fn parse(content: &str, directory: &Path) -> Result<Workspace, WorkspaceError> {
+        let mut repositories = Vec::new();
+
+        for (index, line) in content.lines().enumerate() {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let repository =
+                parse_repository(line, directory).map_err(|reason| WorkspaceError::Format {
+                    line: index + 1,
+                    reason,
+                })?;
+
+            repositories.push(repository);
+        }
+
+        Ok(Workspace { repositories })
+    }
+}
+
+fn parse_repository(line: &str, directory: &Path) -> Result<WorkspaceRepository, String> {
+    let mut fields = line.split_whitespace();
+
+    // SAFETY: blank lines were skipped, so there is at least one field
+    let label = fields.next().unwrap();
+
+    let mut repository = WorkspaceRepository {
+        path: directory.join(label),
+        label: label.to_string(),
+        policy: Policy::default(),
+        base: None,
+    };
+
+    for field in fields {
+        let (key, value) = match field.split_once('=') {
+            Some((key, value)) if !value.is_empty() => (key, value),
+            _ => return Err(format!("expected key=value, found {field}")),
+        };
+
+        match key {
+            "protect" => repository
+                .policy
+                .protected
+                .extend(value.split(',').map(Pattern::new)),
+            "base" => repository.base = Some(value.to_string()),
+            "remote" => repository.policy.remote = Some(value.as_bytes().to_vec()),
+            _ => return Err(format!("unknown override {key}")),
+        }
+    }
+
+    // Whatever the remote says, the branch everything is merged into must survive a cleanup
+    if let Some(base) = &repository.base {
+        repository
+            .policy
+            .protected
+            .push(Pattern::new(base.as_str()));
+    }
+
+    Ok(repository)
+}
+
+#[test]
+fn parse_manifest() {
+    let content = "# Team repositories\n\
+                   \n\
+                   api\n\
+                   \x20 services/billing  protect=release/*,hotfix/* base=develop remote=upstream\n";
+
+    let sut = Workspace::parse(content, Path::new("/src")).unwrap();
+
+    let expected = Workspace {
+        repositories: vec![
+            WorkspaceRepository {
+                path: PathBuf::from("/src/api"),
+                label: "api".to_string(),
+                policy: Policy::default(),
+                base: None,
+            },
+            WorkspaceRepository {
+                path: PathBuf::from("/src/services/billing"),
+                label: "services/billing".to_string(),
+                policy: Policy {
+                    protected: vec![
+                        Pattern::new("release/*"),
+                        Pattern::new("hotfix/*"),
+                        Pattern::new("develop"),
+                    ],
+                    remote: Some(b"upstream".to_vec()),
+                },
+                base: Some("develop".to_string()),
+            },
+        ],
+    };
+
+    assert_eq!(sut, expected);
+}
+
+#[test]
+fn absolute_paths_are_kept() {
+    let sut = Workspace::parse("/opt/tools\n", Path::new("/src")).unwrap();
+
+    assert_eq!(sut.repositories[0].path, PathBuf::from("/opt/tools"));
+}
+
+#[test]
+fn parse_errors() {
+    let inputs = ["api protect", "api protect=", "api owner=me"];
+
+    for input in inputs {
+        let sut = Workspace::parse(input, Path::new("/src"));
+
+        assert!(
+            matches!(sut, Err(WorkspaceError::Format { line: 1, .. })),
+            "{input}"
+        );
+    }
+}
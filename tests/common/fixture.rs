@@ -0,0 +1,312 @@
+use git_cleanup::execute::{Executor, Invocation, Waited};
+use std::collections::VecDeque;
+use std::fs;
+use std::os::unix::process::ExitStatusExt;
+use std::path::{Path, PathBuf};
+use std::process::{ExitStatus, Output};
+use std::sync::{Mutex, PoisonError};
+
+/*
+    Record and replay of executed commands, so that tests of whole flows can run without git
+
+    A recording saves every command with its output to a fixture file, and a replay serves them
+    back in the same order, failing loudly as soon as the issued commands differ from the recording
+    Directories are stored relative to a root, so that a fixture recorded in a temporary directory
+    can be replayed from any path, which is never touched
+
+    Both are executors, given to the flow through a Context, so that commands issued from the
+    worker pool are recorded and replayed too. Fixtures of such flows must be recorded with one job,
+    since with more the order of the commands changes from run to run
+
+    Fixture format, one block per command:
+        command git branch -vv
+        directory local
+        exit 0
+        stdout 12
+        <12 bytes of stdout>
+        stderr 0
+        <0 bytes of stderr>
+*/
+
+struct Session {
+    fixture: PathBuf,
+    root: PathBuf,
+    exchanges: Mutex<VecDeque<Exchange>>,
+}
+
+impl Session {
+    fn exchanges(&self) -> std::sync::MutexGuard<'_, VecDeque<Exchange>> {
+        // A panicking replay already failed the test, the exchanges left are only needed to report it
+        self.exchanges
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Exchange {
+    pub command: String,
+    pub directory: String,
+    pub status: Status,
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Status {
+    Exit(i32),
+    Signal(i32),
+}
+
+// Runs the commands, and saves them with their output to the fixture when dropped
+pub struct Recording(Session);
+
+impl Recording {
+    pub fn start(fixture: impl Into<PathBuf>, root: impl Into<PathBuf>) -> Self {
+        Recording(Session {
+            fixture: fixture.into(),
+            root: root.into(),
+            exchanges: Mutex::new(VecDeque::new()),
+        })
+    }
+}
+
+impl Executor for Recording {
+    fn run(&self, invocation: Invocation) -> std::io::Result<Waited> {
+        let directory = relative_directory(&self.0.root, invocation.directory());
+        let command = invocation.command_line().to_string();
+
+        let waited = invocation.spawn();
+
+        if let Ok(Waited::Exited(output)) = &waited {
+            self.0.exchanges().push_back(Exchange {
+                command,
+                directory,
+                status: output.status.into(),
+                stdout: output.stdout.clone(),
+                stderr: output.stderr.clone(),
+            });
+        }
+
+        waited
+    }
+}
+
+impl Drop for Recording {
+    fn drop(&mut self) {
+        let exchanges: Vec<Exchange> = std::mem::take(&mut *self.0.exchanges()).into();
+
+        // SAFETY: Fixtures are only written by tests, which must fail if the fixture can't be saved
+        fs::write(&self.0.fixture, serialize(&exchanges)).unwrap_or_else(|error| {
+            panic!("Error saving fixture {}: {error}", self.0.fixture.display())
+        });
+    }
+}
+
+// Serves the recorded output instead of running the commands, and checks that every one was issued when dropped
+pub struct Replay(Session);
+
+impl Replay {
+    pub fn start(fixture: impl Into<PathBuf>, root: impl Into<PathBuf>) -> Self {
+        let fixture = fixture.into();
+
+        // SAFETY: Fixtures are only read by tests, which must fail if the fixture is unusable
+        let content = fs::read(&fixture)
+            .unwrap_or_else(|error| panic!("Error reading fixture {}: {error}", fixture.display()));
+        let exchanges = parse(&content)
+            .unwrap_or_else(|error| panic!("Error parsing fixture {}: {error}", fixture.display()));
+
+        Self::from_exchanges(fixture, root, exchanges)
+    }
+
+    pub fn from_exchanges(
+        fixture: impl Into<PathBuf>,
+        root: impl Into<PathBuf>,
+        exchanges: Vec<Exchange>,
+    ) -> Self {
+        Replay(Session {
+            fixture: fixture.into(),
+            root: root.into(),
+            exchanges: Mutex::new(exchanges.into()),
+        })
+    }
+}
+
+impl Executor for Replay {
+    fn run(&self, invocation: Invocation) -> std::io::Result<Waited> {
+        let directory = relative_directory(&self.0.root, invocation.directory());
+        let command = invocation.command_line();
+        let fixture = self.0.fixture.display();
+
+        let Some(exchange) = self.0.exchanges().pop_front() else {
+            panic!("Replay of {fixture} has no more commands, but got: {command} (in {directory})");
+        };
+
+        if exchange.command != command || exchange.directory != directory {
+            panic!(
+                "Replay of {fixture} expected: {} (in {}), but got: {command} (in {directory})",
+                exchange.command, exchange.directory
+            );
+        }
+
+        Ok(Waited::Exited(Output {
+            status: exchange.status.into(),
+            stdout: exchange.stdout,
+            stderr: exchange.stderr,
+        }))
+    }
+}
+
+impl Drop for Replay {
+    fn drop(&mut self) {
+        // Panicking while already panicking would abort the test run and hide the original failure
+        if std::thread::panicking() {
+            return;
+        }
+
+        let exchanges = self.0.exchanges();
+
+        if let Some(exchange) = exchanges.front() {
+            panic!(
+                "Replay of {} finished with {} commands never issued, the first one being: {} (in {})",
+                self.0.fixture.display(),
+                exchanges.len(),
+                exchange.command,
+                exchange.directory
+            );
+        }
+    }
+}
+
+fn relative_directory(root: &Path, path: &Path) -> String {
+    match path.strip_prefix(root) {
+        Ok(relative) if relative.as_os_str().is_empty() => ".".to_string(),
+        Ok(relative) => relative.to_string_lossy().to_string(),
+        Err(_) => path.to_string_lossy().to_string(),
+    }
+}
+
+impl From<ExitStatus> for Status {
+    fn from(status: ExitStatus) -> Self {
+        match (status.code(), status.signal()) {
+            (Some(code), _) => Status::Exit(code),
+            (None, Some(signal)) => Status::Signal(signal),
+            (None, None) => Status::Exit(-1),
+        }
+    }
+}
+
+impl From<Status> for ExitStatus {
+    // Raw statuses are encoded like waitpid does, with the exit code in the second byte
+    fn from(status: Status) -> Self {
+        match status {
+            Status::Exit(code) => ExitStatus::from_raw((code & 0xff) << 8),
+            Status::Signal(signal) => ExitStatus::from_raw(signal & 0x7f),
+        }
+    }
+}
+
+pub fn serialize(exchanges: &[Exchange]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+
+    for exchange in exchanges {
+        let status = match exchange.status {
+            Status::Exit(code) => format!("exit {code}"),
+            Status::Signal(signal) => format!("signal {signal}"),
+        };
+
+        bytes.extend(format!("command {}\n", exchange.command).as_bytes());
+        bytes.extend(format!("directory {}\n", exchange.directory).as_bytes());
+        bytes.extend(format!("{status}\n").as_bytes());
+
+        for (name, stream) in [("stdout", &exchange.stdout), ("stderr", &exchange.stderr)] {
+            bytes.extend(format!("{name} {}\n", stream.len()).as_bytes());
+            bytes.extend(stream);
+            bytes.push(b'\n');
+        }
+    }
+
+    bytes
+}
+
+pub fn parse(bytes: &[u8]) -> Result<Vec<Exchange>, String> {
+    let mut reader = Reader { bytes, position: 0 };
+    let mut exchanges = Vec::new();
+
+    while !reader.is_empty() {
+        let command = reader.field("command")?;
+        let directory = reader.field("directory")?;
+
+        let line = reader.line()?;
+        let status = match line.split_once(' ') {
+            Some(("exit", code)) => Status::Exit(number(code)?),
+            Some(("signal", signal)) => Status::Signal(number(signal)?),
+            _ => return Err(format!("Expected exit or signal, found: {line}")),
+        };
+
+        let stdout = reader.stream("stdout")?;
+        let stderr = reader.stream("stderr")?;
+
+        exchanges.push(Exchange {
+            command,
+            directory,
+            status,
+            stdout,
+            stderr,
+        });
+    }
+
+    Ok(exchanges)
+}
+
+fn number<T: std::str::FromStr>(text: &str) -> Result<T, String> {
+    text.parse()
+        .map_err(|_| format!("Expected a number, found: {text}"))
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl Reader<'_> {
+    fn is_empty(&self) -> bool {
+        self.position >= self.bytes.len()
+    }
+
+    fn line(&mut self) -> Result<String, String> {
+        let rest = &self.bytes[self.position..];
+        let end = rest
+            .iter()
+            .position(|byte| *byte == b'\n')
+            .ok_or("Unexpected end of fixture")?;
+
+        self.position += end + 1;
+
+        String::from_utf8(rest[..end].to_vec()).map_err(|error| error.to_string())
+    }
+
+    fn field(&mut self, name: &str) -> Result<String, String> {
+        let line = self.line()?;
+
+        match line.split_once(' ') {
+            Some((field, value)) if field == name => Ok(value.to_string()),
+            _ => Err(format!("Expected {name}, found: {line}")),
+        }
+    }
+
+    // Streams are length prefixed, since they may contain anything, and followed by a newline
+    fn stream(&mut self, name: &str) -> Result<Vec<u8>, String> {
+        let length: usize = number(&self.field(name)?)?;
+        let end = self.position + length;
+
+        if end >= self.bytes.len() || self.bytes[end] != b'\n' {
+            return Err(format!("Truncated {name} in fixture"));
+        }
+
+        let stream = self.bytes[self.position..end].to_vec();
+        self.position = end + 1;
+
+        Ok(stream)
+    }
+}
@@ -0,0 +1,149 @@
+/*
+    Helpers shared by the integration tests, which only reach the library through its public API
+    Every test crate includes this module, but uses only some of the helpers
+*/
+#![allow(dead_code)]
+
+pub mod fixture;
+
+use git_cleanup::classify::{self, records};
+use git_cleanup::clean::CleanupPlan;
+use git_cleanup::execute::{Context, Location};
+use git_cleanup::git::GitQuery;
+use git_cleanup::report;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::{env, fs};
+
+// Commits need an identity, which CI runners often don't configure
+const GIT_ENVIRONMENT: [(&str, &str); 5] = [
+    ("GIT_AUTHOR_NAME", "git-cleanup"),
+    ("GIT_AUTHOR_EMAIL", "git-cleanup@example.com"),
+    ("GIT_COMMITTER_NAME", "git-cleanup"),
+    ("GIT_COMMITTER_EMAIL", "git-cleanup@example.com"),
+    ("GIT_CONFIG_NOSYSTEM", "1"),
+];
+
+// Fixtures are recorded by tests/git_integration.rs and replayed by tests/replay.rs
+pub fn fixture_path(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("fixtures")
+        .join(format!("{name}.fixture"))
+}
+
+// Returns the branches left after an automatic cleanup
+pub fn clean_flow(local: &Context) -> GitQuery {
+    let git_query = GitQuery::query(&local).unwrap();
+    let repository = git_query.to_repository().unwrap();
+
+    let plan = CleanupPlan::new(local, &repository, None).unwrap();
+    let _ = plan.run(local, |_| true);
+
+    GitQuery::query(&local).unwrap()
+}
+
+// Branches as porcelain records, which are easier to compare than the parsed repository
+pub fn branches(query: &GitQuery) -> String {
+    let repository = query.to_repository().unwrap();
+    let records = records(&repository, classify::is_any);
+
+    String::from_utf8_lossy(&report::porcelain(&records, b'\n')).into_owned()
+}
+
+/*
+    Runs a command of the repository setup, panicking when it fails
+    Git gets an identity and an empty HOME, so that neither the system nor the user configuration
+    changes the repositories the tests and the recorded fixtures are made from
+*/
+pub fn run(path: &impl Location, command: &str, args: &[impl AsRef<OsStr>]) {
+    let home = env::temp_dir().join("git-cleanup-home");
+    // SAFETY: Since this is only used for tests, it is OK to panic if an error occurred
+    fs::create_dir_all(&home).unwrap();
+
+    let output = Command::new(command)
+        .args(args)
+        .current_dir(path.directory())
+        .envs(GIT_ENVIRONMENT)
+        .env("HOME", &home)
+        .env("XDG_CONFIG_HOME", &home)
+        .output()
+        .unwrap_or_else(|error| panic!("Error running {command}: {error}"));
+
+    assert!(
+        output.status.success(),
+        "{command} {:?} failed: {}",
+        args.iter().map(AsRef::as_ref).collect::<Vec<_>>(),
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+pub struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+    pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
+        let random_dir_name = rand::random::<u32>().to_string();
+        let path = env::temp_dir().join(random_dir_name);
+        fs::create_dir(path.clone())?;
+        Ok(TempDir { path })
+    }
+
+    pub fn join<P: AsRef<Path>>(&self, path: P) -> TempDir {
+        TempDir {
+            path: self.path.join(path.as_ref()),
+        }
+    }
+
+    pub fn file_path<P: AsRef<Path>>(&self, path: P) -> PathBuf {
+        self.path.join(path.as_ref())
+    }
+}
+
+impl AsRef<Path> for TempDir {
+    fn as_ref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Location for TempDir {
+    fn directory(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir(self);
+    }
+}
+
+// Limitation: Command must receive an argument, otherwise the compiler will hit recursion limit
+#[allow(unused_macros)]
+macro_rules! sequence_execute {
+    ( $path:ident : ($command:literal, $($arg:expr),*) ) => {
+        $crate::common::run(&$path, $command, &[$($arg),*]);
+    };
+
+    ( $path:ident : $($command_and_args:tt),+ ) => {
+        $(
+            $crate::common::sequence_execute! {
+                $path:
+                    $command_and_args
+            }
+        )+
+    };
+
+    ( $($path:ident : $($command_and_args:tt),+)+ ) => {
+        $(
+            $crate::common::sequence_execute! {
+                $path:
+                    $($command_and_args),+
+            }
+        )+
+    };
+}
+
+#[allow(unused_imports)]
+pub(crate) use sequence_execute;
@@ -0,0 +1,981 @@
+#![cfg(feature = "testbin")]
+mod common;
+
+use common::fixture::{Recording, Replay};
+use common::{branches, sequence_execute, TempDir};
+use git_cleanup::classify::{self, records, Pattern};
+use git_cleanup::clean::{CleanupPlan, CurrentBranch, PlannedBranch, RemotePrunePlan};
+use git_cleanup::execute::{self, Context, ExecuteError, GitDir, System};
+use git_cleanup::git::{self, GitQuery, OrphanReason, OrphanedRef, RefName, Snapshot, Submodule};
+use git_cleanup::history;
+use git_cleanup::report;
+use git_cleanup::scan;
+use std::env;
+use std::path::Path;
+use std::process::Command;
+
+/*
+    Runs the flow against real git while recording it, then replays the recording from another root,
+    which checks that the fixture is complete. The fixture is only kept when GIT_CLEANUP_RECORD_FIXTURES is set
+*/
+fn record_flow(name: &str, root: &TempDir, flow: fn(&Context) -> GitQuery) -> GitQuery {
+    let fixture = match env::var_os("GIT_CLEANUP_RECORD_FIXTURES") {
+        Some(_) => common::fixture_path(name),
+        None => root.file_path(format!("{name}.fixture")),
+    };
+
+    let recorded = {
+        let recording = Recording::start(&fixture, root.as_ref());
+        flow(&Context::new(&root.file_path("local"), &recording))
+    };
+
+    let replay_root = Path::new("/git-cleanup-replay");
+    let replay = Replay::start(&fixture, replay_root);
+    let replayed = flow(&Context::new(&replay_root.join("local"), &replay));
+
+    assert_eq!(branches(&replayed), branches(&recorded));
+
+    recorded
+}
+
+fn commit_of(path: &Path, name: &str) -> String {
+    let output = execute::execute(path, "git", &["rev-parse", name]).unwrap();
+
+    String::from_utf8(output).unwrap().trim_end().to_string()
+}
+
+#[test]
+fn check_git_is_available() {
+    let current_dir = env::current_dir().unwrap();
+    execute::execute(&current_dir, "git", &["--version"]).unwrap();
+}
+
+#[test]
+fn error_keeps_stderr() {
+    let not_a_repository = TempDir::new().unwrap();
+    let sut = execute::execute(&not_a_repository, "git", &["branch", "-vv"]).unwrap_err();
+
+    match sut {
+        ExecuteError::NotARepository { command, stderr } => {
+            assert_eq!(command, "git branch -vv");
+            assert!(stderr.contains("not a git repository"));
+        }
+        other => panic!("Unexpected error: {other}"),
+    }
+}
+
+#[test]
+fn test_sequence_execute() {
+    let root = TempDir::new().unwrap();
+    let subdir_one = root.join("subdir_one");
+    let subdir_two = root.join("subdir_two");
+
+    sequence_execute! {
+        root:
+            ("touch", "root_file"),
+            ("mkdir", "subdir_one"),
+            ("mkdir", "subdir_two")
+
+        subdir_one:
+            ("touch", "subdir_one_file1"),
+            ("touch", "subdir_one_file2")
+
+        subdir_two:
+            ("touch", "subdir_two_file1"),
+            ("touch", "subdir_two_file2")
+    }
+
+    let paths = [
+        root.file_path("root_file"),
+        subdir_one.file_path("subdir_one_file1"),
+        subdir_one.file_path("subdir_one_file2"),
+        subdir_two.file_path("subdir_two_file1"),
+        subdir_two.file_path("subdir_two_file2"),
+    ];
+
+    for path in paths {
+        match path.try_exists() {
+            Ok(exists) => {
+                if !exists {
+                    panic!(
+                        "Error executing file_sequence!. File ({}) does not exist",
+                        path.to_string_lossy()
+                    );
+                }
+            }
+            Err(error) => panic!(
+                "Error while checking file ({}) exists: {error}",
+                path.to_string_lossy()
+            ),
+        }
+    }
+}
+
+#[test]
+fn test_query_repository() {
+    let root = TempDir::new().unwrap();
+    let remote = root.join("remote");
+    let local = root.join("local");
+
+    sequence_execute! {
+        root:
+            ("mkdir", "remote")
+
+        remote:
+            ("git", "init", "-b", "main"),
+            ("touch", ".mainfile"),
+            ("git", "add", ".mainfile"),
+            ("git", "commit", "-m", "Main commit"),
+            ("git", "checkout", "-b", "develop"),
+            ("touch", ".developfile"),
+            ("git", "add", ".developfile"),
+            ("git", "commit", "-m", "Develop commit"),
+            ("git", "checkout", "main")
+
+        root:
+            ("git", "clone", "-l", "remote", "local")
+
+        local:
+            ("git", "checkout", "main"),
+            ("git", "checkout", "develop")
+    };
+
+    let git_query = GitQuery::query(&local).unwrap();
+    let sut = branches(&git_query);
+
+    let expected = "*\tdevelop\tsynchronized\torigin\tdevelop\n\
+                    \tmain\tsynchronized\torigin\tmain\n";
+
+    assert_eq!(sut, expected);
+}
+
+#[test]
+fn test_clean() {
+    let root = TempDir::new().unwrap();
+    let remote = root.join("remote");
+    let local = root.join("local");
+
+    sequence_execute! {
+        root:
+            ("mkdir", "remote")
+
+        remote:
+            ("git", "init", "-b", "main"),
+            ("touch", ".mainfile"),
+            ("git", "add", ".mainfile"),
+            ("git", "commit", "-m", "Main commit"),
+            ("git", "checkout", "-b", "develop"),
+            ("touch", ".developfile"),
+            ("git", "add", ".developfile"),
+            ("git", "commit", "-m", "Develop commit"),
+            ("git", "checkout", "-b", "feature_branch"),
+            ("git", "checkout", "main")
+
+        root:
+            ("git", "clone", "-l", "remote", "local")
+
+        local:
+            ("git", "checkout", "main"),
+            ("git", "checkout", "develop"),
+            ("git", "checkout", "feature_branch"),
+            ("git", "checkout", "-b", "local_checkout"),
+            ("touch", ".localfile"),
+            ("git",  "add", ".localfile"),
+            ("git", "commit", "-m", "Local commit"),
+            ("git", "checkout", "develop")
+
+        remote:
+            ("git", "branch", "-D", "feature_branch")
+
+        local:
+            ("git", "fetch", "--prune")
+    };
+
+    let git_query = record_flow("clean", &root, common::clean_flow);
+    let sut = branches(&git_query);
+
+    let expected = "*\tdevelop\tsynchronized\torigin\tdevelop\n\
+                    \tlocal_checkout\tlocal\t\t\n\
+                    \tmain\tsynchronized\torigin\tmain\n";
+
+    assert_eq!(sut, expected);
+}
+
+#[test]
+fn test_clean_current_branch() {
+    let root = TempDir::new().unwrap();
+    let remote = root.join("remote");
+    let local = root.join("local");
+
+    sequence_execute! {
+        root:
+            ("mkdir", "remote")
+
+        remote:
+            ("git", "init", "-b", "main"),
+            ("touch", ".mainfile"),
+            ("git", "add", ".mainfile"),
+            ("git", "commit", "-m", "Main commit"),
+            ("git", "checkout", "-b", "feature_branch"),
+            ("git", "checkout", "main")
+
+        root:
+            ("git", "clone", "-l", "remote", "local")
+
+        local:
+            ("git", "checkout", "feature_branch")
+
+        remote:
+            ("git", "branch", "-D", "feature_branch")
+
+        local:
+            ("git", "fetch", "--prune")
+    };
+
+    let git_query = record_flow("clean_current_branch", &root, common::clean_flow);
+    let sut = branches(&git_query);
+
+    let expected = "*\tmain\tsynchronized\torigin\tmain\n";
+
+    assert_eq!(sut, expected);
+}
+
+#[test]
+fn test_stats_counts_commits_of_gone_current_branch() {
+    let root = TempDir::new().unwrap();
+    let remote = root.join("remote");
+    let local = root.join("local");
+
+    sequence_execute! {
+        root:
+            ("mkdir", "remote")
+
+        remote:
+            ("git", "init", "-b", "main"),
+            ("git", "commit", "--allow-empty", "-m", "Main commit"),
+            ("git", "branch", "feature_branch")
+
+        root:
+            ("git", "clone", "-l", "remote", "local")
+
+        local:
+            ("git", "checkout", "feature_branch"),
+            ("git", "commit", "--allow-empty", "-m", "First local commit"),
+            ("git", "commit", "--allow-empty", "-m", "Second local commit")
+
+        remote:
+            ("git", "branch", "-D", "feature_branch")
+
+        local:
+            ("git", "fetch", "--prune")
+    };
+
+    let git_query = GitQuery::query(&local).unwrap();
+    let repository = git_query.to_repository().unwrap();
+
+    let sut = report::stats(&local, &repository).unwrap();
+
+    // clean switches to main before deleting the current branch, which orphans both commits
+    assert_eq!(sut.orphaned_commits, 2);
+}
+
+#[test]
+fn test_clean_bare_repository() {
+    let root = TempDir::new().unwrap();
+    let remote = root.join("remote");
+    let local = root.join("local");
+
+    sequence_execute! {
+        root:
+            ("mkdir", "remote")
+
+        remote:
+            ("git", "init", "-b", "main"),
+            ("touch", ".mainfile"),
+            ("git", "add", ".mainfile"),
+            ("git", "commit", "-m", "Main commit"),
+            ("git", "branch", "feature_branch"),
+            ("git", "branch", "old_branch")
+
+        root:
+            ("git", "clone", "--bare", "-l", "remote", "local")
+
+        // Bare clones don't track their remote, so it is set up like in a mirror that is also pruned
+        local:
+            ("git", "config", "remote.origin.fetch", "+refs/heads/*:refs/remotes/origin/*"),
+            ("git", "fetch"),
+            ("git", "branch", "-u", "origin/main", "main"),
+            ("git", "branch", "-u", "origin/feature_branch", "feature_branch"),
+            ("git", "branch", "-u", "origin/old_branch", "old_branch"),
+            ("git", "symbolic-ref", "HEAD", "refs/heads/feature_branch")
+
+        remote:
+            ("git", "branch", "-D", "feature_branch", "old_branch")
+
+        local:
+            ("git", "fetch", "--prune")
+    };
+
+    let git_query = record_flow("clean_bare", &root, common::clean_flow);
+    let sut = branches(&git_query);
+
+    // HEAD is never moved, since it picks the branch clones check out
+    let expected = "*\tfeature_branch\tgone\torigin\tfeature_branch\n\
+                    \tmain\tsynchronized\torigin\tmain\n";
+
+    assert_eq!(sut, expected);
+}
+
+#[test]
+fn test_clean_current_branch_dirty() {
+    let root = TempDir::new().unwrap();
+    let remote = root.join("remote");
+    let local = root.join("local");
+
+    sequence_execute! {
+        root:
+            ("mkdir", "remote")
+
+        remote:
+            ("git", "init", "-b", "main"),
+            ("touch", ".mainfile"),
+            ("git", "add", ".mainfile"),
+            ("git", "commit", "-m", "Main commit"),
+            ("git", "checkout", "-b", "feature_branch"),
+            ("git", "checkout", "main")
+
+        root:
+            ("git", "clone", "-l", "remote", "local")
+
+        local:
+            ("git", "checkout", "feature_branch"),
+            ("touch", ".dirtyfile"),
+            ("git", "add", ".dirtyfile")
+
+        remote:
+            ("git", "branch", "-D", "feature_branch")
+
+        local:
+            ("git", "fetch", "--prune")
+    };
+
+    let git_query = GitQuery::query(&local).unwrap();
+    let repository = git_query.to_repository().unwrap();
+
+    let plan = CleanupPlan::new(local.as_ref(), &repository, None).unwrap();
+
+    let expected = CurrentBranch::Dirty {
+        name: RefName(b"feature_branch"),
+        target: b"main".to_vec(),
+    };
+    assert_eq!(plan.current, Some(expected));
+
+    let outcome = plan.run(local.as_ref(), |_| true);
+    assert!(outcome.deleted.is_empty());
+    assert!(outcome.error.is_none());
+
+    let git_query = GitQuery::query(&local).unwrap();
+    let sut = branches(&git_query);
+
+    let expected = "*\tfeature_branch\tgone\torigin\tfeature_branch\n\
+                    \tmain\tsynchronized\torigin\tmain\n";
+
+    assert_eq!(sut, expected);
+}
+
+#[test]
+#[cfg(unix)]
+fn test_clean_non_utf8_branch() {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    let root = TempDir::new().unwrap();
+    let remote = root.join("remote");
+    let local = root.join("local");
+    let name = OsStr::from_bytes(b"caf\xe9");
+
+    sequence_execute! {
+        root:
+            ("mkdir", "remote")
+
+        remote:
+            ("git", "init", "-b", "main"),
+            ("touch", ".mainfile"),
+            ("git", "add", ".mainfile"),
+            ("git", "commit", "-m", "Main commit")
+    };
+
+    execute::execute(&remote, "git", &[OsStr::new("branch"), name]).unwrap();
+
+    sequence_execute! {
+        root:
+            ("git", "clone", "-l", "remote", "local")
+    };
+
+    execute::execute(&local, "git", &[OsStr::new("checkout"), name]).unwrap();
+    execute::execute(
+        &remote,
+        "git",
+        &[OsStr::new("branch"), OsStr::new("-D"), name],
+    )
+    .unwrap();
+
+    sequence_execute! {
+        local:
+            ("git", "fetch", "--prune")
+    };
+
+    let git_query = common::clean_flow(&Context::new(local.as_ref(), &System));
+    let sut = branches(&git_query);
+
+    let expected = "*\tmain\tsynchronized\torigin\tmain\n";
+
+    assert_eq!(sut, expected);
+}
+
+#[test]
+fn test_snapshot_save_and_load() {
+    let root = TempDir::new().unwrap();
+    let local = root.join("local");
+
+    sequence_execute! {
+        root:
+            ("mkdir", "local")
+
+        local:
+            ("git", "init", "-b", "main"),
+            ("git", "commit", "--allow-empty", "-m", "Main commit"),
+            ("git", "branch", "feature")
+    };
+
+    // Queried on another thread, which the borrowed Repository couldn't leave
+    let path = local.as_ref().to_path_buf();
+    let snapshot = std::thread::spawn(move || Snapshot::query(&path).unwrap())
+        .join()
+        .unwrap();
+
+    let file = root.file_path("snapshot");
+    snapshot.save(&file).unwrap();
+    let sut = Snapshot::load(&file).unwrap();
+    std::fs::remove_file(file).unwrap();
+
+    assert_eq!(sut, snapshot);
+    assert_eq!(
+        report::porcelain(&records(&sut.repository(), classify::is_any), b'\n'),
+        b"\tfeature\tlocal\t\t\n*\tmain\tlocal\t\t\n"
+    );
+}
+
+#[test]
+fn test_history_shows_pruned_upstream() {
+    let root = TempDir::new().unwrap();
+    let remote = root.join("remote");
+    let local = root.join("local");
+
+    sequence_execute! {
+        root:
+            ("mkdir", "remote")
+
+        remote:
+            ("git", "init", "-b", "main"),
+            ("git", "commit", "--allow-empty", "-m", "Main commit"),
+            ("git", "branch", "feature")
+
+        root:
+            ("git", "clone", "-l", "remote", "local")
+
+        local:
+            ("git", "checkout", "feature"),
+            ("git", "checkout", "main")
+    };
+
+    let path = local.as_ref();
+    assert_eq!(history::load_state(path).unwrap(), None);
+    history::save_state(path, &Snapshot::query(&path).unwrap()).unwrap();
+
+    sequence_execute! {
+        remote:
+            ("git", "branch", "-D", "feature")
+
+        local:
+            ("git", "fetch", "--prune")
+    };
+
+    let recorded = history::load_state(path).unwrap().unwrap();
+    let current = Snapshot::query(&path).unwrap();
+    let sut = report::changes_text(&history::changes(&recorded, &current));
+
+    assert_eq!(sut, "Upstream gone:\n    feature => origin/feature\n");
+    assert!(history::state_path(path)
+        .unwrap()
+        .starts_with(path.join(".git")));
+}
+
+#[test]
+fn test_scan_repositories() {
+    let root = TempDir::new().unwrap();
+    let first = root.join("first");
+    let nested = root.join("first/nested");
+    let second = root.join("group/second");
+
+    sequence_execute! {
+        root:
+            ("mkdir", "-p", "first", "group/second", "plain")
+
+        first:
+            ("git", "init", "-b", "main"),
+            ("git", "init", "-b", "main", "nested")
+
+        second:
+            ("git", "init", "-b", "main")
+    };
+
+    let sut = scan::repositories(root.as_ref(), false).unwrap();
+    assert_eq!(sut, [first.as_ref(), second.as_ref()]);
+
+    let sut = scan::repositories(root.as_ref(), true).unwrap();
+    assert_eq!(
+        sut,
+        [
+            first.file_path(""),
+            nested.file_path(""),
+            second.file_path("")
+        ]
+    );
+}
+
+// Each record is led by the repository it belongs to, so that scripts still get one branch per line
+#[test]
+fn test_list_porcelain_of_repositories() {
+    let root = TempDir::new().unwrap();
+    let api = root.join("api");
+    let web = root.join("web");
+
+    sequence_execute! {
+        root:
+            ("mkdir", "api", "web")
+
+        api:
+            ("git", "init", "-b", "main"),
+            ("git", "commit", "--allow-empty", "-m", "Main commit"),
+            ("git", "branch", "feature")
+
+        web:
+            ("git", "init", "-b", "main"),
+            ("git", "commit", "--allow-empty", "-m", "Main commit")
+    };
+
+    let output = Command::new(env!("CARGO_BIN_EXE_git-cleanup"))
+        .arg("--recursive")
+        .arg(root.as_ref())
+        .args(["list", "--all", "--porcelain"])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "api\t\tfeature\tlocal\t\t\napi\t*\tmain\tlocal\t\t\nweb\t*\tmain\tlocal\t\t\n"
+    );
+}
+
+#[test]
+fn test_submodules() {
+    let root = TempDir::new().unwrap();
+    let library = root.join("library");
+    let top = root.join("top");
+
+    sequence_execute! {
+        root:
+            ("mkdir", "library", "top")
+
+        library:
+            ("git", "init", "-b", "main"),
+            ("git", "commit", "--allow-empty", "-m", "Library commit")
+
+        top:
+            ("git", "init", "-b", "main"),
+            ("git", "commit", "--allow-empty", "-m", "Top commit"),
+            ("git", "-c", "protocol.file.allow=always", "submodule", "add", "../library", "libs/first"),
+            ("git", "-c", "protocol.file.allow=always", "submodule", "add", "../library", "libs/second"),
+            ("git", "commit", "-m", "Add submodules"),
+            ("git", "submodule", "deinit", "-f", "libs/second")
+    };
+
+    let sut = git::submodules(&top).unwrap();
+
+    let expected = [
+        Submodule {
+            path: b"libs/first".to_vec(),
+            initialized: true,
+        },
+        Submodule {
+            path: b"libs/second".to_vec(),
+            initialized: false,
+        },
+    ];
+
+    assert_eq!(sut, expected);
+}
+
+#[test]
+fn test_fetch_prune_per_remote() {
+    let root = TempDir::new().unwrap();
+    let origin = root.join("origin");
+    let local = root.join("local");
+
+    sequence_execute! {
+        root:
+            ("mkdir", "origin")
+
+        origin:
+            ("git", "init", "-b", "main"),
+            ("git", "commit", "--allow-empty", "-m", "Main commit"),
+            ("git", "branch", "feature"),
+            ("git", "branch", "fix/login")
+
+        root:
+            ("git", "clone", "-l", "origin", "local"),
+            ("git", "clone", "--bare", "-l", "origin", "upstream.git")
+
+        local:
+            ("git", "remote", "add", "upstream", "../upstream.git"),
+            ("git", "remote", "add", "unreachable", "../missing.git"),
+            ("git", "fetch", "upstream")
+
+        origin:
+            ("git", "branch", "-D", "feature", "fix/login")
+    };
+
+    let path = local.as_ref();
+    assert_eq!(
+        git::remotes(&path).unwrap(),
+        [
+            b"origin".to_vec(),
+            b"unreachable".to_vec(),
+            b"upstream".to_vec()
+        ]
+    );
+
+    let sut = git::fetch_prune(&path, Some(RefName(b"origin"))).unwrap();
+    assert_eq!(
+        sut,
+        [b"origin/feature".to_vec(), b"origin/fix/login".to_vec()]
+    );
+
+    assert!(git::fetch_prune(&path, Some(RefName(b"unreachable"))).is_err());
+
+    let sut = git::fetch_prune(&path, Some(RefName(b"upstream"))).unwrap();
+    assert!(sut.is_empty());
+
+    let sut = git::tracking_refs(&path, None).unwrap();
+    assert_eq!(
+        sut,
+        [
+            b"origin/main".to_vec(),
+            b"upstream/feature".to_vec(),
+            b"upstream/fix/login".to_vec(),
+            b"upstream/main".to_vec()
+        ]
+    );
+}
+
+// Both remotes are fetched at once by a single git fetch, so neither fails to lock the refs of the other
+#[test]
+fn test_fetch_prune_multiple_remotes() {
+    let root = TempDir::new().unwrap();
+    let origin = root.join("origin");
+    let upstream = root.join("upstream.git");
+    let local = root.join("local");
+
+    sequence_execute! {
+        root:
+            ("mkdir", "origin")
+
+        origin:
+            ("git", "init", "-b", "main"),
+            ("git", "commit", "--allow-empty", "-m", "Main commit"),
+            ("git", "branch", "feature"),
+            ("git", "branch", "fix/login")
+
+        root:
+            ("git", "clone", "-l", "origin", "local"),
+            ("git", "clone", "--bare", "-l", "origin", "upstream.git")
+
+        local:
+            ("git", "remote", "add", "upstream", "../upstream.git"),
+            ("git", "fetch", "upstream")
+
+        origin:
+            ("git", "branch", "-D", "feature")
+
+        upstream:
+            ("git", "branch", "-D", "fix/login")
+    };
+
+    let path = local.as_ref();
+    let remotes = [RefName(b"origin"), RefName(b"upstream")];
+
+    let sut = git::fetch_prune_multiple(&path, &remotes, 2).unwrap();
+
+    assert_eq!(sut.len(), 2);
+    assert_eq!(sut[0].as_ref().unwrap(), &[b"origin/feature".to_vec()]);
+    assert_eq!(sut[1].as_ref().unwrap(), &[b"upstream/fix/login".to_vec()]);
+
+    // A remote that can't be reached fails on its own
+    sequence_execute! {
+        local:
+            ("git", "remote", "add", "unreachable", "../missing.git")
+
+        origin:
+            ("git", "branch", "-D", "fix/login")
+    };
+
+    let remotes = [
+        RefName(b"origin"),
+        RefName(b"unreachable"),
+        RefName(b"upstream"),
+    ];
+
+    let sut = git::fetch_prune_multiple(&path, &remotes, 2).unwrap();
+
+    assert_eq!(sut[0].as_ref().unwrap(), &[b"origin/fix/login".to_vec()]);
+    assert!(sut[1].is_err());
+    assert!(sut[2].as_ref().unwrap().is_empty());
+}
+
+#[test]
+fn test_prune_remote() {
+    let root = TempDir::new().unwrap();
+    let work = root.join("work");
+    let remote = root.join("remote.git");
+
+    sequence_execute! {
+        root:
+            ("git", "init", "--bare", "-b", "main", "remote.git"),
+            ("git", "clone", "remote.git", "work")
+
+        work:
+            ("git", "commit", "--allow-empty", "-m", "Main commit"),
+            ("git", "branch", "merged"),
+            ("git", "branch", "fix/merged"),
+            ("git", "branch", "release/1.0"),
+            ("git", "checkout", "-b", "unmerged"),
+            ("git", "commit", "--allow-empty", "-m", "Unmerged commit"),
+            ("git", "checkout", "main"),
+            ("git", "push", "origin", "main", "merged", "fix/merged", "release/1.0", "unmerged"),
+            ("git", "remote", "set-head", "origin", "--auto")
+    };
+
+    let path = work.as_ref();
+    let protected = [Pattern::new("release/*")];
+
+    let sut = RemotePrunePlan::new(path, RefName(b"origin"), None, 0, &protected).unwrap();
+
+    let main = commit_of(path, "origin/main");
+    let expected = RemotePrunePlan {
+        remote: b"origin".to_vec(),
+        base: b"main".to_vec(),
+        branches: vec![
+            PlannedBranch {
+                name: b"fix/merged".to_vec(),
+                commit: main.clone(),
+            },
+            PlannedBranch {
+                name: b"merged".to_vec(),
+                commit: main,
+            },
+        ],
+    };
+
+    assert_eq!(sut, expected);
+
+    // Nothing is that old yet
+    let old = RemotePrunePlan::new(path, RefName(b"origin"), None, 1, &protected).unwrap();
+    assert!(old.branches.is_empty());
+
+    let outcome = sut.run(path, 1);
+    assert_eq!(
+        outcome.deleted,
+        [b"fix/merged".to_vec(), b"merged".to_vec()]
+    );
+    assert!(outcome.failures.is_empty());
+
+    let remaining =
+        execute::execute(&remote, "git", &["branch", "--format=%(refname:short)"]).unwrap();
+    assert_eq!(remaining, b"main\nrelease/1.0\nunmerged\n");
+
+    assert_eq!(
+        git::tracking_refs(&path, Some(RefName(b"origin"))).unwrap(),
+        [
+            b"origin/main".to_vec(),
+            b"origin/release/1.0".to_vec(),
+            b"origin/unmerged".to_vec()
+        ]
+    );
+}
+
+// A branch someone pushed to after the fetch the plan was made from is refused by the remote, not deleted
+#[test]
+fn test_prune_remote_keeps_moved_branch() {
+    let root = TempDir::new().unwrap();
+    let work = root.join("work");
+    let other = root.join("other");
+    let remote = root.join("remote.git");
+
+    sequence_execute! {
+        root:
+            ("git", "init", "--bare", "-b", "main", "remote.git"),
+            ("git", "clone", "remote.git", "work")
+
+        work:
+            ("git", "commit", "--allow-empty", "-m", "Main commit"),
+            ("git", "branch", "merged"),
+            ("git", "branch", "moved"),
+            ("git", "push", "origin", "main", "merged", "moved")
+
+        root:
+            ("git", "clone", "remote.git", "other")
+    };
+
+    let path = work.as_ref();
+    let plan = RemotePrunePlan::new(path, RefName(b"origin"), Some(b"main"), 0, &[]).unwrap();
+
+    sequence_execute! {
+        other:
+            ("git", "checkout", "moved"),
+            ("git", "commit", "--allow-empty", "-m", "New commit"),
+            ("git", "push", "origin", "moved")
+    };
+
+    let outcome = plan.run(path, 10);
+
+    assert_eq!(outcome.deleted, [b"merged".to_vec()]);
+    assert_eq!(outcome.failures.len(), 1);
+    assert_eq!(outcome.failures[0].branches, [b"moved".to_vec()]);
+
+    let remaining =
+        execute::execute(&remote, "git", &["log", "-1", "--format=%s", "moved"]).unwrap();
+    assert_eq!(remaining, b"New commit\n");
+}
+
+#[test]
+fn test_orphaned_refs() {
+    let root = TempDir::new().unwrap();
+    let origin = root.join("origin");
+    let local = root.join("local");
+
+    sequence_execute! {
+        root:
+            ("mkdir", "origin")
+
+        origin:
+            ("git", "init", "-b", "main"),
+            ("git", "commit", "--allow-empty", "-m", "Main commit"),
+            ("git", "branch", "other")
+
+        root:
+            ("git", "clone", "-l", "origin", "local")
+
+        local:
+            // Left behind by a remote that was removed
+            ("git", "update-ref", "refs/remotes/removed/feature", "HEAD"),
+            // origin/HEAD still points to origin/main, which is then tracked by no local branch
+            ("git", "branch", "--unset-upstream", "main")
+    };
+
+    let path = local.as_ref();
+    let sut = git::orphaned_refs(&path).unwrap();
+
+    let expected = [
+        OrphanedRef {
+            name: b"origin/other".to_vec(),
+            reason: OrphanReason::Untracked,
+        },
+        OrphanedRef {
+            name: b"removed/feature".to_vec(),
+            reason: OrphanReason::UnknownRemote,
+        },
+    ];
+
+    assert_eq!(sut, expected);
+
+    git::delete_tracking_ref(&path, RefName(b"removed/feature")).unwrap();
+
+    assert_eq!(
+        git::tracking_refs(&path, None).unwrap(),
+        [b"origin/main".to_vec(), b"origin/other".to_vec()]
+    );
+}
+
+#[test]
+fn test_tracking_refs_of_nested_remotes() {
+    let root = TempDir::new().unwrap();
+    let origin = root.join("origin");
+    let local = root.join("local");
+
+    sequence_execute! {
+        root:
+            ("mkdir", "origin")
+
+        origin:
+            ("git", "init", "-b", "main"),
+            ("git", "commit", "--allow-empty", "-m", "Main commit"),
+            ("git", "branch", "nested")
+
+        root:
+            ("git", "clone", "-l", "origin", "local")
+
+        local:
+            ("git", "remote", "add", "origin/upstream", "../origin"),
+            ("git", "fetch", "origin/upstream")
+    };
+
+    let path = local.as_ref();
+
+    assert_eq!(
+        git::tracking_refs(&path, Some(RefName(b"origin"))).unwrap(),
+        [b"origin/main".to_vec(), b"origin/nested".to_vec()]
+    );
+    assert_eq!(
+        git::tracking_refs(&path, Some(RefName(b"origin/upstream"))).unwrap(),
+        [
+            b"origin/upstream/main".to_vec(),
+            b"origin/upstream/nested".to_vec()
+        ]
+    );
+
+    let branches = git::remote_branches(&path, RefName(b"origin"), RefName(b"main")).unwrap();
+    let names: Vec<&[u8]> = branches
+        .iter()
+        .map(|branch| branch.name.as_slice())
+        .collect();
+    assert_eq!(names, [&b"main"[..], b"nested"]);
+}
+
+#[test]
+fn test_git_dir_of_the_location() {
+    let root = TempDir::new().unwrap();
+    let first = root.join("first");
+    let second = root.join("second");
+
+    sequence_execute! {
+        root:
+            ("git", "init", "-b", "main", "first"),
+            ("git", "init", "-b", "trunk", "second")
+
+        first:
+            ("git", "commit", "--allow-empty", "-m", "First")
+
+        second:
+            ("git", "commit", "--allow-empty", "-m", "Second")
+    };
+
+    let git_dir = second.file_path(".git");
+    let sut = GitQuery::query(&Context::new(first.as_ref(), &System)).unwrap();
+    assert_eq!(branches(&sut), "*\tmain\tlocal\t\t\n");
+
+    let location = Context::new(first.as_ref(), &System).with_git_dir(GitDir::At(&git_dir));
+    let sut = GitQuery::query(&location).unwrap();
+    assert_eq!(branches(&sut), "*\ttrunk\tlocal\t\t\n");
+}
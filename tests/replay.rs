@@ -0,0 +1,168 @@
+mod common;
+
+use common::fixture::{parse, serialize, Exchange, Replay, Status};
+use common::{branches, clean_flow, fixture_path};
+use git_cleanup::execute::{self, Context};
+use std::path::Path;
+use std::process::ExitStatus;
+
+/*
+    Flows replayed from fixtures recorded against real git, so that they run fast and offline
+    The fixtures are recorded by tests/git_integration.rs, setting GIT_CLEANUP_RECORD_FIXTURES
+    to overwrite them, e.g. after changing which git commands are executed
+*/
+
+// Never touched, since every command is replayed
+const REPLAY_ROOT: &str = "/git-cleanup-replay";
+
+#[test]
+fn replay_clean() {
+    let root = Path::new(REPLAY_ROOT);
+    let replay = Replay::start(fixture_path("clean"), root);
+    let local = root.join("local");
+
+    let sut = branches(&clean_flow(&Context::new(&local, &replay)));
+
+    let expected = "*\tdevelop\tsynchronized\torigin\tdevelop\n\
+                    \tlocal_checkout\tlocal\t\t\n\
+                    \tmain\tsynchronized\torigin\tmain\n";
+
+    assert_eq!(sut, expected);
+}
+
+#[test]
+fn replay_clean_current_branch() {
+    let root = Path::new(REPLAY_ROOT);
+    let replay = Replay::start(fixture_path("clean_current_branch"), root);
+    let local = root.join("local");
+
+    let sut = branches(&clean_flow(&Context::new(&local, &replay)));
+
+    assert_eq!(sut, "*\tmain\tsynchronized\torigin\tmain\n");
+}
+
+#[test]
+fn replay_clean_bare() {
+    let root = Path::new(REPLAY_ROOT);
+    let replay = Replay::start(fixture_path("clean_bare"), root);
+    let local = root.join("local");
+
+    let sut = branches(&clean_flow(&Context::new(&local, &replay)));
+
+    let expected = "*\tfeature_branch\tgone\torigin\tfeature_branch\n\
+                    \tmain\tsynchronized\torigin\tmain\n";
+
+    assert_eq!(sut, expected);
+}
+
+fn exchange(command: &str, stdout: &[u8]) -> Exchange {
+    Exchange {
+        command: command.to_string(),
+        directory: "local".to_string(),
+        status: Status::Exit(0),
+        stdout: stdout.to_vec(),
+        stderr: Vec::new(),
+    }
+}
+
+#[test]
+fn serialize_and_parse() {
+    let exchanges = vec![
+        exchange(
+            "git branch -vv",
+            b"* main 1234567 [origin/main] Main commit\n",
+        ),
+        Exchange {
+            command: "git branch -d feature".to_string(),
+            directory: ".".to_string(),
+            status: Status::Exit(1),
+            stdout: b"no trailing newline\nstdout 3".to_vec(),
+            stderr: b"error: branch 'feature' not found.\n".to_vec(),
+        },
+    ];
+
+    let sut = parse(&serialize(&exchanges)).unwrap();
+
+    assert_eq!(sut, exchanges);
+}
+
+#[test]
+fn parse_truncated_stream() {
+    let sut = parse(b"command git status\ndirectory .\nexit 0\nstdout 10\nshort\n");
+    assert_eq!(sut, Err("Truncated stdout in fixture".to_string()));
+}
+
+#[test]
+fn status_round_trip() {
+    for status in [Status::Exit(0), Status::Exit(128), Status::Signal(9)] {
+        assert_eq!(Status::from(ExitStatus::from(status)), status);
+    }
+}
+
+#[test]
+fn replay_in_order() {
+    let root = Path::new("/replayed");
+    let local = root.join("local");
+
+    let replay = Replay::from_exchanges(
+        "in memory",
+        root,
+        vec![
+            exchange("git status", b"first"),
+            exchange("git log", b"second"),
+        ],
+    );
+    let local = Context::new(&local, &replay);
+
+    let first = execute::execute(&local, "git", &["status"]).unwrap();
+    let second = execute::execute(&local, "git", &["log"]).unwrap();
+
+    assert_eq!(
+        (first.as_slice(), second.as_slice()),
+        (&b"first"[..], &b"second"[..])
+    );
+}
+
+#[test]
+#[should_panic(expected = "expected: git status (in local), but got: git log (in local)")]
+fn replay_fails_on_different_command() {
+    let root = Path::new("/replayed");
+
+    let replay = Replay::from_exchanges("in memory", root, vec![exchange("git status", b"")]);
+    let local = root.join("local");
+
+    let _ = execute::execute(&Context::new(&local, &replay), "git", &["log"]);
+}
+
+#[test]
+#[should_panic(expected = "commands never issued, the first one being: git log (in local)")]
+fn replay_fails_on_missing_command() {
+    let root = Path::new("/replayed");
+
+    let replay = Replay::from_exchanges(
+        "in memory",
+        root,
+        vec![exchange("git status", b""), exchange("git log", b"")],
+    );
+    let local = root.join("local");
+
+    let _ = execute::execute(&Context::new(&local, &replay), "git", &["status"]);
+}
+
+#[test]
+fn replay_commands_of_other_threads() {
+    let root = Path::new("/replayed");
+    let local = root.join("local");
+
+    let replay = Replay::from_exchanges("in memory", root, vec![exchange("git status", b"ok")]);
+    let local = Context::new(&local, &replay);
+
+    let sut = std::thread::scope(|scope| {
+        scope
+            .spawn(|| execute::execute(&local, "git", &["status"]).unwrap())
+            .join()
+            .unwrap()
+    });
+
+    assert_eq!(sut, b"ok");
+}